@@ -0,0 +1,53 @@
+-- Proposals as collected from the Snapshot hub. Timestamps are stored in UTC;
+-- quorum, scores_total and votes keep the hub's numbers as text.
+CREATE TABLE IF NOT EXISTS proposals (
+    id TEXT PRIMARY KEY,
+    ipfs TEXT,
+    space JSONB,
+    "type" TEXT,
+    title TEXT,
+    body TEXT,
+    discussion TEXT,
+    author TEXT,
+    quorum TEXT NOT NULL DEFAULT '0',
+    quorum_type TEXT,
+    "start" TIMESTAMP,
+    "end" TIMESTAMP,
+    snapshot TEXT,
+    choices JSONB,
+    labels JSONB,
+    scores JSONB,
+    scores_total TEXT NOT NULL DEFAULT '0',
+    scores_state TEXT,
+    state TEXT,
+    strategies JSONB,
+    created TIMESTAMP,
+    updated TIMESTAMP,
+    votes TEXT NOT NULL DEFAULT '0',
+    privacy TEXT,
+    plugins JSONB,
+    flagged BOOLEAN
+);
+CREATE INDEX IF NOT EXISTS proposals_space_id_idx ON proposals ((space->>'id'));
+CREATE INDEX IF NOT EXISTS proposals_state_idx ON proposals (state);
+CREATE INDEX IF NOT EXISTS proposals_end_idx ON proposals ("end");
+CREATE INDEX IF NOT EXISTS proposals_created_idx ON proposals (created);
+CREATE INDEX IF NOT EXISTS proposals_updated_idx ON proposals (updated);
+
+-- LLM analyses of proposals. created_at is in Unix seconds.
+CREATE TABLE IF NOT EXISTS recommendations (
+    id BIGSERIAL PRIMARY KEY,
+    proposal_id TEXT NOT NULL,
+    technical_impact JSONB,
+    economic_consequences JSONB,
+    governance_and_decentralization JSONB,
+    advantages JSONB,
+    risks JSONB,
+    recommendation JSONB,
+    new_flag BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at BIGINT NOT NULL DEFAULT (EXTRACT(EPOCH FROM NOW()))::BIGINT,
+    CONSTRAINT recommendations_proposal_id_fkey
+        FOREIGN KEY (proposal_id) REFERENCES proposals (id)
+);
+CREATE INDEX IF NOT EXISTS recommendations_proposal_id_idx
+    ON recommendations (proposal_id, created_at DESC);
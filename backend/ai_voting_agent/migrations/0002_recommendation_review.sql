@@ -0,0 +1,7 @@
+ALTER TABLE recommendations ADD COLUMN IF NOT EXISTS approved_by TEXT;
+ALTER TABLE recommendations ADD COLUMN IF NOT EXISTS approved_at TIMESTAMP;
+ALTER TABLE recommendations ADD COLUMN IF NOT EXISTS approval_comment TEXT;
+ALTER TABLE recommendations ADD COLUMN IF NOT EXISTS escalated_at TIMESTAMP;
+ALTER TABLE recommendations ADD COLUMN IF NOT EXISTS invalidated_at TIMESTAMP;
+ALTER TABLE recommendations ADD COLUMN IF NOT EXISTS invalidation_reason TEXT;
+ALTER TABLE recommendations ADD COLUMN IF NOT EXISTS supersedes_id BIGINT;
@@ -0,0 +1,50 @@
+CREATE TABLE IF NOT EXISTS vote_actions (
+    id BIGSERIAL PRIMARY KEY,
+    proposal_id TEXT NOT NULL,
+    recommendation_id BIGINT,
+    space TEXT NOT NULL,
+    choice INTEGER NOT NULL,
+    signer_address TEXT,
+    signed_message JSONB,
+    signature TEXT,
+    hub_response JSONB,
+    status TEXT NOT NULL,
+    approval JSONB NOT NULL DEFAULT '[]'::jsonb,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS vote_actions_proposal_id_idx ON vote_actions (proposal_id);
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS attempt INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS resubmission_of BIGINT REFERENCES vote_actions (id);
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS reconcile_status TEXT;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS reconcile_details JSONB;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS reconciled_at TIMESTAMP;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS replaces_action_id BIGINT REFERENCES vote_actions (id);
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS safe_message_hash TEXT;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS identity TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS channel TEXT NOT NULL DEFAULT 'snapshot';
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS simulation JSONB;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS rationale TEXT;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS rationale_post JSONB;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS verified BOOLEAN;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS verification JSONB;
+ALTER TABLE vote_actions ADD COLUMN IF NOT EXISTS verified_at TIMESTAMP;
+
+CREATE TABLE IF NOT EXISTS vote_executions (
+    id BIGSERIAL PRIMARY KEY,
+    proposal_id TEXT NOT NULL,
+    recommendation_id BIGINT,
+    choice INTEGER,
+    approval JSONB NOT NULL DEFAULT '[]'::jsonb,
+    window_secs BIGINT,
+    jitter_secs BIGINT NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'pending',
+    vote_action_id BIGINT REFERENCES vote_actions (id),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+ALTER TABLE vote_executions ADD COLUMN IF NOT EXISTS replaces_action_id BIGINT REFERENCES vote_actions (id);
+ALTER TABLE vote_executions ADD COLUMN IF NOT EXISTS identity TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE vote_executions ADD COLUMN IF NOT EXISTS dry_run BOOLEAN NOT NULL DEFAULT FALSE;
+DROP INDEX IF EXISTS vote_executions_pending_idx;
+CREATE UNIQUE INDEX IF NOT EXISTS vote_executions_pending_identity_idx
+    ON vote_executions (proposal_id, identity) WHERE status = 'pending';
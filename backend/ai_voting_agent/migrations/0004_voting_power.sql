@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS voting_power (
+    proposal_id TEXT NOT NULL,
+    address TEXT NOT NULL,
+    vp DOUBLE PRECISION NOT NULL,
+    vp_by_strategy JSONB NOT NULL DEFAULT '[]'::jsonb,
+    source TEXT NOT NULL,
+    snapshot TEXT,
+    computed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (proposal_id, address)
+);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS delegation_actions (
+    id BIGSERIAL PRIMARY KEY,
+    identity TEXT NOT NULL,
+    address TEXT NOT NULL,
+    action TEXT NOT NULL,
+    space TEXT NOT NULL,
+    delegate TEXT,
+    requested_by TEXT NOT NULL,
+    tx_hash TEXT,
+    raw_tx TEXT,
+    status TEXT NOT NULL,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
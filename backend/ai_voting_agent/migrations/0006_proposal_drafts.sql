@@ -0,0 +1,26 @@
+CREATE TABLE IF NOT EXISTS proposal_drafts (
+    id BIGSERIAL PRIMARY KEY,
+    space TEXT NOT NULL,
+    identity TEXT,
+    proposal_type TEXT NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    discussion TEXT NOT NULL DEFAULT '',
+    choices JSONB NOT NULL,
+    start_at BIGINT NOT NULL,
+    end_at BIGINT NOT NULL,
+    snapshot BIGINT NOT NULL,
+    review JSONB,
+    status TEXT NOT NULL DEFAULT 'draft',
+    validation JSONB,
+    author TEXT,
+    signed_message JSONB,
+    signature TEXT,
+    hub_response JSONB,
+    error TEXT,
+    created_by TEXT NOT NULL,
+    submitted_by TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS proposal_drafts_space_idx ON proposal_drafts (space);
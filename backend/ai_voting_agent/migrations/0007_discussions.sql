@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS discussion_posts (
+    id BIGSERIAL PRIMARY KEY,
+    proposal_id TEXT NOT NULL,
+    topic_id BIGINT NOT NULL,
+    post_id BIGINT NOT NULL,
+    post_number INTEGER NOT NULL,
+    username TEXT NOT NULL,
+    content TEXT NOT NULL,
+    posted_at TIMESTAMP,
+    fetched_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (proposal_id, post_id)
+);
+CREATE TABLE IF NOT EXISTS discussion_summaries (
+    proposal_id TEXT PRIMARY KEY,
+    topic_id BIGINT NOT NULL,
+    post_count INTEGER NOT NULL,
+    summary TEXT NOT NULL,
+    sentiment JSONB NOT NULL,
+    concerns JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,8 @@
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS ipfs_status TEXT;
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS ipfs_checked_hash TEXT;
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS ipfs_signer TEXT;
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS ipfs_signature TEXT;
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS ipfs_mismatches JSONB;
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS ipfs_envelope JSONB;
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS ipfs_checked_at TIMESTAMP;
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS verified BOOLEAN;
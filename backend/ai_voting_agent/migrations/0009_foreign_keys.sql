@@ -0,0 +1,30 @@
+-- Databases created before migrations have the tables without these keys.
+-- They are added NOT VALID so existing rows are not checked, only new ones.
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'recommendations_proposal_id_fkey') THEN
+        ALTER TABLE recommendations ADD CONSTRAINT recommendations_proposal_id_fkey
+            FOREIGN KEY (proposal_id) REFERENCES proposals (id) NOT VALID;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'recommendations_supersedes_id_fkey') THEN
+        ALTER TABLE recommendations ADD CONSTRAINT recommendations_supersedes_id_fkey
+            FOREIGN KEY (supersedes_id) REFERENCES recommendations (id) NOT VALID;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'vote_actions_recommendation_id_fkey') THEN
+        ALTER TABLE vote_actions ADD CONSTRAINT vote_actions_recommendation_id_fkey
+            FOREIGN KEY (recommendation_id) REFERENCES recommendations (id) NOT VALID;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'vote_executions_recommendation_id_fkey') THEN
+        ALTER TABLE vote_executions ADD CONSTRAINT vote_executions_recommendation_id_fkey
+            FOREIGN KEY (recommendation_id) REFERENCES recommendations (id) NOT VALID;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'discussion_summaries_proposal_id_fkey') THEN
+        ALTER TABLE discussion_summaries ADD CONSTRAINT discussion_summaries_proposal_id_fkey
+            FOREIGN KEY (proposal_id) REFERENCES proposals (id) NOT VALID;
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'discussion_posts_proposal_id_fkey') THEN
+        ALTER TABLE discussion_posts ADD CONSTRAINT discussion_posts_proposal_id_fkey
+            FOREIGN KEY (proposal_id) REFERENCES proposals (id) NOT VALID;
+    END IF;
+END
+$$;
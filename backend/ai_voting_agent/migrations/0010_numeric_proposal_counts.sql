@@ -0,0 +1,16 @@
+-- quorum, scores_total and votes become numbers so they can be filtered and
+-- sorted in SQL.
+ALTER TABLE proposals ALTER COLUMN quorum DROP DEFAULT;
+ALTER TABLE proposals ALTER COLUMN quorum TYPE DOUBLE PRECISION
+    USING COALESCE(NULLIF(TRIM(quorum), ''), '0')::DOUBLE PRECISION;
+ALTER TABLE proposals ALTER COLUMN quorum SET DEFAULT 0;
+
+ALTER TABLE proposals ALTER COLUMN scores_total DROP DEFAULT;
+ALTER TABLE proposals ALTER COLUMN scores_total TYPE DOUBLE PRECISION
+    USING COALESCE(NULLIF(TRIM(scores_total), ''), '0')::DOUBLE PRECISION;
+ALTER TABLE proposals ALTER COLUMN scores_total SET DEFAULT 0;
+
+ALTER TABLE proposals ALTER COLUMN votes DROP DEFAULT;
+ALTER TABLE proposals ALTER COLUMN votes TYPE BIGINT
+    USING COALESCE(NULLIF(TRIM(votes), ''), '0')::NUMERIC::BIGINT;
+ALTER TABLE proposals ALTER COLUMN votes SET DEFAULT 0;
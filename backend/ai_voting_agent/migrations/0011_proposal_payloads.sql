@@ -0,0 +1,10 @@
+-- Raw hub objects of every proposal version, keyed by the hub's `updated`
+-- (or `created` for never edited proposals) in Unix seconds.
+CREATE TABLE IF NOT EXISTS proposal_payloads (
+    id BIGSERIAL PRIMARY KEY,
+    proposal_id TEXT NOT NULL REFERENCES proposals (id),
+    version BIGINT NOT NULL,
+    raw_payload JSONB NOT NULL,
+    fetched_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (proposal_id, version)
+);
@@ -0,0 +1,15 @@
+-- Every change of a known proposal seen by the collector, as a field-level diff.
+-- recommendation_id is the recommendation that was current when the edit was
+-- seen; flagged marks material edits made after it.
+CREATE TABLE IF NOT EXISTS proposal_revisions (
+    id BIGSERIAL PRIMARY KEY,
+    proposal_id TEXT NOT NULL REFERENCES proposals (id),
+    hub_updated BIGINT,
+    changes JSONB NOT NULL,
+    material BOOLEAN NOT NULL,
+    recommendation_id BIGINT REFERENCES recommendations (id),
+    flagged BOOLEAN NOT NULL DEFAULT FALSE,
+    detected_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS proposal_revisions_proposal_id_idx ON proposal_revisions (proposal_id, id);
+CREATE INDEX IF NOT EXISTS proposal_revisions_flagged_idx ON proposal_revisions (flagged) WHERE flagged;
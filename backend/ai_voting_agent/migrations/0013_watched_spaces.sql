@@ -0,0 +1,8 @@
+-- Spaces the collector syncs. backfilled_at stays NULL until the history of a
+-- newly added space has been pulled.
+CREATE TABLE IF NOT EXISTS watched_spaces (
+    space_id TEXT PRIMARY KEY,
+    added_by TEXT NOT NULL,
+    added_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    backfilled_at TIMESTAMP
+);
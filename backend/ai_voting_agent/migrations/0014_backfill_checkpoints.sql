@@ -0,0 +1,14 @@
+-- Progress of `backfill` runs over a window of a space, so an interrupted run
+-- resumes from `cursor`, the `created` of the last stored proposal.
+CREATE TABLE IF NOT EXISTS backfill_checkpoints (
+    space_id TEXT NOT NULL,
+    from_ts BIGINT NOT NULL,
+    to_ts BIGINT NOT NULL,
+    cursor BIGINT NOT NULL,
+    proposals BIGINT NOT NULL DEFAULT 0,
+    batches BIGINT NOT NULL DEFAULT 0,
+    started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP,
+    PRIMARY KEY (space_id, from_ts, to_ts)
+);
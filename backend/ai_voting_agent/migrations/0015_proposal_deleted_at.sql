@@ -0,0 +1,3 @@
+-- Proposals deleted on the hub keep their row, with state 'deleted' and the
+-- time the deletion was seen.
+ALTER TABLE proposals ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP;
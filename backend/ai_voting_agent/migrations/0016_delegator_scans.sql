@@ -0,0 +1,13 @@
+-- Accounts seen delegating to one of our identities in the DelegateRegistry,
+-- per network, space and delegate, with the block range already scanned for
+-- SetDelegate logs so later evaluations only scan the new blocks.
+CREATE TABLE IF NOT EXISTS delegator_scans (
+    network TEXT NOT NULL,
+    space TEXT NOT NULL,
+    delegate TEXT NOT NULL,
+    start_block BIGINT NOT NULL,
+    scanned_to BIGINT NOT NULL,
+    delegators TEXT[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (network, space, delegate)
+);
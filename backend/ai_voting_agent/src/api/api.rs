@@ -1,20 +1,53 @@
 use std::sync::Arc;
 
-use actix_web::{web, HttpResponse, Responder};
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
 use log::{error, info};
+use reqwest::Client as HttpClient;
+use subtle::ConstantTimeEq;
 use serde::Deserialize;
+use serde_json::json;
 use tokio_postgres::NoTls;
 
 use crate::{
-    proposal_snapchot::repository::{
-        get_proposals_by_id, get_proposals_by_space_id, get_spaces_vec,
+    config::config::Config,
+    delegation::{
+        delegation::{
+            change_delegation, get_identity_delegations, DelegationOrder, DELEGATION_CLEAR,
+            DELEGATION_SET, DELEGATION_SIMULATED, DELEGATION_SENT,
+        },
+        repository::get_delegation_actions,
     },
-    recommendation::{
-        ai::get_analysis_response,
-        repository::{get_recommendation_by_id, save_recommendation, get_new_recommendation},
+    draft::{
+        draft::{review_draft, submit_draft},
+        repository::{
+            create_draft, get_draft, get_drafts, ProposalDraft, DRAFT_OPEN, DRAFT_REJECTED,
+            DRAFT_SIMULATED, DRAFT_SUBMITTED,
+        },
     },
+    forum::{ingestion::ingest_discussion, repository::get_discussion_posts},
+    identity::identity::IdentityRegistry,
+    ipfs::ipfs::verify_proposal,
+    proposal_snapchot::{
+        hub::HubClient,
+        revision::get_proposal_revisions,
+        webhook::{handle_event, SnapshotEvent},
+        watchlist::{add_watched_space, get_watched_spaces, remove_watched_space},
+    },
+    recommendation::{ai::get_analysis_response, store::Recommendation},
+    store::store::Stores,
+    voting::{
+        executor::{execute_vote, identities_for_proposal, VoteOrder},
+        governor::{cast_governor_vote, GovernorVoteOrder},
+        rationale::publish_vote_rationale,
+        repository::{
+            get_vote_executions, get_vote_history, STATUS_AWAITING_SIGNATURES, STATUS_SIMULATED,
+            STATUS_SUBMITTED,
+        },
+        schedule::approve_and_schedule,
+    },
+    voting_power::power::{ensure_voting_power, get_governor_voting_power},
 };
 
 #[derive(Deserialize)]
@@ -22,9 +55,131 @@ pub struct ProposalsQueryParams {
     pub date: String,
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteRequest {
+    /// 1-based choice; resolved from the latest recommendation when omitted.
+    pub choice: Option<u32>,
+    pub approved_by: String,
+    pub comment: Option<String>,
+    /// Vote only as this identity instead of every identity of the space.
+    pub identity: Option<String>,
+    /// Sign and validate the vote without sending it.
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRequest {
+    /// 1-based choice; resolved from the recommendation at execution when omitted.
+    pub choice: Option<u32>,
+    pub approved_by: String,
+    pub comment: Option<String>,
+    /// Simulate the scheduled votes instead of sending them.
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GovernorVoteRequest {
+    /// 0 = against, 1 = for, 2 = abstain.
+    pub support: u8,
+    pub reason: Option<String>,
+    pub approved_by: String,
+    pub comment: Option<String>,
+    pub identity: Option<String>,
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct VoteExecutionsParams {
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VotingPowerParams {
+    pub refresh: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct VoteHistoryParams {
+    pub proposal_id: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct DelegationsParams {
+    pub space: String,
+}
+
+#[derive(Deserialize)]
+pub struct DelegationHistoryParams {
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DelegationRequest {
+    /// Snapshot space id; empty or omitted for the global delegation.
+    pub space: Option<String>,
+    /// Required when setting a delegate.
+    pub delegate: Option<String>,
+    pub requested_by: String,
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedSpaceRequest {
+    pub space: String,
+    pub requested_by: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DraftRequest {
+    pub space: String,
+    /// Author of the proposal; the first identity of the space when omitted.
+    pub identity: Option<String>,
+    /// Snapshot voting type; `single-choice` when omitted.
+    #[serde(rename = "type")]
+    pub proposal_type: Option<String>,
+    pub title: String,
+    pub body: String,
+    pub discussion: Option<String>,
+    pub choices: Vec<String>,
+    /// Unix seconds.
+    pub start: i64,
+    /// Unix seconds.
+    pub end: i64,
+    pub snapshot: i64,
+    pub created_by: String,
+    /// Have the LLM review the draft right away.
+    pub review: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DraftSubmitRequest {
+    pub submitted_by: String,
+    /// Sign and check the proposal without sending it.
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Deserialize)]
+pub struct DraftsParams {
+    pub space: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub db_client: Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    pub stores: Stores,
+    pub config: Config,
+    pub registry: Arc<IdentityRegistry>,
+    pub hub: Arc<HubClient>,
+    /// Shared client for outbound HTTP calls, see `http_client::build_http_client`.
+    pub http: HttpClient,
 }
 
 pub async fn get_proposals(
@@ -32,8 +187,11 @@ pub async fn get_proposals(
     path: web::Path<String>,
 ) -> impl Responder {
     let space_id = path.into_inner();
-    match get_proposals_by_space_id(&app_state.db_client, &space_id).await {
-        Ok(json_value) => HttpResponse::Ok().json(json_value),
+    match app_state.stores.proposals.active_in_space(&space_id).await {
+        Ok(proposals) => {
+            let proposals: Vec<_> = proposals.iter().map(|proposal| proposal.to_json()).collect();
+            HttpResponse::Ok().json(proposals)
+        }
         Err(err) => {
             eprintln!("Error fetching proposals: {}", err);
             HttpResponse::InternalServerError().body(err.to_string())
@@ -42,8 +200,11 @@ pub async fn get_proposals(
 }
 
 pub async fn get_spaces(app_state: web::Data<AppState>) -> impl Responder {
-    match get_spaces_vec(&app_state.db_client).await {
-        Ok(json_value) => HttpResponse::Ok().json(json_value),
+    match app_state.stores.proposals.spaces().await {
+        Ok(spaces) => {
+            let spaces: Vec<_> = spaces.iter().map(|space| space.to_json()).collect();
+            HttpResponse::Ok().json(spaces)
+        }
         Err(err) => {
             eprintln!("Error fetching spaces: {}", err);
             HttpResponse::InternalServerError().body(err.to_string())
@@ -57,9 +218,10 @@ pub async fn get_recommendation(
 ) -> impl Responder {
     let proposal_id = path.into_inner();
     info!("proposal_id: {:?}", proposal_id);
-    let recommendation = get_recommendation_by_id(&app_state.db_client, &proposal_id).await;
+    let recommendation = app_state.stores.recommendations.latest(&proposal_id).await;
     match recommendation {
-        Ok(json_value) => HttpResponse::Ok().json(json_value),
+        Ok(Some(recommendation)) => HttpResponse::Ok().json(recommendation.to_json()),
+        Ok(None) => HttpResponse::NotFound().body("Recommendation not found"),
         Err(err) => {
             eprintln!("Error fetching recommendation: {}", err);
             HttpResponse::InternalServerError().body(err.to_string())
@@ -71,9 +233,13 @@ pub async fn get_recommendation(
 pub async fn get_prop_and_rec(
     app_state: web::Data<AppState>,
 ) -> impl Responder {
-    let recommendation = get_new_recommendation(&app_state.db_client).await;
+    let recommendation = app_state.stores.recommendations.newest_flagged().await;
     match recommendation {
-        Ok(json_value) => HttpResponse::Ok().json(json_value),
+        Ok(Some(recommendation)) => HttpResponse::Ok().json(json!({
+            "proposalId": recommendation.proposal_id,
+            "voteOption": recommendation.recommendation,
+        })),
+        Ok(None) => HttpResponse::NotFound().body("Recommendation not found"),
         Err(err) => {
             eprintln!("Error fetching recommendation: {}", err);
             HttpResponse::InternalServerError().body(err.to_string())
@@ -86,15 +252,36 @@ pub async fn update_recommendation(
     path: web::Path<String>,
 ) -> impl Responder {
     let proposal_id = path.into_inner();
-    let proposal_json = get_proposals_by_id(&app_state.db_client, &proposal_id).await;
+    let proposal_json = app_state.stores.proposals.get(&proposal_id).await;
 
-    if let Ok(proposal) = proposal_json {
+    if let Ok(Some(proposal)) = proposal_json {
+        let proposal = proposal.to_json();
         println!("proposal_json: {:?}", proposal);
-        let recommendation = get_analysis_response(&proposal.to_string()).await;
+        match verify_proposal(&app_state.db_client, &app_state.config, &app_state.http, &proposal).await {
+            Ok(Some(check)) if check.is_tampered() => {
+                error!("ALERT: proposal {} differs from its IPFS pin", proposal_id);
+                return HttpResponse::Conflict().json(check);
+            }
+            Ok(_) => {}
+            Err(err) => {
+                return HttpResponse::BadGateway()
+                    .body(format!("Could not check the IPFS pin: {}", err));
+            }
+        }
+        let discussion = ingest_discussion(&app_state.db_client, &app_state.config, &app_state.http, &proposal)
+            .await
+            .unwrap_or_else(|err| {
+                error!("Could not read the discussion of {}: {}", proposal_id, err);
+                None
+            });
+        let recommendation = get_analysis_response(&json!([proposal]).to_string(), discussion.as_ref()).await;
         match recommendation {
             Ok(recommendation) => {
-                let _ =
-                    save_recommendation(&app_state.db_client, &proposal_id, &recommendation).await;
+                let _ = app_state
+                    .stores
+                    .recommendations
+                    .save(&Recommendation::from_analysis(&proposal_id, &recommendation))
+                    .await;
                 HttpResponse::Ok().body(format!("Recommendation: {}", recommendation))
             }
             Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
@@ -105,8 +292,726 @@ pub async fn update_recommendation(
     }
 }
 
-pub async fn post_vote(path: web::Path<String>) -> impl Responder {
+/// Votes right away as the proposal's identities. Admin only, dry runs
+/// included: a simulated vote is still signed with our key.
+pub async fn post_vote(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<VoteRequest>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
     let proposal_id = path.into_inner();
+    let request = body.into_inner();
+    let approval = json!([{
+        "approvedBy": request.approved_by,
+        "approvedAt": chrono::Utc::now().timestamp(),
+        "comment": request.comment,
+    }]);
+
+    let identities =
+        match identities_for_proposal(&app_state.stores, &app_state.registry, &proposal_id).await {
+            Ok(identities) => identities,
+            Err(err) => {
+                error!("Error resolving identities for proposal {}: {}", proposal_id, err);
+                return HttpResponse::InternalServerError().body(err.to_string());
+            }
+        };
+    let identities: Vec<_> = identities
+        .into_iter()
+        .filter(|identity| request.identity.as_ref().is_none_or(|name| &identity.name == name))
+        .collect();
+    if identities.is_empty() {
+        return HttpResponse::BadRequest().body("No voting identity for this proposal");
+    }
 
-    HttpResponse::Ok().body(format!("Voting for proposal: {}", proposal_id))
+    let mut executions = Vec::with_capacity(identities.len());
+    for identity in identities {
+        let mut order = VoteOrder::new(&proposal_id, request.choice, approval.clone());
+        order.dry_run = request.dry_run.unwrap_or(false);
+        match execute_vote(&app_state.stores, &app_state.config, &app_state.http, &identity, order).await {
+            Ok(execution) => executions.push(execution),
+            Err(err) => {
+                error!("Error voting for proposal {} as {}: {}", proposal_id, identity.name, err);
+                return HttpResponse::InternalServerError().body(err.to_string());
+            }
+        }
+    }
+    if executions
+        .iter()
+        .all(|e| e.status == STATUS_SUBMITTED || e.status == STATUS_SIMULATED)
+    {
+        HttpResponse::Ok().json(executions)
+    } else if executions
+        .iter()
+        .all(|e| e.status == STATUS_SUBMITTED || e.status == STATUS_AWAITING_SIGNATURES)
+    {
+        HttpResponse::Accepted().json(executions)
+    } else {
+        HttpResponse::BadGateway().json(executions)
+    }
+}
+
+pub async fn get_votes_history(
+    app_state: web::Data<AppState>,
+    query: web::Query<VoteHistoryParams>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(100);
+    match get_vote_history(&app_state.db_client, query.proposal_id.as_ref(), limit).await {
+        Ok(json_value) => HttpResponse::Ok().json(json_value),
+        Err(err) => {
+            eprintln!("Error fetching vote history: {}", err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+pub async fn approve_recommendation(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<ApprovalRequest>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    let proposal_id = path.into_inner();
+    let request = body.into_inner();
+    let scheduled = approve_and_schedule(
+        &app_state.stores,
+        &app_state.config,
+        &app_state.registry,
+        &proposal_id,
+        request.choice,
+        &request.approved_by,
+        request.comment.as_ref(),
+        request.dry_run.unwrap_or(false),
+    )
+    .await;
+    match scheduled {
+        Ok(execution_ids) => HttpResponse::Ok().json(json!({ "executionIds": execution_ids })),
+        Err(err) => {
+            error!("Error approving recommendation for {}: {}", proposal_id, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+pub async fn get_votes_scheduled(
+    app_state: web::Data<AppState>,
+    query: web::Query<VoteExecutionsParams>,
+) -> impl Responder {
+    match get_vote_executions(&app_state.db_client, query.status.as_ref()).await {
+        Ok(json_value) => HttpResponse::Ok().json(json_value),
+        Err(err) => {
+            eprintln!("Error fetching scheduled votes: {}", err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+pub async fn get_voting_power(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+    query: web::Query<VotingPowerParams>,
+) -> impl Responder {
+    let proposal_id = path.into_inner();
+    let proposal = match app_state.stores.proposals.get(&proposal_id).await {
+        Ok(proposal) => proposal.map(|proposal| proposal.to_json()),
+        Err(err) => {
+            eprintln!("Error fetching proposal: {}", err);
+            return HttpResponse::InternalServerError().body(err.to_string());
+        }
+    };
+    let Some(proposal) = proposal else {
+        return HttpResponse::NotFound().body("Proposal not found");
+    };
+    let refresh = query.refresh.unwrap_or(false);
+    let space = proposal["space"]["id"].as_str().unwrap_or_default();
+    let mut powers = Vec::new();
+    for identity in app_state.registry.for_space(space) {
+        match ensure_voting_power(
+            &app_state.db_client,
+            &app_state.config,
+            &app_state.http,
+            identity.address,
+            &proposal,
+            refresh,
+        ).await {
+            Ok(power) => powers.push(json!({ "identity": identity.name, "votingPower": power })),
+            Err(err) => {
+                error!("Error computing voting power of {} for {}: {}", identity.name, proposal_id, err);
+                return HttpResponse::InternalServerError().body(err.to_string());
+            }
+        }
+    }
+    HttpResponse::Ok().json(powers)
+}
+
+pub async fn get_governor_voting_power_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let proposal_id = path.into_inner();
+    let Ok(onchain_id) = ethers::types::U256::from_dec_str(&proposal_id) else {
+        return HttpResponse::BadRequest().body("Governor proposal id must be a decimal number");
+    };
+    let config = &app_state.config;
+    let governor = &config.dao_contract_address;
+    let network = app_state.registry.governor_network(governor);
+    let mut powers = Vec::new();
+    for identity in app_state.registry.for_governor(governor) {
+        match get_governor_voting_power(config, network, governor, identity.address, onchain_id).await {
+            Ok(power) => powers.push(json!({ "identity": identity.name, "votingPower": power })),
+            Err(err) => {
+                error!("Error computing governor voting power for {}: {}", proposal_id, err);
+                return HttpResponse::InternalServerError().body(err.to_string());
+            }
+        }
+    }
+    HttpResponse::Ok().json(powers)
+}
+
+pub async fn post_governor_vote(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<GovernorVoteRequest>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    let proposal_id = path.into_inner();
+    let Ok(onchain_id) = ethers::types::U256::from_dec_str(&proposal_id) else {
+        return HttpResponse::BadRequest().body("Governor proposal id must be a decimal number");
+    };
+    let request = body.into_inner();
+    if request.support > 2 {
+        return HttpResponse::BadRequest().body("support must be 0, 1 or 2");
+    }
+    let governor = &app_state.config.dao_contract_address;
+    let identities: Vec<_> = app_state
+        .registry
+        .for_governor(governor)
+        .into_iter()
+        .filter(|identity| identity.safe.is_none())
+        .filter(|identity| request.identity.as_ref().is_none_or(|name| &identity.name == name))
+        .collect();
+    if identities.is_empty() {
+        return HttpResponse::BadRequest().body("No voting identity for this governor");
+    }
+    let approval = json!([{
+        "approvedBy": request.approved_by,
+        "approvedAt": chrono::Utc::now().timestamp(),
+        "comment": request.comment,
+    }]);
+
+    let mut executions = Vec::with_capacity(identities.len());
+    for identity in identities {
+        let order = GovernorVoteOrder {
+            governor: governor.clone(),
+            network: app_state.registry.governor_network(governor).to_string(),
+            proposal_id: onchain_id,
+            support: request.support,
+            reason: request.reason.clone().unwrap_or_default(),
+            approval: approval.clone(),
+            dry_run: request.dry_run.unwrap_or(false),
+        };
+        match cast_governor_vote(&app_state.stores, &app_state.config, &identity, order).await {
+            Ok(execution) => executions.push(execution),
+            Err(err) => {
+                error!("Error voting on governor proposal {} as {}: {}", proposal_id, identity.name, err);
+                return HttpResponse::InternalServerError().body(err.to_string());
+            }
+        }
+    }
+    if executions
+        .iter()
+        .all(|e| e.status == STATUS_SUBMITTED || e.status == STATUS_SIMULATED)
+    {
+        HttpResponse::Ok().json(executions)
+    } else {
+        HttpResponse::BadGateway().json(executions)
+    }
+}
+
+pub async fn get_identities(app_state: web::Data<AppState>) -> impl Responder {
+    let identities: Vec<_> = app_state
+        .registry
+        .all()
+        .iter()
+        .map(|identity| identity.summary())
+        .collect();
+    HttpResponse::Ok().json(identities)
+}
+
+/// Compares a presented secret with the configured one in constant time, so
+/// response timing does not reveal how much of it matched.
+fn secret_matches(provided: Option<&str>, secret: &str) -> bool {
+    provided.is_some_and(|provided| bool::from(provided.as_bytes().ct_eq(secret.as_bytes())))
+}
+
+/// Checks the `Authorization: Bearer <ADMIN_API_TOKEN>` header of admin
+/// endpoints. Admin endpoints are disabled when no token is configured.
+fn check_admin(app_state: &AppState, req: &HttpRequest) -> Option<HttpResponse> {
+    let Some(token) = &app_state.config.admin_api_token else {
+        return Some(HttpResponse::Forbidden().body("Admin API is disabled"));
+    };
+    let provided = req
+        .headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !secret_matches(provided, token) {
+        return Some(HttpResponse::Unauthorized().body("Invalid admin token"));
+    }
+    None
+}
+
+pub async fn get_delegations(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<DelegationsParams>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    let mut delegations = Vec::new();
+    for identity in app_state.registry.all() {
+        match get_identity_delegations(
+            &app_state.db_client,
+            &app_state.config,
+            &app_state.http,
+            identity,
+            &query.space,
+        )
+        .await
+        {
+            Ok(entry) => delegations.push(entry),
+            Err(err) => {
+                error!("Error reading delegations of {} for {}: {}", identity.name, query.space, err);
+                return HttpResponse::InternalServerError().body(err.to_string());
+            }
+        }
+    }
+    HttpResponse::Ok().json(delegations)
+}
+
+pub async fn get_delegation_history(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    query: web::Query<DelegationHistoryParams>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    match get_delegation_actions(&app_state.db_client, query.limit.unwrap_or(50)).await {
+        Ok(actions) => HttpResponse::Ok().json(actions),
+        Err(err) => {
+            error!("Error fetching delegation history: {}", err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+async fn delegation_action(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    identity_name: String,
+    action: &str,
+    request: DelegationRequest,
+) -> HttpResponse {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    let Some(identity) = app_state.registry.get(&identity_name) else {
+        return HttpResponse::NotFound().body(format!("Unknown identity {}", identity_name));
+    };
+    if action == DELEGATION_SET && request.delegate.is_none() {
+        return HttpResponse::BadRequest().body("delegate is required");
+    }
+    info!(
+        "{} requested delegation {} for {} in '{}'",
+        request.requested_by,
+        action,
+        identity.name,
+        request.space.as_deref().unwrap_or("")
+    );
+    let order = DelegationOrder {
+        action: action.to_string(),
+        space: request.space.unwrap_or_default(),
+        delegate: request.delegate,
+        requested_by: request.requested_by,
+        dry_run: request.dry_run.unwrap_or(false),
+    };
+    match change_delegation(&app_state.db_client, &app_state.config, &identity, order).await {
+        Ok(result) => {
+            let status = result["status"].as_str().unwrap_or_default();
+            if status == DELEGATION_SENT || status == DELEGATION_SIMULATED {
+                HttpResponse::Ok().json(result)
+            } else {
+                HttpResponse::BadGateway().json(result)
+            }
+        }
+        Err(err) => {
+            error!("Error changing delegation of {}: {}", identity.name, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+pub async fn set_delegation(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<DelegationRequest>,
+) -> impl Responder {
+    delegation_action(app_state, req, path.into_inner(), DELEGATION_SET, body.into_inner()).await
+}
+
+pub async fn clear_delegation(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: web::Json<DelegationRequest>,
+) -> impl Responder {
+    delegation_action(app_state, req, path.into_inner(), DELEGATION_CLEAR, body.into_inner()).await
+}
+
+/// Receives Snapshot webhook deliveries and applies each proposal event right
+/// away. Deliveries must carry `SNAPSHOT_WEBHOOK_SECRET` in `Authentication`.
+pub async fn post_snapshot_webhook(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<SnapshotEvent>,
+) -> impl Responder {
+    let Some(secret) = &app_state.config.snapshot_webhook_secret else {
+        return HttpResponse::Forbidden().body("Snapshot webhook is disabled");
+    };
+    let provided = req
+        .headers()
+        .get("Authentication")
+        .and_then(|value| value.to_str().ok());
+    if !secret_matches(provided, secret) {
+        return HttpResponse::Unauthorized().body("Invalid webhook secret");
+    }
+    let event = body.into_inner();
+    match handle_event(
+        &app_state.db_client,
+        &app_state.stores,
+        &app_state.config,
+        &app_state.hub,
+        &event,
+    )
+    .await
+    {
+        Ok(outcome) => HttpResponse::Ok().json(json!({ "id": event.id, "outcome": outcome })),
+        Err(err) => {
+            error!("Error handling webhook {} for {}: {}", event.event, event.id, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+/// Spaces the collector syncs.
+pub async fn get_watchlist(app_state: web::Data<AppState>, req: HttpRequest) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    match get_watched_spaces(&app_state.db_client).await {
+        Ok(spaces) => HttpResponse::Ok().json(spaces),
+        Err(err) => {
+            error!("Error fetching watched spaces: {}", err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+/// Adds a space to the watchlist; its history is backfilled by the next
+/// collection.
+pub async fn post_watchlist(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<WatchedSpaceRequest>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    let request = body.into_inner();
+    let space = request.space.trim();
+    if space.is_empty() {
+        return HttpResponse::BadRequest().body("space is required");
+    }
+    info!("{} added space {} to the watchlist", request.requested_by, space);
+    match add_watched_space(&app_state.db_client, space, &request.requested_by).await {
+        Ok(Some(watched)) => HttpResponse::Created().json(watched),
+        Ok(None) => HttpResponse::Conflict().body(format!("Space {} is already watched", space)),
+        Err(err) => {
+            error!("Error adding space {} to the watchlist: {}", space, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+/// Removes a space from the watchlist. Its collected proposals are kept.
+pub async fn delete_watchlist(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    let space = path.into_inner();
+    match remove_watched_space(&app_state.db_client, &space).await {
+        Ok(true) => {
+            info!("Space {} removed from the watchlist", space);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => HttpResponse::NotFound().body(format!("Space {} is not watched", space)),
+        Err(err) => {
+            error!("Error removing space {} from the watchlist: {}", space, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+pub async fn post_draft(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    body: web::Json<DraftRequest>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    let request = body.into_inner();
+    let mut draft = ProposalDraft {
+        id: 0,
+        space: request.space,
+        identity: request.identity,
+        proposal_type: request.proposal_type.unwrap_or_else(|| "single-choice".to_string()),
+        title: request.title,
+        body: request.body,
+        discussion: request.discussion.unwrap_or_default(),
+        choices: request.choices,
+        start: request.start,
+        end: request.end,
+        snapshot: request.snapshot,
+        review: None,
+        status: DRAFT_OPEN.to_string(),
+        validation: None,
+        author: None,
+        signed_message: None,
+        signature: None,
+        hub_response: None,
+        error: None,
+        created_by: request.created_by,
+        submitted_by: None,
+    };
+    draft.id = match create_draft(&app_state.db_client, &draft).await {
+        Ok(id) => id,
+        Err(err) => {
+            error!("Error creating draft: {}", err);
+            return HttpResponse::InternalServerError().body(err.to_string());
+        }
+    };
+    if request.review.unwrap_or(false) {
+        match review_draft(&app_state.db_client, &draft).await {
+            Ok(review) => draft.review = Some(review),
+            Err(err) => error!("Error reviewing draft {}: {}", draft.id, err),
+        }
+    }
+    HttpResponse::Created().json(draft)
+}
+
+pub async fn get_drafts_handler(
+    app_state: web::Data<AppState>,
+    query: web::Query<DraftsParams>,
+) -> impl Responder {
+    match get_drafts(&app_state.db_client, query.space.as_ref()).await {
+        Ok(drafts) => HttpResponse::Ok().json(drafts),
+        Err(err) => {
+            error!("Error fetching drafts: {}", err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+pub async fn get_draft_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    match get_draft(&app_state.db_client, path.into_inner()).await {
+        Ok(Some(draft)) => HttpResponse::Ok().json(draft),
+        Ok(None) => HttpResponse::NotFound().body("Draft not found"),
+        Err(err) => {
+            error!("Error fetching draft: {}", err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+pub async fn post_draft_review(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    let id = path.into_inner();
+    let draft = match get_draft(&app_state.db_client, id).await {
+        Ok(Some(draft)) => draft,
+        Ok(None) => return HttpResponse::NotFound().body("Draft not found"),
+        Err(err) => {
+            error!("Error fetching draft {}: {}", id, err);
+            return HttpResponse::InternalServerError().body(err.to_string());
+        }
+    };
+    match review_draft(&app_state.db_client, &draft).await {
+        Ok(review) => HttpResponse::Ok().json(review),
+        Err(err) => {
+            error!("Error reviewing draft {}: {}", id, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+pub async fn post_draft_submit(
+    app_state: web::Data<AppState>,
+    req: HttpRequest,
+    path: web::Path<i64>,
+    body: web::Json<DraftSubmitRequest>,
+) -> impl Responder {
+    if let Some(response) = check_admin(&app_state, &req) {
+        return response;
+    }
+    let id = path.into_inner();
+    let request = body.into_inner();
+    let result = submit_draft(
+        &app_state.db_client,
+        &app_state.config,
+        &app_state.http,
+        &app_state.registry,
+        id,
+        &request.submitted_by,
+        request.dry_run.unwrap_or(false),
+    )
+    .await;
+    match result {
+        Ok(Some(draft)) if draft.status == DRAFT_SUBMITTED || draft.status == DRAFT_SIMULATED => {
+            HttpResponse::Ok().json(draft)
+        }
+        Ok(Some(draft)) if draft.status == DRAFT_REJECTED && draft.error.is_none() => {
+            HttpResponse::UnprocessableEntity().json(draft)
+        }
+        Ok(Some(draft)) => HttpResponse::BadGateway().json(draft),
+        Ok(None) => HttpResponse::NotFound().body("Draft not found"),
+        Err(err) => {
+            error!("Error submitting draft {}: {}", id, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+/// Posts the rationale of a submitted vote to the forum, e.g. when
+/// `PUBLISH_RATIONALE` is off or an earlier attempt failed.
+pub async fn post_vote_rationale(
+    app_state: web::Data<AppState>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let action_id = path.into_inner();
+    let action = match app_state.stores.votes.action(action_id).await {
+        Ok(Some(action)) => action,
+        Ok(None) => return HttpResponse::NotFound().body("Vote not found"),
+        Err(err) => {
+            error!("Error fetching vote {}: {}", action_id, err);
+            return HttpResponse::InternalServerError().body(err.to_string());
+        }
+    };
+    if action.status != STATUS_SUBMITTED {
+        return HttpResponse::Conflict().body(format!("Vote is {}", action.status));
+    }
+    match publish_vote_rationale(&app_state.stores, &app_state.config, &app_state.http, action_id, &action).await {
+        Ok(Some(post)) if post.get("error").is_none() => HttpResponse::Ok().json(post),
+        Ok(Some(post)) => HttpResponse::BadGateway().json(post),
+        Ok(None) => HttpResponse::UnprocessableEntity()
+            .body("Vote has no rationale or its proposal has no thread on the forum"),
+        Err(err) => {
+            error!("Error publishing rationale of vote {}: {}", action_id, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+pub async fn get_discussion(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let proposal_id = path.into_inner();
+    match get_discussion_posts(&app_state.db_client, &proposal_id).await {
+        Ok(posts) => HttpResponse::Ok().json(posts),
+        Err(err) => {
+            error!("Error fetching discussion of {}: {}", proposal_id, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+/// Changes of a proposal seen by the collector, oldest first. Material edits
+/// made after our recommendation are `flagged`.
+pub async fn get_proposal_revisions_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let proposal_id = path.into_inner();
+    match get_proposal_revisions(&app_state.db_client, &proposal_id).await {
+        Ok(revisions) => HttpResponse::Ok().json(revisions),
+        Err(err) => {
+            error!("Error fetching revisions of {}: {}", proposal_id, err);
+            HttpResponse::InternalServerError().body(err.to_string())
+        }
+    }
+}
+
+/// Checks a proposal against its IPFS pin and returns the outcome.
+pub async fn get_ipfs_check_handler(
+    app_state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let proposal_id = path.into_inner();
+    let proposal = match app_state.stores.proposals.get(&proposal_id).await {
+        Ok(proposal) => proposal.map(|proposal| proposal.to_json()),
+        Err(err) => {
+            error!("Error fetching proposal {}: {}", proposal_id, err);
+            return HttpResponse::InternalServerError().body(err.to_string());
+        }
+    };
+    let Some(proposal) = proposal else {
+        return HttpResponse::NotFound().body("Proposal not found");
+    };
+    match verify_proposal(&app_state.db_client, &app_state.config, &app_state.http, &proposal).await {
+        Ok(Some(check)) => HttpResponse::Ok().json(check),
+        Ok(None) => HttpResponse::NotFound().body("Proposal has no IPFS hash"),
+        Err(err) => {
+            error!("Error checking the IPFS pin of {}: {}", proposal_id, err);
+            HttpResponse::BadGateway().body(err.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secrets_must_match_exactly() {
+        assert!(secret_matches(Some("s3cret"), "s3cret"));
+        assert!(!secret_matches(Some("s3cre"), "s3cret"));
+        assert!(!secret_matches(Some("s3cret!"), "s3cret"));
+        assert!(!secret_matches(Some(""), "s3cret"));
+        assert!(!secret_matches(None, "s3cret"));
+    }
 }
@@ -5,9 +5,22 @@ use actix_web::{
     web::{self},
     App, HttpServer,
 };
-use ai_voting_agent::api::api::{get_proposals, get_recommendation, get_spaces, post_vote, AppState};
+use ai_voting_agent::api::api::{
+    approve_recommendation, clear_delegation, get_discussion, get_delegation_history, get_ipfs_check_handler, get_delegations, get_proposal_revisions_handler,
+    get_draft_handler, get_drafts_handler, get_governor_voting_power_handler, get_prop_and_rec, get_proposals,
+    get_recommendation, get_spaces, get_votes_history, get_votes_scheduled, get_voting_power,
+    get_identities, post_governor_vote, post_draft, post_draft_review, post_draft_submit, post_vote, post_vote_rationale, set_delegation, AppState,
+    get_watchlist, post_watchlist, delete_watchlist, post_snapshot_webhook,
+};
 use ai_voting_agent::config::config::Config;
+use ai_voting_agent::http_client::http_client::build_http_client;
+use ai_voting_agent::migration::migration::{run_migrations, schema_version};
+use ai_voting_agent::proposal_snapchot::backfill::{run_backfill, BackfillRange};
+use ai_voting_agent::proposal_snapchot::hub::HubClient;
+use ai_voting_agent::proposal_snapchot::reprocess::reprocess_proposals;
 use ai_voting_agent::scheduler::scheduler;
+use ai_voting_agent::identity::identity::load_identities;
+use ai_voting_agent::store::store::Stores;
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
 use tokio_postgres::NoTls;
@@ -17,6 +30,11 @@ use tokio_postgres::NoTls;
 async fn main() -> std::io::Result<()> {
 
     env_logger::init();
+    // `migrate` applies pending migrations and exits, `reprocess` also derives the
+    // proposal columns again from the stored hub payloads, and
+    // `backfill --space X --from YYYY-MM-DD --to YYYY-MM-DD` pulls a historical
+    // window of a space; anything else starts the agent.
+    let command = std::env::args().nth(1);
     let config = Config::from_env().unwrap();
 
     let manager =
@@ -26,12 +44,70 @@ async fn main() -> std::io::Result<()> {
     let pool = Pool::builder().max_size(10).build(manager).await.unwrap();
     let pool: Arc<Pool<PostgresConnectionManager<NoTls>>> = Arc::new(pool);
 
-    let app_state = AppState { db_client: pool.clone() };
+    let applied = run_migrations(&pool).await.unwrap();
+    println!(
+        "Database schema at version {:?}, {} migration(s) applied",
+        schema_version(&pool).await.unwrap(),
+        applied.len()
+    );
+    // One hub client for the whole process, so its circuit breaker sees every query.
+    let http = build_http_client().unwrap();
+    let hub = Arc::new(HubClient::from_config(&config, &http));
+    match command.as_deref() {
+        Some("migrate") => return Ok(()),
+        Some("reprocess") => {
+            let report = reprocess_proposals(&pool).await.unwrap();
+            println!(
+                "Reprocessed {} proposals, {} payload(s) could not be read",
+                report.reprocessed, report.failed
+            );
+            return Ok(());
+        }
+        Some("backfill") => {
+            let range = BackfillRange::from_args(std::env::args().skip(2)).unwrap();
+            let report = run_backfill(&pool, &Stores::postgres(pool.clone()), &hub, &range)
+                .await
+                .unwrap();
+            println!(
+                "Backfilled {} proposals of {} in {} batches, {:.1}s ({:.0}/s)",
+                report.proposals,
+                range.space,
+                report.batches,
+                report.elapsed.as_secs_f64(),
+                report.rate()
+            );
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let registry = Arc::new(load_identities(&config).await.unwrap());
+    for identity in registry.all() {
+        println!("Voting identity {}: {:?}", identity.name, identity.address);
+    }
+    if config.dry_run {
+        println!("DRY_RUN is set: votes are simulated and never sent");
+    }
+
+    let stores = Stores::postgres(pool.clone());
+    let app_state = AppState {
+        db_client: pool.clone(),
+        stores: stores.clone(),
+        config: config.clone(),
+        registry: registry.clone(),
+        hub: hub.clone(),
+        http: http.clone(),
+    };
 
     let sheduler_pool: Arc<Pool<PostgresConnectionManager<NoTls>>> = pool.clone();
    
+    let sheduler_stores = stores.clone();
+    let sheduler_config = config.clone();
+    let sheduler_registry = registry.clone();
+    let sheduler_hub = hub.clone();
+    let sheduler_http = http.clone();
     tokio::spawn(async move {
-        scheduler::start_scheduler(sheduler_pool).await;
+        scheduler::start_scheduler(sheduler_pool, sheduler_stores, sheduler_config, sheduler_registry, sheduler_hub, sheduler_http).await;
     }); 
 
     println!("start REST API server 0.0.0.0:8080");
@@ -47,9 +123,36 @@ async fn main() -> std::io::Result<()> {
             .wrap(Logger::default())
             .route("/spaces", web::get().to(get_spaces))
             .route("/proposals/{space_id}", web::get().to(get_proposals))
+            .route("/proposals/{proposal_id}/revisions", web::get().to(get_proposal_revisions_handler))
             .route("/recommendation/{proposal_id}", web::get().to(get_recommendation))
+            .route("/discussion/{proposal_id}", web::get().to(get_discussion))
+            .route("/ipfs/{proposal_id}", web::get().to(get_ipfs_check_handler))
             .route("/vote/{proposal_id}", web::post().to(post_vote))
+            .route("/recommendation/{proposal_id}/approve", web::post().to(approve_recommendation))
             .route("/get_prop_and_rec", web::get().to(get_prop_and_rec))
+            .route("/votes/history", web::get().to(get_votes_history))
+            .route("/votes/scheduled", web::get().to(get_votes_scheduled))
+            .route("/votes/{action_id}/rationale", web::post().to(post_vote_rationale))
+            .route("/voting_power/{proposal_id}", web::get().to(get_voting_power))
+            .route(
+                "/voting_power/governor/{proposal_id}",
+                web::get().to(get_governor_voting_power_handler),
+            )
+            .route("/governor/{proposal_id}/vote", web::post().to(post_governor_vote))
+            .route("/identities", web::get().to(get_identities))
+            .route("/drafts", web::post().to(post_draft))
+            .route("/drafts", web::get().to(get_drafts_handler))
+            .route("/drafts/{id}", web::get().to(get_draft_handler))
+            .route("/drafts/{id}/review", web::post().to(post_draft_review))
+            .route("/drafts/{id}/submit", web::post().to(post_draft_submit))
+            .route("/admin/delegations", web::get().to(get_delegations))
+            .route("/admin/delegations/history", web::get().to(get_delegation_history))
+            .route("/admin/delegations/{identity}/set", web::post().to(set_delegation))
+            .route("/admin/delegations/{identity}/clear", web::post().to(clear_delegation))
+            .route("/webhooks/snapshot", web::post().to(post_snapshot_webhook))
+            .route("/admin/spaces", web::get().to(get_watchlist))
+            .route("/admin/spaces", web::post().to(post_watchlist))
+            .route("/admin/spaces/{space_id}", web::delete().to(delete_watchlist))
     })
     .bind("0.0.0.0:8080")?
     .run()
@@ -1,18 +1,169 @@
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
 use std::error::Error;
 use std::fmt;
 
+/// When an approved recommendation is turned into a vote.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VoteStrategy {
+    /// Vote as soon as the recommendation is approved.
+    Immediate,
+    /// Vote in the last N hours before the proposal `end`.
+    LastHours(i64),
+}
+
+impl VoteStrategy {
+    /// Parses `immediate` or `last:<hours>`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "immediate" => Some(VoteStrategy::Immediate),
+            other => other
+                .strip_prefix("last:")
+                .and_then(|hours| hours.trim().parse().ok())
+                .filter(|hours: &i64| *hours > 0)
+                .map(VoteStrategy::LastHours),
+        }
+    }
+}
+
+/// What happens when a re-analysis changes the choice we already voted for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RevotePolicy {
+    /// Keep the existing vote.
+    Never,
+    /// Wait until the new recommendation is approved.
+    Approval,
+    /// Queue the replacement vote right away.
+    Auto,
+}
+
+impl RevotePolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "never" => Some(RevotePolicy::Never),
+            "approval" => Some(RevotePolicy::Approval),
+            "auto" => Some(RevotePolicy::Auto),
+            _ => None,
+        }
+    }
+}
+
+/// Whether Snapshot votes are cast as the Safe at `safe_wallet_address`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SafeVoteMode {
+    /// Vote as the signer's own address.
+    #[serde(rename = "off")]
+    Disabled,
+    /// Collect owner signatures on the vote through the Safe Transaction Service.
+    Owners,
+    /// Wait until the Safe approved the vote on chain (SignMessageLib) and send an empty signature.
+    PreApproved,
+}
+
+impl SafeVoteMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "off" => Some(SafeVoteMode::Disabled),
+            "owners" => Some(SafeVoteMode::Owners),
+            "preapproved" => Some(SafeVoteMode::PreApproved),
+            _ => None,
+        }
+    }
+}
+
+/// Where the voting key lives. Secrets stay out of `Debug` output.
+#[derive(Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", rename_all_fields = "camelCase")]
+pub enum SignerConfig {
+    /// Encrypted JSON keystore; the password comes from a file or the environment.
+    Keystore {
+        path: String,
+        password_file: Option<String>,
+        password: Option<String>,
+    },
+    /// Remote signing service, see `signer::remote`.
+    Remote {
+        url: String,
+        token: Option<String>,
+        address: Option<String>,
+    },
+}
+
+impl fmt::Debug for SignerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignerConfig::Keystore { path, .. } => {
+                f.debug_struct("Keystore").field("path", path).finish_non_exhaustive()
+            }
+            SignerConfig::Remote { url, address, .. } => f
+                .debug_struct("Remote")
+                .field("url", url)
+                .field("address", address)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Config {
     pub openai_api_key: String,
     pub arbitrum_rpc_url: String,
     pub dao_contract_address: String,
     pub safe_wallet_address: String,
-    pub safe_wallet_private_key: String,
+    /// Signer of the default identity, and of registry identities without their own.
+    pub signer: Option<SignerConfig>,
+    /// JSON registry of voting identities, see `identity::identity`.
+    pub identities_file: Option<String>,
     pub pg_user: String,
     pub pg_pass: String,
     pub pg_host: String,
     pub pg_db: String,
+    pub default_vote_strategy: VoteStrategy,
+    pub space_vote_strategies: HashMap<String, VoteStrategy>,
+    pub vote_jitter_secs: u64,
+    pub escalation_hours: i64,
+    pub revote_policy: RevotePolicy,
+    /// JSON-RPC endpoints by chain id, e.g. `"1"` or `"42161"`.
+    pub rpc_urls: HashMap<String, String>,
+    pub score_api_url: String,
+    pub skip_zero_voting_power: bool,
+    pub safe_vote_mode: SafeVoteMode,
+    /// Chain id the Safe is deployed on and the hub checks its signatures against.
+    pub safe_network: String,
+    pub safe_tx_service_url: String,
+    /// Build, sign and validate votes but never send them.
+    pub dry_run: bool,
+    /// Bearer token for `/admin` endpoints; they are disabled when unset.
+    pub admin_api_token: Option<String>,
+    /// Chain id whose DelegateRegistry holds our Snapshot delegations.
+    pub delegation_network: String,
+    /// Chain id of `dao_contract_address` and of registry governors without their own.
+    pub governor_network: String,
+    /// Snapshot delegation subgraph; delegators are read from registry logs when unset.
+    pub delegation_subgraph_url: Option<String>,
+    /// Snapshot hub signed messages and space lookups go to.
+    pub snapshot_hub_url: String,
+    /// Sent as `x-api-key` to the hub for its higher rate limit.
+    pub snapshot_api_key: Option<String>,
+    /// Secret Snapshot sends in the `Authentication` header of webhook
+    /// deliveries; the webhook route is disabled when unset.
+    pub snapshot_webhook_secret: Option<String>,
+    /// Base URL of the Discourse-compatible forum API: proposal threads are read
+    /// from it and rationales posted to it.
+    pub forum_url: Option<String>,
+    /// Public address of the forum, as used in proposal `discussion` links.
+    pub forum_public_url: Option<String>,
+    pub forum_api_key: Option<String>,
+    pub forum_api_username: Option<String>,
+    /// Post the rationale of each submitted vote to the proposal's forum thread.
+    pub publish_rationale: bool,
+    /// IPFS gateway proposal envelopes are fetched from, e.g. a local kubo
+    /// node at `http://127.0.0.1:8080/ipfs`.
+    pub ipfs_gateway_url: String,
+    /// Collect proposals of every space on the hub instead of only the watchlist.
+    pub collect_all_spaces: bool,
 }
 
 #[derive(Debug)]
@@ -37,26 +188,199 @@ impl Config {
             .map_err(|_| ConfigError("DAO_CONTRACT_ADDRESS не установлен".into()))?;
         let safe_wallet_address = env::var("SAFE_WALLET_ADDRESS")
             .map_err(|_| ConfigError("SAFE_WALLET_ADDRESS не установлен".into()))?;
-        let safe_wallet_private_key = env::var("SAFE_WALLET_PRIVATE_KEY")
-            .map_err(|_| ConfigError("SAFE_WALLET_PRIVATE_KEY не установлен".into()))?; 
+        let identities_file = env::var("IDENTITIES_FILE").ok();
+        let signer_kind = env::var("SIGNER_KIND").ok().or_else(|| match identities_file {
+            Some(_) if env::var("SIGNER_KEYSTORE_PATH").is_err() => None,
+            _ => Some("keystore".to_string()),
+        });
+        let signer = match signer_kind.as_deref() {
+            None => None,
+            Some("keystore") => Some(SignerConfig::Keystore {
+                path: env::var("SIGNER_KEYSTORE_PATH")
+                    .map_err(|_| ConfigError("SIGNER_KEYSTORE_PATH не установлен".into()))?,
+                password_file: env::var("SIGNER_KEYSTORE_PASSWORD_FILE").ok(),
+                password: env::var("SIGNER_KEYSTORE_PASSWORD").ok(),
+            }),
+            Some("remote") => Some(SignerConfig::Remote {
+                url: env::var("SIGNER_REMOTE_URL")
+                    .map_err(|_| ConfigError("SIGNER_REMOTE_URL не установлен".into()))?,
+                token: env::var("SIGNER_REMOTE_TOKEN").ok(),
+                address: env::var("SIGNER_ADDRESS").ok(),
+            }),
+            Some(other) => return Err(ConfigError(format!("SIGNER_KIND неверный: {}", other)).into()),
+        };
+        let mut rpc_urls = parse_rpc_urls(&env::var("RPC_URLS").unwrap_or_default())?;
+        rpc_urls
+            .entry("42161".to_string())
+            .or_insert_with(|| arbitrum_rpc_url.clone());
+        let score_api_url = env::var("SCORE_API_URL")
+            .unwrap_or_else(|_| "https://score.snapshot.org".to_string());
+        let skip_zero_voting_power = env::var("SKIP_ZERO_VOTING_POWER")
+            .map(|v| v != "false")
+            .unwrap_or(true);
+        let safe_vote_mode = match env::var("SAFE_VOTE_MODE") {
+            Ok(value) => SafeVoteMode::parse(&value)
+                .ok_or_else(|| ConfigError(format!("SAFE_VOTE_MODE неверный: {}", value)))?,
+            Err(_) => SafeVoteMode::Disabled,
+        };
+        let safe_network = env::var("SAFE_NETWORK").unwrap_or_else(|_| "42161".to_string());
+        let safe_tx_service_url = env::var("SAFE_TX_SERVICE_URL")
+            .unwrap_or_else(|_| "https://safe-transaction-arbitrum.safe.global".to_string());
+        let dry_run = env::var("DRY_RUN").map(|v| v == "true").unwrap_or(false);
+        let admin_api_token = env::var("ADMIN_API_TOKEN").ok().filter(|t| !t.is_empty());
+        let delegation_network = env::var("DELEGATION_NETWORK").unwrap_or_else(|_| "1".to_string());
+        let governor_network = env::var("GOVERNOR_NETWORK").unwrap_or_else(|_| "42161".to_string());
+        let delegation_subgraph_url = env::var("DELEGATION_SUBGRAPH_URL").ok();
+        let snapshot_hub_url = env::var("SNAPSHOT_HUB_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|_| "https://hub.snapshot.org".to_string());
+        let snapshot_api_key = env::var("SNAPSHOT_API_KEY").ok().filter(|key| !key.is_empty());
+        let snapshot_webhook_secret = env::var("SNAPSHOT_WEBHOOK_SECRET").ok().filter(|s| !s.is_empty());
+        let forum_url = env::var("FORUM_URL")
+            .ok()
+            .map(|url| url.trim_end_matches('/').to_string());
+        let forum_public_url = env::var("FORUM_PUBLIC_URL")
+            .ok()
+            .map(|url| url.trim_end_matches('/').to_string())
+            .or_else(|| forum_url.clone());
+        let forum_api_key = env::var("FORUM_API_KEY").ok();
+        let forum_api_username = env::var("FORUM_API_USERNAME").ok();
+        let publish_rationale = env::var("PUBLISH_RATIONALE").map(|v| v == "true").unwrap_or(false);
+        if publish_rationale && (forum_url.is_none() || forum_api_key.is_none()) {
+            return Err(ConfigError("PUBLISH_RATIONALE требует FORUM_URL и FORUM_API_KEY".into()).into());
+        }
+        let ipfs_gateway_url = env::var("IPFS_GATEWAY_URL")
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or_else(|_| "https://ipfs.io/ipfs".to_string());
+        let collect_all_spaces = env::var("COLLECT_ALL_SPACES").map(|v| v == "true").unwrap_or(false);
         let pg_user = env::var("PG_USER").unwrap_or_else(|_| "".to_string());
         let pg_pass = env::var("PG_PASS").unwrap_or_default();
         let pg_host = env::var("PG_HOST").unwrap_or_else(|_| "172.17.0.1".to_string());
         let pg_db = env::var("PG_DB").unwrap_or_else(|_| "".to_string());
+        let default_vote_strategy = match env::var("VOTE_STRATEGY") {
+            Ok(value) => VoteStrategy::parse(&value)
+                .ok_or_else(|| ConfigError(format!("VOTE_STRATEGY неверный: {}", value)))?,
+            Err(_) => VoteStrategy::Immediate,
+        };
+        let space_vote_strategies =
+            parse_space_vote_strategies(&env::var("SPACE_VOTE_STRATEGIES").unwrap_or_default())?;
+        let vote_jitter_secs = env::var("VOTE_JITTER_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let escalation_hours = env::var("ESCALATION_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(24);
+        let revote_policy = match env::var("REVOTE_POLICY") {
+            Ok(value) => RevotePolicy::parse(&value)
+                .ok_or_else(|| ConfigError(format!("REVOTE_POLICY неверный: {}", value)))?,
+            Err(_) => RevotePolicy::Approval,
+        };
 
         Ok(Config {
             openai_api_key,
             arbitrum_rpc_url,
             dao_contract_address,
             safe_wallet_address,
-            safe_wallet_private_key,
+            signer,
+            identities_file,
             pg_user,
             pg_pass,
             pg_host,
-            pg_db
+            pg_db,
+            default_vote_strategy,
+            space_vote_strategies,
+            vote_jitter_secs,
+            escalation_hours,
+            revote_policy,
+            rpc_urls,
+            score_api_url,
+            skip_zero_voting_power,
+            safe_vote_mode,
+            safe_network,
+            safe_tx_service_url,
+            dry_run,
+            admin_api_token,
+            delegation_network,
+            governor_network,
+            delegation_subgraph_url,
+            snapshot_hub_url,
+            snapshot_api_key,
+            snapshot_webhook_secret,
+            forum_url,
+            forum_public_url,
+            forum_api_key,
+            forum_api_username,
+            publish_rationale,
+            ipfs_gateway_url,
+            collect_all_spaces,
         })
     }
 
+    /// The defaults of `from_env` without a signer, database or forum, for tests.
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        Config {
+            openai_api_key: String::new(),
+            arbitrum_rpc_url: String::new(),
+            dao_contract_address: String::new(),
+            safe_wallet_address: String::new(),
+            signer: None,
+            identities_file: None,
+            pg_user: String::new(),
+            pg_pass: String::new(),
+            pg_host: String::new(),
+            pg_db: String::new(),
+            default_vote_strategy: VoteStrategy::Immediate,
+            space_vote_strategies: HashMap::new(),
+            vote_jitter_secs: 0,
+            escalation_hours: 24,
+            revote_policy: RevotePolicy::Approval,
+            rpc_urls: HashMap::new(),
+            score_api_url: "https://score.snapshot.org".to_string(),
+            skip_zero_voting_power: true,
+            safe_vote_mode: SafeVoteMode::Disabled,
+            safe_network: "42161".to_string(),
+            safe_tx_service_url: String::new(),
+            dry_run: false,
+            admin_api_token: None,
+            delegation_network: "1".to_string(),
+            governor_network: "42161".to_string(),
+            delegation_subgraph_url: None,
+            snapshot_hub_url: "https://hub.snapshot.org".to_string(),
+            snapshot_api_key: None,
+            snapshot_webhook_secret: None,
+            forum_url: None,
+            forum_public_url: None,
+            forum_api_key: None,
+            forum_api_username: None,
+            publish_rationale: false,
+            ipfs_gateway_url: "https://ipfs.io/ipfs".to_string(),
+            collect_all_spaces: false,
+        }
+    }
+
+    pub fn rpc_url(&self, network: &str) -> Option<&String> {
+        self.rpc_urls.get(network)
+    }
+
+    /// `POST` endpoint of the hub for signed messages.
+    pub fn hub_message_url(&self) -> String {
+        format!("{}/api/message", self.snapshot_hub_url)
+    }
+
+    pub fn hub_graphql_url(&self) -> String {
+        format!("{}/graphql", self.snapshot_hub_url)
+    }
+
+    pub fn vote_strategy_for(&self, space_id: &str) -> VoteStrategy {
+        self.space_vote_strategies
+            .get(space_id)
+            .cloned()
+            .unwrap_or_else(|| self.default_vote_strategy.clone())
+    }
+
     pub fn to_pg_connection_string(&self) -> String {
         match self.pg_pass.is_empty() {
             true => format!(
@@ -69,4 +393,37 @@ impl Config {
             ),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Parses `space=strategy` pairs separated by commas,
+/// e.g. `arbitrumfoundation.eth=last:24,aave.eth=immediate`.
+fn parse_space_vote_strategies(value: &str) -> Result<HashMap<String, VoteStrategy>, ConfigError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (space, strategy) = pair
+                .split_once('=')
+                .ok_or_else(|| ConfigError(format!("SPACE_VOTE_STRATEGIES неверный: {}", pair)))?;
+            let strategy = VoteStrategy::parse(strategy)
+                .ok_or_else(|| ConfigError(format!("SPACE_VOTE_STRATEGIES неверный: {}", pair)))?;
+            Ok((space.trim().to_string(), strategy))
+        })
+        .collect()
+}
+
+/// Parses `chain_id=url` pairs separated by commas,
+/// e.g. `1=https://eth.llamarpc.com,42161=https://arb1.arbitrum.io/rpc`.
+fn parse_rpc_urls(value: &str) -> Result<HashMap<String, String>, ConfigError> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(chain, url)| (chain.trim().to_string(), url.trim().to_string()))
+                .ok_or_else(|| ConfigError(format!("RPC_URLS неверный: {}", pair)))
+        })
+        .collect()
+}
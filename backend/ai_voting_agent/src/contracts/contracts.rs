@@ -0,0 +1,87 @@
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use std::{error::Error, sync::Arc};
+
+/// Snapshot DelegateRegistry, deployed at the same address on every chain.
+pub const DELEGATE_REGISTRY_ADDRESS: &str = "0x469788fE6E9E9681C6ebF3bF78e7Fd26Fc015446";
+
+abigen!(
+    DelegateRegistry,
+    r#"[
+        function delegation(address delegator, bytes32 id) external view returns (address)
+        function setDelegate(bytes32 id, address delegate) external
+        function clearDelegate(bytes32 id) external
+        event SetDelegate(address indexed delegator, bytes32 indexed id, address indexed delegate)
+        event ClearDelegate(address indexed delegator, bytes32 indexed id, address indexed delegate)
+    ]"#
+);
+
+/// Value `isValidSignature(bytes32,bytes)` returns for a valid signature (EIP-1271).
+pub const EIP1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+abigen!(
+    Erc1271,
+    r#"[
+        function isValidSignature(bytes32 dataHash, bytes signature) external view returns (bytes4)
+    ]"#
+);
+
+abigen!(
+    Erc20Votes,
+    r#"[
+        function balanceOf(address account) external view returns (uint256)
+        function getVotes(address account) external view returns (uint256)
+        function decimals() external view returns (uint8)
+    ]"#
+);
+
+abigen!(
+    Governor,
+    r#"[
+        function getVotes(address account, uint256 timepoint) external view returns (uint256)
+        function proposalSnapshot(uint256 proposalId) external view returns (uint256)
+        function hasVoted(uint256 proposalId, address account) external view returns (bool)
+        function token() external view returns (address)
+        function castVoteWithReason(uint256 proposalId, uint8 support, string reason) external returns (uint256)
+    ]"#
+);
+
+abigen!(
+    GnosisSafe,
+    r#"[
+        function getThreshold() external view returns (uint256)
+        function isOwner(address owner) external view returns (bool)
+        function signedMessages(bytes32 messageHash) external view returns (uint256)
+        function isValidSignature(bytes32 dataHash, bytes signature) external view returns (bytes4)
+    ]"#
+);
+
+pub type HttpProvider = Arc<Provider<Http>>;
+
+pub fn http_provider(rpc_url: &str) -> Result<HttpProvider, Box<dyn Error + Send + Sync>> {
+    Ok(Arc::new(Provider::<Http>::try_from(rpc_url)?))
+}
+
+/// Sets the chain id and lets the node fill nonce, gas and fees, so the
+/// transaction can be signed as is.
+pub async fn prepare_transaction(
+    provider: &HttpProvider,
+    tx: &mut TypedTransaction,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let chain_id = provider.get_chainid().await?;
+    tx.set_chain_id(chain_id.as_u64());
+    provider.fill_transaction(tx, None).await?;
+    Ok(())
+}
+
+/// Encodes a Snapshot space id the way the DelegateRegistry keys it
+/// (`formatBytes32String`): UTF-8 bytes, right-padded with zeros.
+pub fn space_id_bytes32(space_id: &str) -> Result<[u8; 32], Box<dyn Error + Send + Sync>> {
+    let bytes = space_id.as_bytes();
+    if bytes.len() > 31 {
+        return Err(format!("Space id {} is too long for bytes32", space_id).into());
+    }
+    let mut id = [0u8; 32];
+    id[..bytes.len()].copy_from_slice(bytes);
+    Ok(id)
+}
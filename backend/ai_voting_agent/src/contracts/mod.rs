@@ -0,0 +1 @@
+pub mod contracts;
\ No newline at end of file
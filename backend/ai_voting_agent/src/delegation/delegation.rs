@@ -0,0 +1,268 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use reqwest::Client as HttpClient;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{error::Error, str::FromStr, sync::Arc};
+use tokio_postgres::NoTls;
+
+use crate::{
+    config::config::Config,
+    contracts::contracts::{
+        http_provider, prepare_transaction, space_id_bytes32, DelegateRegistry, HttpProvider,
+        DELEGATE_REGISTRY_ADDRESS,
+    },
+    delegation::repository::{save_delegation_action, DelegationAction},
+    identity::identity::VotingIdentity,
+    signer::signer::sign_transaction,
+    voting::snapshot::hex,
+    voting_power::power::find_delegators,
+};
+
+pub const DELEGATION_SET: &str = "set";
+pub const DELEGATION_CLEAR: &str = "clear";
+
+pub const DELEGATION_SENT: &str = "sent";
+pub const DELEGATION_SIMULATED: &str = "simulated";
+pub const DELEGATION_FAILED: &str = "failed";
+
+const DELEGATIONS_QUERY: &str = r#"
+query Delegations($delegate: String!, $spaces: [String!]) {
+  delegations(first: 1000, where: { delegate: $delegate, space_in: $spaces }) {
+    delegator
+    space
+    timestamp
+  }
+}
+"#;
+
+/// Delegations of one identity for a space, in both directions.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityDelegations {
+    pub identity: String,
+    pub address: String,
+    pub space: String,
+    /// Who our identity delegates to for this space, if anyone.
+    pub delegated_to: Option<String>,
+    /// Whether `delegated_to` comes from the space or the global delegation.
+    pub delegated_for: Option<String>,
+    /// Accounts whose delegation for this space points to our identity.
+    pub delegators: Vec<String>,
+    /// `subgraph` or `registry-logs`.
+    pub delegators_source: String,
+}
+
+/// Provider for the network the DelegateRegistry is read and written on.
+fn delegation_provider(config: &Config) -> Result<HttpProvider, Box<dyn Error + Send + Sync>> {
+    let rpc_url = config
+        .rpc_url(&config.delegation_network)
+        .ok_or_else(|| format!("No RPC URL for network {}", config.delegation_network))?;
+    http_provider(rpc_url)
+}
+
+/// Delegators of `delegate` for `space_id` (or globally) according to the
+/// delegation subgraph.
+async fn subgraph_delegators(
+    http: &HttpClient,
+    url: &str,
+    space_id: &str,
+    delegate: Address,
+) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    let body = json!({
+        "query": DELEGATIONS_QUERY,
+        "variables": {
+            "delegate": format!("{:?}", delegate),
+            "spaces": [space_id, ""],
+        }
+    });
+    let response = http.post(url).json(&body).send().await?;
+    if !response.status().is_success() {
+        return Err(format!("Delegation subgraph failed with status: {}", response.status()).into());
+    }
+    let resp_json: Value = response.json().await?;
+    if let Some(errors) = resp_json.get("errors") {
+        return Err(format!("Delegation subgraph errors: {}", errors).into());
+    }
+    let delegations = resp_json["data"]["delegations"]
+        .as_array()
+        .ok_or("No delegations array in subgraph response")?;
+
+    let mut delegators: Vec<String> = delegations
+        .iter()
+        .filter_map(|d| d["delegator"].as_str())
+        .filter_map(|d| Address::from_str(d).ok())
+        .map(|d| ethers::utils::to_checksum(&d, None))
+        .collect();
+    delegators.sort();
+    delegators.dedup();
+    Ok(delegators)
+}
+
+/// Reads the current delegations of `identity` for `space_id`: its own
+/// delegation from the DelegateRegistry, and the accounts delegating to it
+/// from the subgraph when configured, from the registry logs otherwise.
+pub async fn get_identity_delegations(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    http: &HttpClient,
+    identity: &VotingIdentity,
+    space_id: &str,
+) -> Result<IdentityDelegations, Box<dyn Error + Send + Sync>> {
+    let provider = delegation_provider(config)?;
+    let registry = DelegateRegistry::new(Address::from_str(DELEGATE_REGISTRY_ADDRESS)?, provider.clone());
+    let space_key = space_id_bytes32(space_id)?;
+
+    let mut delegated_to = None;
+    let mut delegated_for = None;
+    for (key, scope) in [(space_key, space_id), ([0u8; 32], "")] {
+        let delegate = registry.delegation(identity.address, key).call().await?;
+        if delegate != Address::zero() {
+            delegated_to = Some(ethers::utils::to_checksum(&delegate, None));
+            delegated_for = Some(scope.to_string());
+            break;
+        }
+    }
+
+    let (delegators, delegators_source) = match &config.delegation_subgraph_url {
+        Some(url) => (
+            subgraph_delegators(http, url, space_id, identity.address).await?,
+            "subgraph",
+        ),
+        None => (
+            find_delegators(
+                db_client,
+                &provider,
+                &config.delegation_network,
+                space_id,
+                identity.address,
+                None,
+            )
+            .await?
+                .iter()
+                .map(|d| ethers::utils::to_checksum(d, None))
+                .collect(),
+            "registry-logs",
+        ),
+    };
+
+    Ok(IdentityDelegations {
+        identity: identity.name.clone(),
+        address: identity.checksum_address(),
+        space: space_id.to_string(),
+        delegated_to,
+        delegated_for,
+        delegators,
+        delegators_source: delegators_source.to_string(),
+    })
+}
+
+/// What to change in the DelegateRegistry for one identity.
+#[derive(Debug, Clone)]
+pub struct DelegationOrder {
+    /// `set` or `clear`.
+    pub action: String,
+    /// Empty for the global delegation.
+    pub space: String,
+    /// Required for `set`.
+    pub delegate: Option<String>,
+    pub requested_by: String,
+    pub dry_run: bool,
+}
+
+async fn build_delegation_transaction(
+    config: &Config,
+    identity: &VotingIdentity,
+    order: &DelegationOrder,
+) -> Result<(TypedTransaction, HttpProvider), Box<dyn Error + Send + Sync>> {
+    let provider = delegation_provider(config)?;
+    let registry = DelegateRegistry::new(Address::from_str(DELEGATE_REGISTRY_ADDRESS)?, provider.clone());
+    let space_key = space_id_bytes32(&order.space)?;
+    let mut tx = match order.action.as_str() {
+        DELEGATION_SET => {
+            let delegate = order.delegate.as_deref().ok_or("A delegate is required")?;
+            registry.set_delegate(space_key, Address::from_str(delegate)?).tx
+        }
+        DELEGATION_CLEAR => registry.clear_delegate(space_key).tx,
+        other => return Err(format!("Unknown delegation action {}", other).into()),
+    };
+    tx.set_from(identity.address);
+    prepare_transaction(&provider, &mut tx).await?;
+    Ok((tx, provider))
+}
+
+/// Builds, signs and sends a `setDelegate` or `clearDelegate` transaction
+/// from `identity` (or simulates it with `eth_call` in dry-run mode) and
+/// records it in `delegation_actions`.
+pub async fn change_delegation(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    identity: &VotingIdentity,
+    order: DelegationOrder,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    if identity.safe.is_some() {
+        return Err(format!(
+            "Safe identity {} must change its delegation through the Safe",
+            identity.name
+        )
+        .into());
+    }
+
+    let mut action = DelegationAction {
+        identity: identity.name.clone(),
+        address: identity.checksum_address(),
+        action: order.action.clone(),
+        space: order.space.clone(),
+        delegate: order.delegate.clone(),
+        requested_by: order.requested_by.clone(),
+        tx_hash: None,
+        raw_tx: None,
+        status: DELEGATION_FAILED.to_string(),
+        error: None,
+    };
+
+    let result = async {
+        let (tx, provider) = build_delegation_transaction(config, identity, &order).await?;
+        let raw = sign_transaction(&identity.signer, &tx).await?;
+        action.raw_tx = Some(hex(&raw));
+        action.tx_hash = Some(format!("{:?}", H256::from(ethers::utils::keccak256(&raw))));
+        if config.dry_run || order.dry_run {
+            provider.call(&tx, None).await?;
+            action.status = DELEGATION_SIMULATED.to_string();
+        } else {
+            let pending = provider.send_raw_transaction(raw).await?;
+            action.tx_hash = Some(format!("{:?}", pending.tx_hash()));
+            action.status = DELEGATION_SENT.to_string();
+        }
+        Ok::<(), Box<dyn Error + Send + Sync>>(())
+    }
+    .await;
+    if let Err(err) = result {
+        action.error = Some(err.to_string());
+    }
+
+    let id = save_delegation_action(db_client, &action).await?;
+    match &action.error {
+        None => log::info!(
+            "Delegation {} ({} {} for '{}') recorded as {}",
+            id, action.identity, action.action, action.space, action.status
+        ),
+        Some(err) => log::error!(
+            "Delegation {} ({} {} for '{}') failed: {}",
+            id, action.identity, action.action, action.space, err
+        ),
+    }
+
+    Ok(json!({
+        "id": id,
+        "identity": action.identity,
+        "action": action.action,
+        "space": action.space,
+        "delegate": action.delegate,
+        "status": action.status,
+        "txHash": action.tx_hash,
+        "error": action.error,
+    }))
+}
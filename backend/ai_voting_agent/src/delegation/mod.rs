@@ -0,0 +1,2 @@
+pub mod delegation;
+pub mod repository;
@@ -0,0 +1,92 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use serde_json::{json, Value};
+use std::{error::Error, sync::Arc};
+use tokio_postgres::NoTls;
+
+/// One `setDelegate`/`clearDelegate` transaction, as written to the audit log.
+#[derive(Debug, Clone)]
+pub struct DelegationAction {
+    pub identity: String,
+    pub address: String,
+    pub action: String,
+    pub space: String,
+    pub delegate: Option<String>,
+    pub requested_by: String,
+    pub tx_hash: Option<String>,
+    pub raw_tx: Option<String>,
+    pub status: String,
+    pub error: Option<String>,
+}
+
+pub async fn save_delegation_action(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    action: &DelegationAction,
+) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_one(
+            r#"
+            INSERT INTO delegation_actions
+                (identity, address, action, space, delegate, requested_by, tx_hash, raw_tx,
+                 status, error)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING id
+            "#,
+            &[
+                &action.identity,
+                &action.address,
+                &action.action,
+                &action.space,
+                &action.delegate,
+                &action.requested_by,
+                &action.tx_hash,
+                &action.raw_tx,
+                &action.status,
+                &action.error,
+            ],
+        )
+        .await?;
+    Ok(row.get("id"))
+}
+
+pub async fn get_delegation_actions(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    limit: i64,
+) -> Result<Value, Box<dyn Error>> {
+    let conn = db_client.get().await?;
+    let rows = conn
+        .query(
+            r#"
+            SELECT
+                id, identity, address, action, space, delegate, requested_by, tx_hash,
+                status, error, (EXTRACT(EPOCH FROM created_at))::int8 AS created_at
+            FROM delegation_actions
+            ORDER BY created_at DESC, id DESC
+            LIMIT $1
+            "#,
+            &[&limit],
+        )
+        .await?;
+
+    let actions: Vec<Value> = rows
+        .into_iter()
+        .map(|row| {
+            json!({
+                "id": row.get::<_, i64>("id"),
+                "identity": row.get::<_, String>("identity"),
+                "address": row.get::<_, String>("address"),
+                "action": row.get::<_, String>("action"),
+                "space": row.get::<_, String>("space"),
+                "delegate": row.get::<_, Option<String>>("delegate"),
+                "requestedBy": row.get::<_, String>("requested_by"),
+                "txHash": row.get::<_, Option<String>>("tx_hash"),
+                "status": row.get::<_, String>("status"),
+                "error": row.get::<_, Option<String>>("error"),
+                "createdAt": row.get::<_, i64>("created_at"),
+            })
+        })
+        .collect();
+    Ok(json!(actions))
+}
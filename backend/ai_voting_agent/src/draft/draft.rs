@@ -0,0 +1,321 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use chrono::Utc;
+use log::{error, info};
+use ethers::prelude::*;
+use reqwest::Client as HttpClient;
+use serde_json::{json, Value};
+use std::{error::Error, sync::Arc};
+use tokio_postgres::NoTls;
+
+use crate::{
+    config::config::Config,
+    contracts::contracts::http_provider,
+    draft::repository::{
+        get_draft, save_draft_review, save_draft_submission, DraftSubmission, ProposalDraft,
+        DRAFT_FAILED, DRAFT_REJECTED, DRAFT_SIMULATED, DRAFT_SUBMITTED,
+    },
+    identity::identity::{IdentityRegistry, VotingIdentity},
+    recommendation::ai::get_draft_review,
+    voting::snapshot::{sign_proposal, submit_vote, vote_hash, Proposal, VoteError},
+};
+
+/// Voting types the hub accepts.
+pub const PROPOSAL_TYPES: [&str; 6] = [
+    "single-choice",
+    "approval",
+    "quadratic",
+    "ranked-choice",
+    "weighted",
+    "basic",
+];
+
+const TITLE_LIMIT: usize = 256;
+const BODY_LIMIT: usize = 10_000;
+
+const SPACE_SETTINGS_QUERY: &str = r#"
+query Space($id: String!) {
+  space(id: $id) {
+    id
+    network
+    admins
+    moderators
+    members
+    voting {
+      delay
+      period
+      type
+    }
+    filters {
+      onlyMembers
+      minScore
+    }
+    validation {
+      name
+      params
+    }
+  }
+}
+"#;
+
+/// Reads the settings proposals to `space_id` are validated against.
+pub async fn fetch_space_settings(
+    config: &Config,
+    http: &HttpClient,
+    space_id: &str,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let body = json!({
+        "query": SPACE_SETTINGS_QUERY,
+        "variables": { "id": space_id }
+    });
+    let response = http
+        .post(config.hub_graphql_url())
+        .json(&body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(format!("Space query failed with status: {}", response.status()).into());
+    }
+    let resp_json: Value = response.json().await?;
+    if let Some(errors) = resp_json.get("errors") {
+        return Err(format!("Space query errors: {}", errors).into());
+    }
+    match &resp_json["data"]["space"] {
+        Value::Null => Err(format!("Space {} not found on the hub", space_id).into()),
+        space => Ok(space.clone()),
+    }
+}
+
+fn is_listed(list: &Value, address: Address) -> bool {
+    list.as_array().is_some_and(|entries| {
+        entries
+            .iter()
+            .filter_map(|entry| entry.as_str())
+            .any(|entry| entry.parse::<Address>().ok() == Some(address))
+    })
+}
+
+/// Checks a draft against the hub's limits and the space settings.
+///
+/// Returns every problem found, so they can be fixed in one pass; an empty
+/// list means the hub should accept the proposal.
+pub async fn validate_draft(
+    config: &Config,
+    draft: &ProposalDraft,
+    author: Address,
+    space: &Value,
+    now: i64,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if draft.title.trim().is_empty() {
+        problems.push("title is empty".to_string());
+    }
+    if draft.title.chars().count() > TITLE_LIMIT {
+        problems.push(format!("title is longer than {} characters", TITLE_LIMIT));
+    }
+    if draft.body.chars().count() > BODY_LIMIT {
+        problems.push(format!("body is longer than {} characters", BODY_LIMIT));
+    }
+    if draft.choices.is_empty() || draft.choices.iter().any(|c| c.trim().is_empty()) {
+        problems.push("choices must be non-empty".to_string());
+    }
+    if !PROPOSAL_TYPES.contains(&draft.proposal_type.as_str()) {
+        problems.push(format!("unknown proposal type {}", draft.proposal_type));
+    }
+    if draft.proposal_type == "basic" && !(2..=3).contains(&draft.choices.len()) {
+        problems.push("basic proposals have 2 or 3 choices".to_string());
+    }
+    if let Some(required) = space["voting"]["type"].as_str().filter(|t| !t.is_empty()) {
+        if required != draft.proposal_type {
+            problems.push(format!("space only allows {} proposals", required));
+        }
+    }
+
+    if draft.end <= draft.start {
+        problems.push("end must be after start".to_string());
+    }
+    let delay = space["voting"]["delay"].as_i64().unwrap_or(0);
+    if draft.start < now + delay {
+        problems.push(format!("start must be at least {} seconds from now", delay));
+    }
+    if let Some(period) = space["voting"]["period"].as_i64().filter(|p| *p > 0) {
+        if draft.end - draft.start != period {
+            problems.push(format!("voting period must be {} seconds", period));
+        }
+    }
+
+    if space["filters"]["onlyMembers"].as_bool().unwrap_or(false)
+        && !["members", "admins", "moderators"]
+            .iter()
+            .any(|list| is_listed(&space[*list], author))
+    {
+        problems.push(format!("{:?} is not a member of the space", author));
+    }
+
+    if draft.snapshot <= 0 {
+        problems.push("snapshot block is missing".to_string());
+    } else if let Some(rpc_url) = space["network"].as_str().and_then(|n| config.rpc_url(n)) {
+        match http_provider(rpc_url) {
+            Ok(provider) => match provider.get_block_number().await {
+                Ok(latest) if draft.snapshot as u64 > latest.as_u64() => problems.push(format!(
+                    "snapshot block {} is after the latest block {}",
+                    draft.snapshot, latest
+                )),
+                Ok(_) => {}
+                Err(e) => log::warn!("Cannot check snapshot block of draft {}: {}", draft.id, e),
+            },
+            Err(e) => log::warn!("Cannot check snapshot block of draft {}: {}", draft.id, e),
+        }
+    }
+
+    problems
+}
+
+/// Asks the LLM to review a draft and stores the review with it.
+pub async fn review_draft(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    draft: &ProposalDraft,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let draft_json = json!({
+        "space": draft.space,
+        "type": draft.proposal_type,
+        "title": draft.title,
+        "body": draft.body,
+        "discussion": draft.discussion,
+        "choices": draft.choices,
+        "start": draft.start,
+        "end": draft.end,
+    })
+    .to_string();
+    let review = get_draft_review(&draft_json).await?;
+    save_draft_review(db_client, draft.id, &review).await?;
+    Ok(review)
+}
+
+/// The identity a draft is authored by: the one named in the draft, or the
+/// first identity of its space that can sign for itself.
+fn draft_author(
+    registry: &IdentityRegistry,
+    draft: &ProposalDraft,
+) -> Result<Arc<VotingIdentity>, Box<dyn Error + Send + Sync>> {
+    let identity = match &draft.identity {
+        Some(name) => registry
+            .get(name)
+            .ok_or_else(|| format!("Unknown identity {}", name))?,
+        None => registry
+            .for_space(&draft.space)
+            .into_iter()
+            .find(|identity| identity.safe.is_none())
+            .ok_or_else(|| format!("No identity can author proposals in {}", draft.space))?,
+    };
+    if identity.safe.is_some() {
+        return Err(format!("Safe identity {} cannot author proposals", identity.name).into());
+    }
+    Ok(identity)
+}
+
+/// Validates a draft against its space, signs it as its author and sends it
+/// to the hub (or, in dry-run mode, only checks the signature).
+///
+/// Returns the draft as stored after the attempt.
+pub async fn submit_draft(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    http: &HttpClient,
+    registry: &IdentityRegistry,
+    id: i64,
+    submitted_by: &str,
+    dry_run: bool,
+) -> Result<Option<ProposalDraft>, Box<dyn Error + Send + Sync>> {
+    let Some(draft) = get_draft(db_client, id).await? else {
+        return Ok(None);
+    };
+    if draft.status == DRAFT_SUBMITTED {
+        return Err(format!("Draft {} was already submitted", id).into());
+    }
+    let identity = draft_author(registry, &draft)?;
+    let space = fetch_space_settings(config, http, &draft.space).await?;
+    let now = Utc::now().timestamp();
+
+    let mut submission = DraftSubmission {
+        status: DRAFT_FAILED.to_string(),
+        validation: None,
+        author: Some(identity.checksum_address()),
+        signed_message: None,
+        signature: None,
+        hub_response: None,
+        error: None,
+        submitted_by: submitted_by.to_string(),
+    };
+
+    let problems = validate_draft(config, &draft, identity.address, &space, now).await;
+    submission.validation = Some(json!({ "problems": problems }));
+    if !problems.is_empty() {
+        info!("Draft {} does not fit space {}: {:?}", id, draft.space, problems);
+        submission.status = DRAFT_REJECTED.to_string();
+        save_draft_submission(db_client, id, &submission).await?;
+        return get_draft(db_client, id).await;
+    }
+
+    let proposal = Proposal {
+        from: identity.address,
+        space: draft.space.clone(),
+        timestamp: now as u64,
+        r#type: draft.proposal_type.clone(),
+        title: draft.title.clone(),
+        body: draft.body.clone(),
+        discussion: draft.discussion.clone(),
+        choices: draft.choices.clone(),
+        start: draft.start as u64,
+        end: draft.end as u64,
+        snapshot: draft.snapshot as u64,
+        plugins: "{}".to_string(),
+        app: "snapshot-v2".to_string(),
+    };
+
+    match sign_proposal(&identity.signer, &proposal).await {
+        Ok(signed) => {
+            submission.signed_message = Some(signed.typed_data.clone());
+            submission.signature = Some(signed.signature.clone());
+            if config.dry_run || dry_run {
+                let hash = vote_hash(&signed.typed_data)?;
+                let signature: Signature = signed.signature.parse()?;
+                let recovered = signature.recover(RecoveryMessage::Hash(hash))?;
+                if recovered != identity.address {
+                    return Err(format!(
+                        "Proposal signature recovers to {:?} instead of {:?}",
+                        recovered, identity.address
+                    )
+                    .into());
+                }
+                submission.hub_response = Some(json!({
+                    "proposalHash": format!("{:?}", hash),
+                    "payload": signed.payload(),
+                }));
+                submission.status = DRAFT_SIMULATED.to_string();
+            } else {
+                match submit_vote(http, &config.hub_message_url(), &signed).await {
+                    Ok(receipt) => {
+                        submission.hub_response = Some(serde_json::to_value(&receipt)?);
+                        submission.status = DRAFT_SUBMITTED.to_string();
+                    }
+                    Err(VoteError::Rejected { status, response }) => {
+                        submission.error = Some(format!("Hub rejected the proposal ({})", status));
+                        submission.hub_response = Some(response);
+                        submission.status = DRAFT_REJECTED.to_string();
+                    }
+                    Err(err) => submission.error = Some(err.to_string()),
+                }
+            }
+        }
+        Err(err) => submission.error = Some(err.to_string()),
+    }
+
+    match &submission.error {
+        None => info!("Draft {} for {} is {}", id, draft.space, submission.status),
+        Some(err) => error!("Draft {} for {} was not submitted: {}", id, draft.space, err),
+    }
+    save_draft_submission(db_client, id, &submission).await?;
+    get_draft(db_client, id).await
+}
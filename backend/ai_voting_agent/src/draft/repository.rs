@@ -0,0 +1,204 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{error::Error, sync::Arc};
+use tokio_postgres::{types::Json, NoTls, Row};
+
+/// Created through the API, not signed yet.
+pub const DRAFT_OPEN: &str = "draft";
+/// Signed and accepted by the hub.
+pub const DRAFT_SUBMITTED: &str = "submitted";
+/// Signed and checked in dry-run mode, never sent.
+pub const DRAFT_SIMULATED: &str = "simulated";
+/// Refused by the space settings or by the hub.
+pub const DRAFT_REJECTED: &str = "rejected";
+/// Could not be signed or did not reach the hub.
+pub const DRAFT_FAILED: &str = "failed";
+
+/// A proposal our team is preparing for a Snapshot space.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposalDraft {
+    pub id: i64,
+    pub space: String,
+    /// Identity the proposal is authored by; the first one of the space when unset.
+    pub identity: Option<String>,
+    /// Snapshot voting type, e.g. `single-choice` or `basic`.
+    pub proposal_type: String,
+    pub title: String,
+    pub body: String,
+    pub discussion: String,
+    pub choices: Vec<String>,
+    /// Unix seconds.
+    pub start: i64,
+    /// Unix seconds.
+    pub end: i64,
+    pub snapshot: i64,
+    pub review: Option<Value>,
+    pub status: String,
+    pub validation: Option<Value>,
+    pub author: Option<String>,
+    pub signed_message: Option<Value>,
+    pub signature: Option<String>,
+    pub hub_response: Option<Value>,
+    pub error: Option<String>,
+    pub created_by: String,
+    pub submitted_by: Option<String>,
+}
+
+/// Outcome of signing a draft and sending it to the hub.
+#[derive(Debug, Clone)]
+pub struct DraftSubmission {
+    pub status: String,
+    pub validation: Option<Value>,
+    pub author: Option<String>,
+    pub signed_message: Option<Value>,
+    pub signature: Option<String>,
+    pub hub_response: Option<Value>,
+    pub error: Option<String>,
+    pub submitted_by: String,
+}
+
+fn row_to_draft(row: &Row) -> ProposalDraft {
+    let choices: Value = row.get("choices");
+    ProposalDraft {
+        id: row.get("id"),
+        space: row.get("space"),
+        identity: row.get("identity"),
+        proposal_type: row.get("proposal_type"),
+        title: row.get("title"),
+        body: row.get("body"),
+        discussion: row.get("discussion"),
+        choices: serde_json::from_value(choices).unwrap_or_default(),
+        start: row.get("start_at"),
+        end: row.get("end_at"),
+        snapshot: row.get("snapshot"),
+        review: row.get("review"),
+        status: row.get("status"),
+        validation: row.get("validation"),
+        author: row.get("author"),
+        signed_message: row.get("signed_message"),
+        signature: row.get("signature"),
+        hub_response: row.get("hub_response"),
+        error: row.get("error"),
+        created_by: row.get("created_by"),
+        submitted_by: row.get("submitted_by"),
+    }
+}
+
+const DRAFT_COLUMNS: &str = r#"
+    id, space, identity, proposal_type, title, body, discussion, choices, start_at, end_at,
+    snapshot, review, status, validation, author, signed_message, signature, hub_response,
+    error, created_by, submitted_by
+"#;
+
+/// Stores a new draft; `id`, `status` and the submission fields are ignored.
+pub async fn create_draft(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    draft: &ProposalDraft,
+) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_one(
+            r#"
+            INSERT INTO proposal_drafts
+                (space, identity, proposal_type, title, body, discussion, choices, start_at,
+                 end_at, snapshot, review, created_by)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING id
+            "#,
+            &[
+                &draft.space,
+                &draft.identity,
+                &draft.proposal_type,
+                &draft.title,
+                &draft.body,
+                &draft.discussion,
+                &Json(json!(draft.choices)),
+                &draft.start,
+                &draft.end,
+                &draft.snapshot,
+                &draft.review.clone().map(Json),
+                &draft.created_by,
+            ],
+        )
+        .await?;
+    Ok(row.get("id"))
+}
+
+pub async fn get_draft(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    id: i64,
+) -> Result<Option<ProposalDraft>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_opt(
+            &format!("SELECT {} FROM proposal_drafts WHERE id = $1", DRAFT_COLUMNS),
+            &[&id],
+        )
+        .await?;
+    Ok(row.as_ref().map(row_to_draft))
+}
+
+pub async fn get_drafts(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    space: Option<&String>,
+) -> Result<Value, Box<dyn Error>> {
+    let conn = db_client.get().await?;
+    let rows = conn
+        .query(
+            &format!(
+                "SELECT {} FROM proposal_drafts WHERE $1::text IS NULL OR space = $1 ORDER BY id DESC",
+                DRAFT_COLUMNS
+            ),
+            &[&space],
+        )
+        .await?;
+    let drafts: Vec<ProposalDraft> = rows.iter().map(row_to_draft).collect();
+    Ok(json!(drafts))
+}
+
+pub async fn save_draft_review(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    id: i64,
+    review: &Value,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    conn.execute(
+        "UPDATE proposal_drafts SET review = $2, updated_at = NOW() WHERE id = $1",
+        &[&id, &Json(review)],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn save_draft_submission(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    id: i64,
+    submission: &DraftSubmission,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    conn.execute(
+        r#"
+        UPDATE proposal_drafts
+        SET status = $2, validation = $3, author = $4, signed_message = $5, signature = $6,
+            hub_response = $7, error = $8, submitted_by = $9, updated_at = NOW()
+        WHERE id = $1
+        "#,
+        &[
+            &id,
+            &submission.status,
+            &submission.validation.clone().map(Json),
+            &submission.author,
+            &submission.signed_message.clone().map(Json),
+            &submission.signature,
+            &submission.hub_response.clone().map(Json),
+            &submission.error,
+            &submission.submitted_by,
+        ],
+    )
+    .await?;
+    Ok(())
+}
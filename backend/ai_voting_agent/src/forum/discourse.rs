@@ -0,0 +1,228 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client as HttpClient;
+use serde_json::{json, Value};
+use std::{error::Error, fmt};
+
+use crate::config::config::Config;
+
+/// Posts fetched per `posts.json` request, as Discourse pages them.
+const POSTS_CHUNK: usize = 20;
+/// Longest thread we read; later posts are ignored.
+pub const MAX_TOPIC_POSTS: usize = 200;
+
+#[derive(Debug)]
+pub struct ForumError(pub String);
+
+impl fmt::Display for ForumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Forum error: {}", self.0)
+    }
+}
+
+impl Error for ForumError {}
+
+/// One post of a topic, with its HTML reduced to text.
+#[derive(Debug, Clone)]
+pub struct ForumPost {
+    pub id: i64,
+    pub post_number: i32,
+    pub username: String,
+    pub content: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Strips tags and decodes the common entities of Discourse's `cooked` HTML.
+fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn parse_post(post: &Value) -> Option<ForumPost> {
+    Some(ForumPost {
+        id: post["id"].as_i64()?,
+        post_number: post["post_number"].as_i64()? as i32,
+        username: post["username"].as_str().unwrap_or_default().to_string(),
+        content: html_to_text(post["cooked"].as_str().unwrap_or_default()),
+        created_at: post["created_at"]
+            .as_str()
+            .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| at.with_timezone(&Utc)),
+    })
+}
+
+/// What the agent reads from and posts to a forum. `DiscourseClient` is the
+/// implementation; tests use a stand-in.
+#[async_trait]
+pub trait ForumClient: Send + Sync {
+    /// Topic id of a `discussion` link, when the link points to this forum.
+    fn topic_id(&self, discussion_url: &str) -> Option<u64>;
+
+    /// Reads the posts of a topic in order, up to `MAX_TOPIC_POSTS`.
+    async fn get_topic_posts(&self, topic_id: u64) -> Result<Vec<ForumPost>, ForumError>;
+
+    /// Replies to a topic and returns the created post.
+    async fn create_post(&self, topic_id: u64, raw: &str) -> Result<Value, ForumError>;
+
+    /// Public link to a post returned by `create_post`.
+    fn post_url(&self, post: &Value) -> Option<String>;
+}
+
+/// Client for a Discourse-compatible forum API.
+#[derive(Debug, Clone)]
+pub struct DiscourseClient {
+    base_url: String,
+    /// Host part of proposal `discussion` links that point to this forum.
+    public_url: String,
+    api_key: Option<String>,
+    api_username: Option<String>,
+    http: HttpClient,
+}
+
+impl DiscourseClient {
+    /// The forum configured with `FORUM_URL`, if any, reached through the
+    /// shared HTTP client.
+    pub fn from_config(config: &Config, http: &HttpClient) -> Option<Self> {
+        let base_url = config.forum_url.clone()?;
+        Some(DiscourseClient {
+            public_url: config.forum_public_url.clone().unwrap_or_else(|| base_url.clone()),
+            base_url,
+            api_key: config.forum_api_key.clone(),
+            api_username: config.forum_api_username.clone(),
+            http: http.clone(),
+        })
+    }
+
+    fn request(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        let builder = match &self.api_key {
+            Some(key) => builder.header("Api-Key", key),
+            None => builder,
+        };
+        match &self.api_username {
+            Some(username) => builder.header("Api-Username", username),
+            None => builder,
+        }
+    }
+
+    async fn get_json(&self, url: String) -> Result<Value, ForumError> {
+        let response = self
+            .request(self.http.get(&url))
+            .send()
+            .await
+            .map_err(|err| ForumError(err.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ForumError(format!("{} returned {}", url, status)));
+        }
+        response.json().await.map_err(|err| ForumError(err.to_string()))
+    }
+}
+
+#[async_trait]
+impl ForumClient for DiscourseClient {
+    /// Topic id of a `discussion` link such as `https://forum.example/t/some-slug/1234/5`,
+    /// when the link points to this forum.
+    fn topic_id(&self, discussion_url: &str) -> Option<u64> {
+        let path = discussion_url
+            .trim()
+            .strip_prefix(&self.public_url)?
+            .split(['?', '#'])
+            .next()?;
+        let mut segments = path.trim_matches('/').split('/');
+        if segments.next()? != "t" {
+            return None;
+        }
+        // `/t/{slug}/{id}` or `/t/{id}`, optionally followed by a post number.
+        let first = segments.next()?;
+        match first.parse() {
+            Ok(id) => Some(id),
+            Err(_) => segments.next()?.parse().ok(),
+        }
+    }
+
+    async fn get_topic_posts(&self, topic_id: u64) -> Result<Vec<ForumPost>, ForumError> {
+        let topic = self.get_json(format!("{}/t/{}.json", self.base_url, topic_id)).await?;
+        let mut posts: Vec<ForumPost> = topic["post_stream"]["posts"]
+            .as_array()
+            .map(|posts| posts.iter().filter_map(parse_post).collect())
+            .unwrap_or_default();
+
+        // The topic only embeds the first page; the stream lists every post id.
+        let missing: Vec<i64> = topic["post_stream"]["stream"]
+            .as_array()
+            .map(|ids| ids.iter().filter_map(|id| id.as_i64()).collect::<Vec<_>>())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|id| !posts.iter().any(|post| post.id == *id))
+            .take(MAX_TOPIC_POSTS.saturating_sub(posts.len()))
+            .collect();
+        for chunk in missing.chunks(POSTS_CHUNK) {
+            let query: Vec<String> = chunk.iter().map(|id| format!("post_ids[]={}", id)).collect();
+            let page = self
+                .get_json(format!(
+                    "{}/t/{}/posts.json?{}",
+                    self.base_url,
+                    topic_id,
+                    query.join("&")
+                ))
+                .await?;
+            if let Some(page_posts) = page["post_stream"]["posts"].as_array() {
+                posts.extend(page_posts.iter().filter_map(parse_post));
+            }
+        }
+
+        posts.sort_by_key(|post| post.post_number);
+        posts.truncate(MAX_TOPIC_POSTS);
+        Ok(posts)
+    }
+
+    async fn create_post(&self, topic_id: u64, raw: &str) -> Result<Value, ForumError> {
+        if self.api_key.is_none() {
+            return Err(ForumError("FORUM_API_KEY is not set".to_string()));
+        }
+        let response = self
+            .request(self.http.post(format!("{}/posts.json", self.base_url)))
+            .json(&json!({ "topic_id": topic_id, "raw": raw }))
+            .send()
+            .await
+            .map_err(|err| ForumError(err.to_string()))?;
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|err| ForumError(err.to_string()))?;
+        if !status.is_success() {
+            return Err(ForumError(format!("post rejected ({}): {}", status, body)));
+        }
+        Ok(body)
+    }
+
+    fn post_url(&self, post: &Value) -> Option<String> {
+        Some(format!(
+            "{}/t/{}/{}/{}",
+            self.public_url,
+            post["topic_slug"].as_str()?,
+            post["topic_id"].as_u64()?,
+            post["post_number"].as_u64()?
+        ))
+    }
+}
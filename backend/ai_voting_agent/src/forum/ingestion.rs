@@ -0,0 +1,157 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use log::info;
+use reqwest::Client as HttpClient;
+use serde_json::Value;
+use std::{error::Error, sync::Arc};
+use tokio_postgres::NoTls;
+
+use crate::{
+    config::config::Config,
+    forum::{
+        discourse::{DiscourseClient, ForumClient, ForumError, ForumPost},
+        repository::{get_discussion_summary, save_discussion_posts, save_discussion_summary},
+    },
+    recommendation::ai::get_discussion_analysis,
+};
+
+/// Longest part of a single post sent to the model.
+const POST_CHARS: usize = 1_500;
+/// Longest transcript sent to the model; later posts are left out.
+const TRANSCRIPT_CHARS: usize = 12_000;
+
+fn transcript(posts: &[ForumPost]) -> String {
+    let mut transcript = String::new();
+    for post in posts {
+        let content: String = post.content.chars().take(POST_CHARS).collect();
+        let line = format!("#{} {}: {}\n", post.post_number, post.username, content);
+        if transcript.len() + line.len() > TRANSCRIPT_CHARS {
+            break;
+        }
+        transcript.push_str(&line);
+    }
+    transcript
+}
+
+/// Posts of the thread a proposal's `discussion` links to on `forum`; `None`
+/// when it links elsewhere or the thread is empty.
+async fn read_thread(
+    forum: &dyn ForumClient,
+    proposal: &Value,
+) -> Result<Option<(u64, Vec<ForumPost>)>, ForumError> {
+    let Some(topic_id) = proposal["discussion"].as_str().and_then(|url| forum.topic_id(url)) else {
+        return Ok(None);
+    };
+    let posts = forum.get_topic_posts(topic_id).await?;
+    Ok((!posts.is_empty()).then_some((topic_id, posts)))
+}
+
+/// Fetches the forum thread linked from a proposal's `discussion`, stores its
+/// posts and returns the summary of the discussion, with its sentiment
+/// breakdown and the concerns raised.
+///
+/// The summary is recomputed only when the thread has new posts. Returns
+/// `None` when no forum is configured or the proposal links elsewhere.
+pub async fn ingest_discussion(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    http: &HttpClient,
+    proposal: &Value,
+) -> Result<Option<Value>, Box<dyn Error + Send + Sync>> {
+    let Some(forum) = DiscourseClient::from_config(config, http) else {
+        return Ok(None);
+    };
+    let Some(proposal_id) = proposal["id"].as_str().map(str::to_string) else {
+        return Ok(None);
+    };
+    let Some((topic_id, posts)) = read_thread(&forum, proposal).await? else {
+        return Ok(None);
+    };
+    save_discussion_posts(db_client, &proposal_id, topic_id, &posts).await?;
+
+    let post_count = posts.len() as i32;
+    if let Some((summarized, summary)) = get_discussion_summary(db_client, &proposal_id).await? {
+        if summarized == post_count {
+            return Ok(Some(summary));
+        }
+    }
+
+    let analysis = get_discussion_analysis(&transcript(&posts)).await?;
+    save_discussion_summary(db_client, &proposal_id, topic_id, post_count, &analysis).await?;
+    info!(
+        "Summarized {} posts of topic {} for proposal {}",
+        post_count, topic_id, proposal_id
+    );
+    Ok(get_discussion_summary(db_client, &proposal_id)
+        .await?
+        .map(|(_, summary)| summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        forum::stub::StubForum,
+        recommendation::ai::{analysis_prompt, discussion_prompt},
+    };
+    use serde_json::json;
+
+    fn proposal(discussion: &str) -> Value {
+        json!({ "id": "0xabc", "discussion": discussion })
+    }
+
+    #[tokio::test]
+    async fn long_thread_is_truncated_into_the_prompt() {
+        let forum = StubForum::with_topic(42, 30, &"a".repeat(2 * POST_CHARS));
+        let (topic_id, posts) = read_thread(&forum, &proposal("https://forum.test/t/42"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((topic_id, posts.len()), (42, 30));
+
+        let transcript = transcript(&posts);
+        assert!(transcript.len() <= TRANSCRIPT_CHARS);
+        let lines: Vec<&str> = transcript.lines().collect();
+        // Every post is cut to `POST_CHARS`, and posts past the limit are left out.
+        assert_eq!(lines.len(), TRANSCRIPT_CHARS / (POST_CHARS + "#10 user10: \n".len()));
+        assert_eq!(lines[0], format!("#1 user1: {}", "a".repeat(POST_CHARS)));
+        assert!(!transcript.contains("user30"));
+
+        let prompt = discussion_prompt(&transcript);
+        assert!(prompt.contains(&format!("[{}]", transcript)));
+    }
+
+    #[tokio::test]
+    async fn short_thread_is_kept_whole() {
+        let forum = StubForum::with_topic(7, 3, "Looks good &amp; safe");
+        let (_, posts) = read_thread(&forum, &proposal("https://forum.test/t/7"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            transcript(&posts),
+            "#1 user1: Looks good &amp; safe\n#2 user2: Looks good &amp; safe\n#3 user3: Looks good &amp; safe\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn threads_elsewhere_or_empty_are_not_read() {
+        let forum = StubForum::with_topic(42, 0, "");
+        assert!(read_thread(&forum, &proposal("https://other.test/t/42")).await.unwrap().is_none());
+        assert!(read_thread(&forum, &proposal("https://forum.test/t/42")).await.unwrap().is_none());
+        assert!(read_thread(&forum, &proposal("https://forum.test/t/43")).await.is_err());
+    }
+
+    #[test]
+    fn discussion_summary_is_injected_into_the_analysis_prompt() {
+        let summary = json!({
+            "summary": "Delegates want a smaller budget.",
+            "sentiment": { "supportive": 2, "opposed": 5, "neutral": 1 },
+            "concerns": ["Budget size"],
+        });
+        let prompt = analysis_prompt("{\"id\":\"0xabc\"}", Some(&summary));
+        assert!(prompt.contains("[{\"id\":\"0xabc\"}]"));
+        assert!(prompt.ends_with(&format!("into account: [{}].", summary)));
+        assert!(!analysis_prompt("{}", None).contains("forum"));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod discourse;
+pub mod ingestion;
+pub mod repository;
+#[cfg(test)]
+pub mod stub;
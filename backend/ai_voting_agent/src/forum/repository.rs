@@ -0,0 +1,151 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use serde_json::{json, Value};
+use std::{error::Error, sync::Arc};
+use tokio_postgres::{types::Json, NoTls};
+
+use crate::forum::discourse::ForumPost;
+
+/// Inserts the posts of a proposal's thread, updating edited ones.
+pub async fn save_discussion_posts(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: &String,
+    topic_id: u64,
+    posts: &[ForumPost],
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut conn = db_client.get().await?;
+    let tx = conn.transaction().await?;
+    let statement = tx
+        .prepare(
+            r#"
+            INSERT INTO discussion_posts
+                (proposal_id, topic_id, post_id, post_number, username, content, posted_at)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (proposal_id, post_id) DO UPDATE SET
+                content = EXCLUDED.content,
+                fetched_at = NOW()
+            "#,
+        )
+        .await?;
+    for post in posts {
+        tx.execute(
+            &statement,
+            &[
+                proposal_id,
+                &(topic_id as i64),
+                &post.id,
+                &post.post_number,
+                &post.username,
+                &post.content,
+                &post.created_at.map(|at| at.naive_utc()),
+            ],
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Summary of a proposal's thread, with the number of posts it was computed from.
+pub async fn get_discussion_summary(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: &String,
+) -> Result<Option<(i32, Value)>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_opt(
+            r#"
+            SELECT topic_id, post_count, summary, sentiment, concerns,
+                (EXTRACT(EPOCH FROM created_at))::int8 AS created_at
+            FROM discussion_summaries
+            WHERE proposal_id = $1
+            "#,
+            &[proposal_id],
+        )
+        .await?;
+
+    Ok(row.map(|row| {
+        let post_count: i32 = row.get("post_count");
+        (
+            post_count,
+            json!({
+                "topicId": row.get::<_, i64>("topic_id"),
+                "postCount": post_count,
+                "summary": row.get::<_, String>("summary"),
+                "sentiment": row.get::<_, Value>("sentiment"),
+                "concerns": row.get::<_, Value>("concerns"),
+                "createdAt": row.get::<_, i64>("created_at"),
+            }),
+        )
+    }))
+}
+
+pub async fn save_discussion_summary(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: &String,
+    topic_id: u64,
+    post_count: i32,
+    summary: &Value,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    conn.execute(
+        r#"
+        INSERT INTO discussion_summaries
+            (proposal_id, topic_id, post_count, summary, sentiment, concerns)
+        VALUES
+            ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (proposal_id) DO UPDATE SET
+            topic_id = EXCLUDED.topic_id,
+            post_count = EXCLUDED.post_count,
+            summary = EXCLUDED.summary,
+            sentiment = EXCLUDED.sentiment,
+            concerns = EXCLUDED.concerns,
+            created_at = NOW()
+        "#,
+        &[
+            proposal_id,
+            &(topic_id as i64),
+            &post_count,
+            &summary["summary"].as_str().unwrap_or_default(),
+            &Json(summary["sentiment"].clone()),
+            &Json(summary["concerns"].clone()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn get_discussion_posts(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: &String,
+) -> Result<Value, Box<dyn Error>> {
+    let conn = db_client.get().await?;
+    let rows = conn
+        .query(
+            r#"
+            SELECT topic_id, post_id, post_number, username, content,
+                (EXTRACT(EPOCH FROM posted_at))::int8 AS posted_at
+            FROM discussion_posts
+            WHERE proposal_id = $1
+            ORDER BY post_number
+            "#,
+            &[proposal_id],
+        )
+        .await?;
+
+    let posts: Vec<Value> = rows
+        .into_iter()
+        .map(|row| {
+            json!({
+                "topicId": row.get::<_, i64>("topic_id"),
+                "postId": row.get::<_, i64>("post_id"),
+                "postNumber": row.get::<_, i32>("post_number"),
+                "username": row.get::<_, String>("username"),
+                "content": row.get::<_, String>("content"),
+                "postedAt": row.get::<_, Option<i64>>("posted_at"),
+            })
+        })
+        .collect();
+    Ok(json!(posts))
+}
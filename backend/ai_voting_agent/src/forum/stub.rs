@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::forum::discourse::{ForumClient, ForumError, ForumPost, MAX_TOPIC_POSTS};
+
+pub const STUB_FORUM_URL: &str = "https://forum.test";
+
+/// Forum stand-in for tests. Topics live at `https://forum.test/t/{id}` and
+/// are served from `topics`; replies are recorded in `created`.
+#[derive(Default)]
+pub struct StubForum {
+    pub topics: HashMap<u64, Vec<ForumPost>>,
+    /// Refuse replies, as a forum without a valid API key does.
+    pub reject_posts: bool,
+    pub created: Mutex<Vec<(u64, String)>>,
+}
+
+impl StubForum {
+    /// A forum with one topic of `count` posts, each `content` long.
+    pub fn with_topic(topic_id: u64, count: usize, content: &str) -> Self {
+        let posts = (1..=count)
+            .map(|n| ForumPost {
+                id: 1000 + n as i64,
+                post_number: n as i32,
+                username: format!("user{}", n),
+                content: content.to_string(),
+                created_at: None,
+            })
+            .collect();
+        StubForum {
+            topics: HashMap::from([(topic_id, posts)]),
+            ..Default::default()
+        }
+    }
+}
+
+#[async_trait]
+impl ForumClient for StubForum {
+    fn topic_id(&self, discussion_url: &str) -> Option<u64> {
+        discussion_url
+            .strip_prefix(STUB_FORUM_URL)?
+            .strip_prefix("/t/")?
+            .parse()
+            .ok()
+    }
+
+    async fn get_topic_posts(&self, topic_id: u64) -> Result<Vec<ForumPost>, ForumError> {
+        let posts = self
+            .topics
+            .get(&topic_id)
+            .ok_or_else(|| ForumError(format!("{}/t/{}.json returned 404", STUB_FORUM_URL, topic_id)))?;
+        Ok(posts.iter().take(MAX_TOPIC_POSTS).cloned().collect())
+    }
+
+    async fn create_post(&self, topic_id: u64, raw: &str) -> Result<Value, ForumError> {
+        if self.reject_posts {
+            return Err(ForumError("post rejected (403)".to_string()));
+        }
+        let mut created = self.created.lock().unwrap();
+        created.push((topic_id, raw.to_string()));
+        Ok(json!({
+            "id": 99,
+            "topic_id": topic_id,
+            "topic_slug": "topic",
+            "post_number": created.len() + 1,
+        }))
+    }
+
+    fn post_url(&self, post: &Value) -> Option<String> {
+        Some(format!(
+            "{}/t/{}/{}/{}",
+            STUB_FORUM_URL,
+            post["topic_slug"].as_str()?,
+            post["topic_id"].as_u64()?,
+            post["post_number"].as_u64()?
+        ))
+    }
+}
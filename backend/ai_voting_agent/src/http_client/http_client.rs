@@ -0,0 +1,15 @@
+use reqwest::Client as HttpClient;
+use std::time::Duration;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Builds the HTTP client shared by the process for the score API, Safe
+/// transaction service, IPFS gateway, forum and hub calls. It is built once
+/// at startup, so connections are pooled and no request waits forever.
+pub fn build_http_client() -> reqwest::Result<HttpClient> {
+    HttpClient::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+}
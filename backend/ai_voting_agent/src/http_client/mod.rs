@@ -0,0 +1,3 @@
+pub mod http_client;
+#[cfg(test)]
+pub mod stub;
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Answers a request from its method, path and body with a status and a body.
+type Handler = dyn Fn(&str, &str, &str) -> (u16, String) + Send + Sync;
+
+/// A local HTTP server standing in for a remote service in tests. Every
+/// connection carries one request, answered by the handler as JSON.
+pub struct StubServer {
+    pub url: String,
+}
+
+impl StubServer {
+    pub async fn start(handler: impl Fn(&str, &str, &str) -> (u16, String) + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handler: Arc<Handler> = Arc::new(handler);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(answer(stream, handler.clone()));
+            }
+        });
+        StubServer { url }
+    }
+}
+
+async fn answer(mut stream: TcpStream, handler: Arc<Handler>) {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    let (head, mut body) = loop {
+        let Ok(read) = stream.read(&mut buf).await else { return };
+        if read == 0 {
+            return;
+        }
+        request.extend_from_slice(&buf[..read]);
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&request[..end]).to_string();
+            break (head, request[end + 4..].to_vec());
+        }
+    };
+    let content_length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    while body.len() < content_length {
+        let Ok(read) = stream.read(&mut buf).await else { return };
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&buf[..read]);
+    }
+
+    let mut request_line = head.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let (status, response) = handler(method, path, &String::from_utf8_lossy(&body));
+    let response = format!(
+        "HTTP/1.1 {} Stub\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        response.len(),
+        response
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
@@ -0,0 +1,349 @@
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{collections::HashMap, error::Error, fmt, str::FromStr, sync::Arc};
+
+use crate::{
+    config::config::{Config, SafeVoteMode, SignerConfig},
+    signer::signer::{load_signer, VoteSigner},
+};
+
+/// Name of the identity built from the environment when no registry file is configured.
+pub const DEFAULT_IDENTITY: &str = "default";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityKind {
+    /// Votes with its own key.
+    Eoa,
+    /// A Safe; its signer is one of the owners, see `voting::safe`.
+    Safe,
+    /// A delegate on a Governor contract; votes on Snapshot with its own key.
+    GovernorDelegate,
+}
+
+/// How votes from a Safe identity get their signature.
+#[derive(Debug, Clone)]
+pub struct SafeSettings {
+    pub mode: SafeVoteMode,
+    /// Chain id the Safe is deployed on.
+    pub network: String,
+    pub tx_service_url: String,
+}
+
+/// An address we vote as, together with the signer that authorizes its votes.
+#[derive(Debug, Clone)]
+pub struct VotingIdentity {
+    pub name: String,
+    pub kind: IdentityKind,
+    pub address: Address,
+    pub signer: Arc<dyn VoteSigner>,
+    /// Set for Safe identities.
+    pub safe: Option<SafeSettings>,
+}
+
+impl VotingIdentity {
+    pub fn checksum_address(&self) -> String {
+        ethers::utils::to_checksum(&self.address, None)
+    }
+
+    pub fn summary(&self) -> Value {
+        json!({
+            "name": self.name,
+            "kind": self.kind,
+            "address": self.checksum_address(),
+            "signer": ethers::utils::to_checksum(&self.signer.address(), None),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SafeFile {
+    mode: Option<SafeVoteMode>,
+    network: Option<String>,
+    tx_service_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IdentityFile {
+    name: String,
+    kind: IdentityKind,
+    /// Required for Safes; defaults to the signer's address otherwise.
+    address: Option<String>,
+    /// Defaults to the signer from the environment.
+    signer: Option<SignerConfig>,
+    safe: Option<SafeFile>,
+}
+
+/// Identities of a governor, either as a bare list or with the chain id the
+/// governor is deployed on.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum GovernorFile {
+    Identities(Vec<String>),
+    WithNetwork { network: String, identities: Vec<String> },
+}
+
+/// A Governor contract and the identities that hold delegated votes on it.
+#[derive(Debug, Clone)]
+struct GovernorSettings {
+    /// Chain id the governor is deployed on.
+    network: String,
+    identities: Vec<String>,
+}
+
+/// Layout of the registry file:
+///
+/// ```json
+/// {
+///   "identities": [
+///     { "name": "treasury", "kind": "safe", "address": "0x…",
+///       "signer": { "kind": "keystore", "path": "/keys/owner.json", "passwordFile": "/run/secrets/owner" },
+///       "safe": { "mode": "owners", "network": "42161" } },
+///     { "name": "delegate", "kind": "governor_delegate",
+///       "signer": { "kind": "remote", "url": "http://signer:9000" } }
+///   ],
+///   "spaces": { "arbitrumfoundation.eth": ["treasury", "delegate"] },
+///   "governors": {
+///     "0xf07DeD9dC292157749B6Fd268E37DF6EA38395B9": ["delegate"],
+///     "0x408ED6354d4973f66138C91495F2f2FCbd8724C3": { "network": "1", "identities": ["delegate"] }
+///   },
+///   "default": ["treasury"]
+/// }
+/// ```
+///
+/// Spaces without a mapping use the `default` identities. Governors listed
+/// without a network are on `GOVERNOR_NETWORK`.
+#[derive(Debug, Deserialize)]
+struct RegistryFile {
+    identities: Vec<IdentityFile>,
+    #[serde(default)]
+    spaces: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    governors: HashMap<String, GovernorFile>,
+    #[serde(default)]
+    default: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct IdentityError(pub String);
+
+impl fmt::Display for IdentityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Identity registry error: {}", self.0)
+    }
+}
+
+impl Error for IdentityError {}
+
+/// The identities we vote as and which of them vote where.
+#[derive(Debug)]
+pub struct IdentityRegistry {
+    identities: Vec<Arc<VotingIdentity>>,
+    spaces: HashMap<String, Vec<String>>,
+    /// Keyed by lowercased governor address.
+    governors: HashMap<String, GovernorSettings>,
+    /// Chain id of governors without settings.
+    governor_network: String,
+    default: Vec<String>,
+}
+
+impl IdentityRegistry {
+    pub fn all(&self) -> &[Arc<VotingIdentity>] {
+        &self.identities
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<VotingIdentity>> {
+        self.identities.iter().find(|i| i.name == name).cloned()
+    }
+
+    fn resolve(&self, names: &[String]) -> Vec<Arc<VotingIdentity>> {
+        names.iter().filter_map(|name| self.get(name)).collect()
+    }
+
+    /// Identities that vote on proposals of a Snapshot space.
+    pub fn for_space(&self, space_id: &str) -> Vec<Arc<VotingIdentity>> {
+        match self.spaces.get(space_id) {
+            Some(names) => self.resolve(names),
+            None => self.resolve(&self.default),
+        }
+    }
+
+    /// Identities that hold delegated votes on a Governor contract.
+    pub fn for_governor(&self, governor_address: &str) -> Vec<Arc<VotingIdentity>> {
+        match self.governors.get(&governor_address.to_lowercase()) {
+            Some(governor) => self.resolve(&governor.identities),
+            None => self.resolve(&self.default),
+        }
+    }
+
+    /// Chain id a Governor contract is deployed on.
+    pub fn governor_network(&self, governor_address: &str) -> &str {
+        self.governors
+            .get(&governor_address.to_lowercase())
+            .map_or(&self.governor_network, |governor| &governor.network)
+    }
+
+    /// A registry where every space is voted on by all of `identities`, for tests.
+    #[cfg(test)]
+    pub fn with_identities(identities: Vec<VotingIdentity>) -> Self {
+        IdentityRegistry {
+            default: identities.iter().map(|i| i.name.clone()).collect(),
+            identities: identities.into_iter().map(Arc::new).collect(),
+            spaces: HashMap::new(),
+            governors: HashMap::new(),
+            governor_network: "42161".to_string(),
+        }
+    }
+}
+
+fn safe_settings(config: &Config, file: Option<SafeFile>) -> SafeSettings {
+    let file = file.unwrap_or(SafeFile {
+        mode: None,
+        network: None,
+        tx_service_url: None,
+    });
+    SafeSettings {
+        mode: file.mode.unwrap_or(SafeVoteMode::Owners),
+        network: file.network.unwrap_or_else(|| config.safe_network.clone()),
+        tx_service_url: file
+            .tx_service_url
+            .unwrap_or_else(|| config.safe_tx_service_url.clone()),
+    }
+}
+
+/// The single identity described by the environment: the Safe at
+/// `SAFE_WALLET_ADDRESS` when Safe voting is enabled, the signer otherwise.
+async fn default_registry(config: &Config) -> Result<IdentityRegistry, Box<dyn Error + Send + Sync>> {
+    let signer_config = config
+        .signer
+        .as_ref()
+        .ok_or_else(|| IdentityError("no signer configured".to_string()))?;
+    let signer = load_signer(signer_config).await?;
+    let identity = match config.safe_vote_mode {
+        SafeVoteMode::Disabled => VotingIdentity {
+            name: DEFAULT_IDENTITY.to_string(),
+            kind: IdentityKind::Eoa,
+            address: signer.address(),
+            signer,
+            safe: None,
+        },
+        _ => VotingIdentity {
+            name: DEFAULT_IDENTITY.to_string(),
+            kind: IdentityKind::Safe,
+            address: Address::from_str(&config.safe_wallet_address)?,
+            signer,
+            safe: Some(SafeSettings {
+                mode: config.safe_vote_mode.clone(),
+                network: config.safe_network.clone(),
+                tx_service_url: config.safe_tx_service_url.clone(),
+            }),
+        },
+    };
+    Ok(IdentityRegistry {
+        identities: vec![Arc::new(identity)],
+        spaces: HashMap::new(),
+        governors: HashMap::new(),
+        governor_network: config.governor_network.clone(),
+        default: vec![DEFAULT_IDENTITY.to_string()],
+    })
+}
+
+/// Loads the identity registry and every signer in it. Called once at startup.
+pub async fn load_identities(config: &Config) -> Result<IdentityRegistry, Box<dyn Error + Send + Sync>> {
+    let Some(path) = &config.identities_file else {
+        return default_registry(config).await;
+    };
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| IdentityError(format!("cannot read {}: {}", path, err)))?;
+    let file: RegistryFile = serde_json::from_str(&content)
+        .map_err(|err| IdentityError(format!("cannot parse {}: {}", path, err)))?;
+
+    // Identities sharing a signer configuration share one loaded signer.
+    let mut default_signer: Option<Arc<dyn VoteSigner>> = None;
+    let mut identities = Vec::with_capacity(file.identities.len());
+    for entry in file.identities {
+        if identities.iter().any(|i: &Arc<VotingIdentity>| i.name == entry.name) {
+            return Err(IdentityError(format!("duplicate identity {}", entry.name)).into());
+        }
+        let signer = match &entry.signer {
+            Some(signer_config) => load_signer(signer_config).await?,
+            None => match &default_signer {
+                Some(signer) => signer.clone(),
+                None => {
+                    let signer_config = config.signer.as_ref().ok_or_else(|| {
+                        IdentityError(format!("identity {} has no signer", entry.name))
+                    })?;
+                    let signer = load_signer(signer_config).await?;
+                    default_signer = Some(signer.clone());
+                    signer
+                }
+            },
+        };
+        let address = match (&entry.address, entry.kind) {
+            (Some(address), _) => Address::from_str(address)
+                .map_err(|err| IdentityError(format!("identity {}: {}", entry.name, err)))?,
+            (None, IdentityKind::Safe) => {
+                return Err(IdentityError(format!("Safe identity {} has no address", entry.name)).into())
+            }
+            (None, _) => signer.address(),
+        };
+        if entry.kind != IdentityKind::Safe && address != signer.address() {
+            return Err(IdentityError(format!(
+                "identity {} is {:?} but its signer is {:?}",
+                entry.name,
+                address,
+                signer.address()
+            ))
+            .into());
+        }
+        let safe = match entry.kind {
+            IdentityKind::Safe => Some(safe_settings(config, entry.safe)),
+            _ => None,
+        };
+        identities.push(Arc::new(VotingIdentity {
+            name: entry.name,
+            kind: entry.kind,
+            address,
+            signer,
+            safe,
+        }));
+    }
+
+    let registry = IdentityRegistry {
+        identities,
+        spaces: file.spaces,
+        governors: file
+            .governors
+            .into_iter()
+            .map(|(governor, entry)| {
+                let settings = match entry {
+                    GovernorFile::Identities(identities) => GovernorSettings {
+                        network: config.governor_network.clone(),
+                        identities,
+                    },
+                    GovernorFile::WithNetwork { network, identities } => {
+                        GovernorSettings { network, identities }
+                    }
+                };
+                (governor.to_lowercase(), settings)
+            })
+            .collect(),
+        governor_network: config.governor_network.clone(),
+        default: file.default,
+    };
+    let unknown: Vec<&String> = registry
+        .spaces
+        .values()
+        .chain(registry.governors.values().map(|governor| &governor.identities))
+        .chain(std::iter::once(&registry.default))
+        .flatten()
+        .filter(|name| registry.get(name).is_none())
+        .collect();
+    if !unknown.is_empty() {
+        return Err(IdentityError(format!("unknown identities {:?}", unknown)).into());
+    }
+    Ok(registry)
+}
@@ -0,0 +1 @@
+pub mod identity;
\ No newline at end of file
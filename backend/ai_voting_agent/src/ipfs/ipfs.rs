@@ -0,0 +1,242 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use ethers::types::{Address, H256};
+use log::{info, warn};
+use reqwest::Client as HttpClient;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{error::Error, fmt, sync::Arc};
+use tokio_postgres::NoTls;
+
+use crate::{
+    config::config::Config,
+    ipfs::repository::{get_ipfs_envelope, save_ipfs_check},
+    verification::verification::verify_signature,
+    voting::snapshot::{hex, vote_hash, VoteError},
+};
+
+/// The pinned message matches the stored proposal and is signed by its author.
+pub const IPFS_VERIFIED: &str = "verified";
+/// The content matches but the author signature does not validate or could not
+/// be checked, e.g. a legacy envelope or a space network without RPC.
+pub const IPFS_UNVERIFIED: &str = "unverified";
+/// The stored proposal differs from what its author signed.
+pub const IPFS_TAMPERED: &str = "tampered";
+
+#[derive(Debug)]
+pub struct IpfsError(pub String);
+
+impl fmt::Display for IpfsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "IPFS error: {}", self.0)
+    }
+}
+
+impl Error for IpfsError {}
+
+/// One difference between the stored proposal and its pinned envelope.
+#[derive(Debug, Clone, Serialize)]
+pub struct IpfsMismatch {
+    pub field: String,
+    pub stored: Value,
+    pub pinned: Value,
+}
+
+/// Outcome of checking a proposal against its IPFS pin.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IpfsCheck {
+    /// The `ipfs` hash that was checked.
+    pub ipfs: String,
+    pub status: String,
+    /// Author whose signature was validated.
+    pub signer: Option<String>,
+    /// How the signature was validated: `ecdsa` or `eip1271`.
+    pub signature: Option<String>,
+    pub mismatches: Vec<IpfsMismatch>,
+    pub checked_at: Option<i64>,
+}
+
+impl IpfsCheck {
+    pub fn is_tampered(&self) -> bool {
+        self.status == IPFS_TAMPERED
+    }
+}
+
+/// Reads the envelope pinned under `cid` from the configured gateway.
+pub async fn fetch_envelope(
+    config: &Config,
+    http: &HttpClient,
+    cid: &str,
+) -> Result<Value, IpfsError> {
+    let url = format!("{}/{}", config.ipfs_gateway_url, cid);
+    let response = http
+        .get(&url)
+        .send()
+        .await
+        .map_err(|err| IpfsError(err.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(IpfsError(format!("{} returned {}", url, status)));
+    }
+    response.json().await.map_err(|err| IpfsError(err.to_string()))
+}
+
+/// Case-insensitive comparison of two hex strings, such as addresses or hashes.
+fn same_hex(a: &Value, b: &Value) -> bool {
+    match (a.as_str(), b.as_str()) {
+        (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+        _ => false,
+    }
+}
+
+/// Typed data of an envelope, with the `primaryType` the hub leaves out.
+fn envelope_typed_data(data: &Value) -> Value {
+    let mut typed_data = data.clone();
+    if typed_data.get("primaryType").is_none() {
+        let primary_type = data["types"]
+            .as_object()
+            .and_then(|types| types.keys().find(|name| *name != "EIP712Domain"))
+            .cloned();
+        typed_data["primaryType"] = json!(primary_type);
+    }
+    typed_data
+}
+
+/// EIP-712 hash of the message in an envelope.
+pub fn envelope_hash(envelope: &Value) -> Result<H256, VoteError> {
+    vote_hash(&envelope_typed_data(&envelope["data"]))
+}
+
+/// Compares a stored proposal with the envelope pinned under its `ipfs` hash
+/// and recomputes the envelope hash. The signature is checked separately.
+pub fn check_envelope(proposal: &Value, envelope: &Value) -> IpfsCheck {
+    let mut check = IpfsCheck {
+        ipfs: proposal["ipfs"].as_str().unwrap_or_default().to_string(),
+        status: IPFS_VERIFIED.to_string(),
+        signer: None,
+        signature: None,
+        mismatches: Vec::new(),
+        checked_at: Some(chrono::Utc::now().timestamp()),
+    };
+    let data = &envelope["data"];
+    if data["message"].is_null() {
+        // Envelopes from before EIP-712 signing carry a JSON string we cannot hash.
+        check.status = IPFS_UNVERIFIED.to_string();
+        check.mismatches.push(IpfsMismatch {
+            field: "envelope".to_string(),
+            stored: Value::Null,
+            pinned: json!("no typed data"),
+        });
+        return check;
+    }
+    let message = &data["message"];
+
+    let mut mismatch = |field: &str, stored: &Value, pinned: &Value| {
+        check.mismatches.push(IpfsMismatch {
+            field: field.to_string(),
+            stored: stored.clone(),
+            pinned: pinned.clone(),
+        });
+    };
+    for field in ["title", "body", "choices"] {
+        if proposal[field] != message[field] {
+            mismatch(field, &proposal[field], &message[field]);
+        }
+    }
+    if proposal["space"]["id"] != message["space"] {
+        mismatch("space", &proposal["space"]["id"], &message["space"]);
+    }
+    if !same_hex(&proposal["author"], &message["from"]) {
+        mismatch("author", &proposal["author"], &message["from"]);
+    }
+    if !same_hex(&proposal["author"], &envelope["address"]) {
+        mismatch("address", &proposal["author"], &envelope["address"]);
+    }
+
+    let hash = match envelope_hash(envelope) {
+        Ok(hash) => hash,
+        Err(err) => {
+            mismatch("hash", &envelope["hash"], &json!(err.to_string()));
+            check.status = IPFS_TAMPERED.to_string();
+            return check;
+        }
+    };
+    let recomputed = json!(hex(hash.as_bytes()));
+    if envelope["hash"].is_string() && !same_hex(&envelope["hash"], &recomputed) {
+        mismatch("hash", &envelope["hash"], &recomputed);
+    }
+    if !check.mismatches.is_empty() {
+        check.status = IPFS_TAMPERED.to_string();
+    }
+    check
+}
+
+/// Checks the envelope signature against the proposal author, through
+/// EIP-1271 when the author is a contract on the space's network.
+async fn check_author_signature(
+    config: &Config,
+    proposal: &Value,
+    envelope: &Value,
+    check: &mut IpfsCheck,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let hash = envelope_hash(envelope)?;
+    let author = proposal["author"].as_str().and_then(|author| author.parse::<Address>().ok());
+    let network = proposal["space"]["network"].as_str().unwrap_or("1");
+    let signature = envelope["sig"].as_str().unwrap_or_default();
+    let method = match author {
+        Some(author) => verify_signature(config, network, author, hash, signature).await?,
+        None => None,
+    };
+    match method {
+        Some(method) => {
+            check.signer = author.map(|author| ethers::utils::to_checksum(&author, None));
+            check.signature = Some(method.to_string());
+        }
+        None => {
+            check.status = IPFS_UNVERIFIED.to_string();
+            check.mismatches.push(IpfsMismatch {
+                field: "signature".to_string(),
+                stored: proposal["author"].clone(),
+                pinned: envelope["sig"].clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Checks a proposal against its IPFS pin and stores the outcome.
+///
+/// The envelope is fetched again only when the proposal's `ipfs` hash changed
+/// since the last check; the stored content is compared with it every time.
+/// Returns `None` for proposals without an `ipfs` hash.
+pub async fn verify_proposal(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    http: &HttpClient,
+    proposal: &Value,
+) -> Result<Option<IpfsCheck>, Box<dyn Error + Send + Sync>> {
+    let Some(cid) = proposal["ipfs"].as_str().filter(|cid| !cid.is_empty()) else {
+        return Ok(None);
+    };
+    let proposal_id = proposal["id"].as_str().unwrap_or_default().to_string();
+    let envelope = match get_ipfs_envelope(db_client, &proposal_id).await? {
+        Some((checked, envelope)) if checked == cid => envelope,
+        _ => fetch_envelope(config, http, cid).await?,
+    };
+
+    let mut check = check_envelope(proposal, &envelope);
+    if check.status == IPFS_VERIFIED {
+        check_author_signature(config, proposal, &envelope, &mut check).await?;
+    }
+    save_ipfs_check(db_client, &proposal_id, &check, &envelope).await?;
+    if check.status == IPFS_VERIFIED {
+        info!("Proposal {} matches its IPFS pin {}", proposal_id, cid);
+    } else {
+        warn!(
+            "Proposal {} is {} against its IPFS pin {}: {:?}",
+            proposal_id, check.status, cid, check.mismatches
+        );
+    }
+    Ok(Some(check))
+}
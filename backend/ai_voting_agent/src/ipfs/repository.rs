@@ -0,0 +1,90 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use serde_json::{json, Value};
+use std::{error::Error, sync::Arc};
+use tokio_postgres::{types::Json, NoTls};
+
+use crate::{
+    ipfs::ipfs::{IpfsCheck, IPFS_VERIFIED},
+    proposal_snapchot::repository::{row_to_proposal, PROPOSAL_COLUMNS},
+};
+
+/// Envelope fetched by the last check of a proposal, with the `ipfs` hash it
+/// was pinned under.
+pub async fn get_ipfs_envelope(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: &String,
+) -> Result<Option<(String, Value)>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_opt(
+            r#"
+            SELECT ipfs_checked_hash, ipfs_envelope
+            FROM proposals
+            WHERE id = $1 AND ipfs_checked_hash IS NOT NULL AND ipfs_envelope IS NOT NULL
+            "#,
+            &[proposal_id],
+        )
+        .await?;
+    Ok(row.map(|row| (row.get("ipfs_checked_hash"), row.get("ipfs_envelope"))))
+}
+
+pub async fn save_ipfs_check(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: &String,
+    check: &IpfsCheck,
+    envelope: &Value,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    conn.execute(
+        r#"
+        UPDATE proposals SET
+            ipfs_status = $2,
+            ipfs_checked_hash = $3,
+            ipfs_signer = $4,
+            ipfs_signature = $5,
+            ipfs_mismatches = $6,
+            ipfs_envelope = $7,
+            ipfs_checked_at = NOW(),
+            verified = ($2 = $8)
+        WHERE id = $1
+        "#,
+        &[
+            proposal_id,
+            &check.status,
+            &check.ipfs,
+            &check.signer,
+            &check.signature,
+            &Json(json!(check.mismatches)),
+            &Json(envelope),
+            &IPFS_VERIFIED,
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Proposals never checked against their IPFS pin, or whose `ipfs` hash changed
+/// since, newest first.
+pub async fn get_unchecked_proposals(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    limit: i64,
+) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let query = format!(
+        r#"
+        SELECT {}
+        FROM proposals
+        WHERE ipfs IS NOT NULL AND ipfs <> ''
+          AND ipfs_checked_hash IS DISTINCT FROM ipfs
+        ORDER BY created DESC
+        LIMIT $1
+        "#,
+        PROPOSAL_COLUMNS
+    );
+    let rows = conn.query(&query, &[&limit]).await?;
+
+    rows.iter()
+        .map(|row| Ok(row_to_proposal(row)?.to_json()))
+        .collect()
+}
@@ -1,6 +1,20 @@
+#![allow(clippy::module_inception)]
+
 pub mod recommendation;
 pub mod proposal_snapchot;
 pub mod config;
 pub mod api;
 pub mod scheduler;
-pub mod voting;
\ No newline at end of file
+pub mod voting;
+pub mod contracts;
+pub mod voting_power;
+pub mod signer;
+pub mod identity;
+pub mod delegation;
+pub mod draft;
+pub mod forum;
+pub mod ipfs;
+pub mod verification;
+pub mod migration;
+pub mod store;
+pub mod http_client;
@@ -0,0 +1,156 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use log::info;
+use std::{error::Error, sync::Arc};
+use tokio_postgres::NoTls;
+
+/// One step of the schema, applied once and recorded in `schema_version`.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Every migration, in the order they are applied. Released migrations are
+/// never edited; changes go into a new one.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "proposals_and_recommendations",
+        sql: include_str!("../../migrations/0001_proposals_and_recommendations.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "recommendation_review",
+        sql: include_str!("../../migrations/0002_recommendation_review.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "vote_actions",
+        sql: include_str!("../../migrations/0003_vote_actions.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "voting_power",
+        sql: include_str!("../../migrations/0004_voting_power.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "delegation_actions",
+        sql: include_str!("../../migrations/0005_delegation_actions.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "proposal_drafts",
+        sql: include_str!("../../migrations/0006_proposal_drafts.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "discussions",
+        sql: include_str!("../../migrations/0007_discussions.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "proposal_verification",
+        sql: include_str!("../../migrations/0008_proposal_verification.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "foreign_keys",
+        sql: include_str!("../../migrations/0009_foreign_keys.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "numeric_proposal_counts",
+        sql: include_str!("../../migrations/0010_numeric_proposal_counts.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "proposal_payloads",
+        sql: include_str!("../../migrations/0011_proposal_payloads.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "proposal_revisions",
+        sql: include_str!("../../migrations/0012_proposal_revisions.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "watched_spaces",
+        sql: include_str!("../../migrations/0013_watched_spaces.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "backfill_checkpoints",
+        sql: include_str!("../../migrations/0014_backfill_checkpoints.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "proposal_deleted_at",
+        sql: include_str!("../../migrations/0015_proposal_deleted_at.sql"),
+    },
+    Migration {
+        version: 16,
+        name: "delegator_scans",
+        sql: include_str!("../../migrations/0016_delegator_scans.sql"),
+    },
+];
+
+const SCHEMA_VERSION_DDL: &str = r#"
+    CREATE TABLE IF NOT EXISTS schema_version (
+        version INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        applied_at TIMESTAMP NOT NULL DEFAULT NOW()
+    );
+"#;
+
+/// Advisory lock held while migrating, so two instances starting together do
+/// not apply the same migration twice.
+const MIGRATION_LOCK: i64 = 0x006d_6967_7261_7465;
+
+/// Applies the migrations that are not recorded in `schema_version` yet, in a
+/// single transaction, and returns the versions it applied.
+pub async fn run_migrations(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+) -> Result<Vec<i32>, Box<dyn Error + Send + Sync>> {
+    let mut conn = db_client.get().await?;
+    conn.batch_execute(SCHEMA_VERSION_DDL).await?;
+
+    let tx = conn.transaction().await?;
+    tx.execute("SELECT pg_advisory_xact_lock($1)", &[&MIGRATION_LOCK])
+        .await?;
+    let applied: Vec<i32> = tx
+        .query("SELECT version FROM schema_version", &[])
+        .await?
+        .into_iter()
+        .map(|row| row.get("version"))
+        .collect();
+
+    let mut newly_applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| !applied.contains(&m.version)) {
+        tx.batch_execute(migration.sql)
+            .await
+            .map_err(|err| format!("Migration {} ({}) failed: {}", migration.version, migration.name, err))?;
+        tx.execute(
+            "INSERT INTO schema_version (version, name) VALUES ($1, $2)",
+            &[&migration.version, &migration.name],
+        )
+        .await?;
+        info!("Applied migration {} ({})", migration.version, migration.name);
+        newly_applied.push(migration.version);
+    }
+    tx.commit().await?;
+    Ok(newly_applied)
+}
+
+/// Highest migration recorded in `schema_version`.
+pub async fn schema_version(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+) -> Result<Option<i32>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_one("SELECT MAX(version) AS version FROM schema_version", &[])
+        .await?;
+    Ok(row.get("version"))
+}
@@ -0,0 +1,224 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use chrono::NaiveDate;
+use std::{
+    error::Error,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio_postgres::NoTls;
+
+use crate::proposal_snapchot::{
+    bulk::{PartialSync, SyncReport},
+    collector::{fetch_proposals_page, invalidate_changed, ProposalFilter, BATCH_SIZE},
+    hub::HubClient,
+};
+use crate::store::store::Stores;
+
+/// Pause between pages, to stay under the hub's rate limit; a 429 is waited
+/// out by `HubClient`.
+const PAGE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Proposals of a space created in `[from, to)`, UTC days.
+#[derive(Debug, Clone)]
+pub struct BackfillRange {
+    pub space: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl BackfillRange {
+    /// Reads `--space X --from YYYY-MM-DD --to YYYY-MM-DD`.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let (mut space, mut from, mut to) = (None, None, None);
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+            match flag.as_str() {
+                "--space" => space = Some(value),
+                "--from" => from = Some(parse_date(&value)?),
+                "--to" => to = Some(parse_date(&value)?),
+                _ => return Err(format!("Unknown backfill argument: {}", flag).into()),
+            }
+        }
+        let range = BackfillRange {
+            space: space.ok_or("--space is required")?,
+            from: from.ok_or("--from is required")?,
+            to: to.ok_or("--to is required")?,
+        };
+        if range.from >= range.to {
+            return Err("--from must be before --to".into());
+        }
+        Ok(range)
+    }
+
+    fn bounds(&self) -> (i64, i64) {
+        let timestamp = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp();
+        (timestamp(self.from), timestamp(self.to))
+    }
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, Box<dyn Error + Send + Sync>> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date {}: {}", value, e).into())
+}
+
+/// Where a backfill of a window stands.
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    /// `created` of the last stored proposal; the next page starts after it.
+    cursor: i64,
+    proposals: i64,
+    batches: i64,
+    completed: bool,
+}
+
+/// The checkpoint of the window, created when the window is new.
+async fn start_checkpoint(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    space: &str,
+    from: i64,
+    to: i64,
+) -> Result<Checkpoint, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    // `created_gt` is exclusive, so the window opens one second earlier.
+    let row = conn
+        .query_one(
+            r#"
+            INSERT INTO backfill_checkpoints (space_id, from_ts, to_ts, cursor)
+            VALUES ($1, $2, $3, $2 - 1)
+            ON CONFLICT (space_id, from_ts, to_ts) DO UPDATE SET updated_at = NOW()
+            RETURNING cursor, proposals, batches, completed_at IS NOT NULL AS completed
+            "#,
+            &[&space, &from, &to],
+        )
+        .await?;
+    Ok(Checkpoint {
+        cursor: row.get("cursor"),
+        proposals: row.get("proposals"),
+        batches: row.get("batches"),
+        completed: row.get("completed"),
+    })
+}
+
+async fn save_checkpoint(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    range: &BackfillRange,
+    checkpoint: &Checkpoint,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (from, to) = range.bounds();
+    let conn = db_client.get().await?;
+    conn.execute(
+        r#"
+        UPDATE backfill_checkpoints SET
+            cursor = $4,
+            proposals = $5,
+            batches = $6,
+            updated_at = NOW(),
+            completed_at = CASE WHEN $7 THEN NOW() END
+        WHERE space_id = $1 AND from_ts = $2 AND to_ts = $3
+        "#,
+        &[
+            &range.space,
+            &from,
+            &to,
+            &checkpoint.cursor,
+            &checkpoint.proposals,
+            &checkpoint.batches,
+            &checkpoint.completed,
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// Pulls the proposals of a space created within a window, page by page.
+///
+/// Progress is checkpointed after every committed page: running the same
+/// window again resumes where an interrupted run stopped, and a completed
+/// window is not fetched again. Edits committed before a failure still
+/// invalidate their recommendations.
+pub async fn run_backfill(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    stores: &Stores,
+    hub: &HubClient,
+    range: &BackfillRange,
+) -> Result<SyncReport, Box<dyn Error + Send + Sync>> {
+    let (from, to) = range.bounds();
+    let mut checkpoint = start_checkpoint(db_client, &range.space, from, to).await?;
+    if checkpoint.completed {
+        println!(
+            "Backfill of {} from {} to {} already completed: {} proposals in {} batches",
+            range.space, range.from, range.to, checkpoint.proposals, checkpoint.batches
+        );
+        return Ok(SyncReport::default());
+    }
+    if checkpoint.batches > 0 {
+        println!(
+            "Resuming backfill of {} after {} proposals in {} batches",
+            range.space, checkpoint.proposals, checkpoint.batches
+        );
+    }
+
+    let mut report = SyncReport::default();
+    let started = Instant::now();
+    let result = backfill_pages(db_client, stores, hub, range, &mut checkpoint, &mut report).await;
+    report.elapsed = started.elapsed();
+    invalidate_changed(stores, report.changed.clone()).await?;
+    if let Err(error) = result {
+        return Err(PartialSync { report, error }.into());
+    }
+
+    checkpoint.completed = true;
+    save_checkpoint(db_client, range, &checkpoint).await?;
+    Ok(report)
+}
+
+async fn backfill_pages(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    stores: &Stores,
+    hub: &HubClient,
+    range: &BackfillRange,
+    checkpoint: &mut Checkpoint,
+    report: &mut SyncReport,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (from, to) = range.bounds();
+    let spaces = [range.space.clone()];
+    let filter = ProposalFilter {
+        spaces: Some(&spaces),
+        created_before: Some(to),
+    };
+    let started = Instant::now();
+    loop {
+        let page = fetch_proposals_page(hub, "created", checkpoint.cursor, &filter).await?;
+        let Some(next) = page.last().and_then(|last| last.created) else {
+            return Ok(());
+        };
+        let changed = stores.proposals.upsert(&page).await?;
+        report.batches += 1;
+        report.proposals += page.len() as u64;
+        report.changed.extend(changed);
+        report.elapsed = started.elapsed();
+
+        let advanced = next > checkpoint.cursor;
+        checkpoint.cursor = checkpoint.cursor.max(next);
+        checkpoint.proposals += page.len() as i64;
+        checkpoint.batches += 1;
+        save_checkpoint(db_client, range, checkpoint).await?;
+        let done = (checkpoint.cursor - from) as f64 / (to - from) as f64;
+        println!(
+            "Backfill of {}: {} proposals in {} batches, up to {} ({:.1}%), {:.0}/s",
+            range.space,
+            checkpoint.proposals,
+            checkpoint.batches,
+            chrono::DateTime::from_timestamp(checkpoint.cursor, 0).unwrap_or_default(),
+            done.clamp(0.0, 1.0) * 100.0,
+            report.rate()
+        );
+
+        if !advanced || page.len() < BATCH_SIZE as usize {
+            return Ok(());
+        }
+        tokio::time::sleep(PAGE_INTERVAL).await;
+    }
+}
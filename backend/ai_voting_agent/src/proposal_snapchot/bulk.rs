@@ -0,0 +1,207 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use std::{collections::HashMap, error::Error, fmt, sync::Arc, time::Duration};
+use tokio_postgres::{
+    binary_copy::BinaryCopyInWriter,
+    types::{Json, Type},
+    NoTls,
+};
+
+use crate::proposal_snapchot::{
+    collector::{
+        existing_proposals, naive_timestamp, record_revisions, PROPOSAL_UPSERT_SET,
+        PROPOSAL_WRITE_COLUMNS,
+    },
+    prop_struct::Proposal,
+};
+
+/// Throughput of a sync, accumulated over its batches.
+#[derive(Debug, Default, Clone)]
+pub struct SyncReport {
+    pub batches: u64,
+    pub proposals: u64,
+    pub elapsed: Duration,
+    /// Known proposals with material edits.
+    pub changed: Vec<String>,
+}
+
+impl SyncReport {
+    /// Proposals written per second.
+    pub fn rate(&self) -> f64 {
+        self.proposals as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn merge(&mut self, other: SyncReport) {
+        self.batches += other.batches;
+        self.proposals += other.proposals;
+        self.elapsed += other.elapsed;
+        self.changed.extend(other.changed);
+    }
+}
+
+/// A sync that stopped on an error; `report` covers the batches committed
+/// before it.
+#[derive(Debug)]
+pub struct PartialSync {
+    pub report: SyncReport,
+    pub error: Box<dyn Error + Send + Sync>,
+}
+
+impl fmt::Display for PartialSync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Sync stopped after {} proposals in {} batches: {}",
+            self.report.proposals, self.report.batches, self.error
+        )
+    }
+}
+
+impl Error for PartialSync {}
+
+/// Column types of the staging table, in `PROPOSAL_WRITE_COLUMNS` order
+/// followed by `version` and `raw_payload`.
+const STAGING_TYPES: &[Type] = &[
+    Type::TEXT,      // id
+    Type::TEXT,      // ipfs
+    Type::JSONB,     // space
+    Type::TEXT,      // type
+    Type::TEXT,      // title
+    Type::TEXT,      // body
+    Type::TEXT,      // discussion
+    Type::TEXT,      // author
+    Type::FLOAT8,    // quorum
+    Type::TEXT,      // quorum_type
+    Type::TIMESTAMP, // start
+    Type::TIMESTAMP, // end
+    Type::TEXT,      // snapshot
+    Type::JSONB,     // choices
+    Type::JSONB,     // labels
+    Type::JSONB,     // scores
+    Type::FLOAT8,    // scores_total
+    Type::TEXT,      // scores_state
+    Type::TEXT,      // state
+    Type::JSONB,     // strategies
+    Type::TIMESTAMP, // created
+    Type::TIMESTAMP, // updated
+    Type::INT8,      // votes
+    Type::TEXT,      // privacy
+    Type::JSONB,     // plugins
+    Type::BOOL,      // flagged
+    Type::INT8,      // version
+    Type::JSONB,     // raw_payload
+];
+
+/// Upserts a batch of proposals in one transaction: rows are streamed with
+/// `COPY` into a temporary staging table and merged with a single
+/// `INSERT ... ON CONFLICT`. Revisions are recorded as in `upsert_proposals`.
+/// Returns the ids of known proposals with material edits.
+pub async fn bulk_upsert_proposals(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposals: &[Proposal],
+) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+    // A merge cannot touch the same row twice; the last copy of a proposal wins.
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    let mut batch: Vec<&Proposal> = Vec::with_capacity(proposals.len());
+    for proposal in proposals {
+        match positions.get(proposal.id.as_str()) {
+            Some(&idx) => batch[idx] = proposal,
+            None => {
+                positions.insert(&proposal.id, batch.len());
+                batch.push(proposal);
+            }
+        }
+    }
+    if batch.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut conn = db_client.get().await?;
+    let transaction = conn.transaction().await?;
+    let ids: Vec<&String> = batch.iter().map(|p| &p.id).collect();
+    let existing = existing_proposals(&transaction, &ids).await?;
+
+    transaction
+        .batch_execute(
+            r#"
+            CREATE TEMP TABLE proposals_staging
+                (LIKE proposals INCLUDING DEFAULTS, version BIGINT, raw_payload JSONB)
+                ON COMMIT DROP
+            "#,
+        )
+        .await?;
+    let sink = transaction
+        .copy_in(&format!(
+            "COPY proposals_staging ({}, version, raw_payload) FROM STDIN (FORMAT binary)",
+            PROPOSAL_WRITE_COLUMNS
+        ))
+        .await?;
+    let writer = BinaryCopyInWriter::new(sink, STAGING_TYPES);
+    let mut writer = std::pin::pin!(writer);
+    for proposal in &batch {
+        writer
+            .as_mut()
+            .write(&[
+                &proposal.id,
+                &proposal.ipfs,
+                &proposal.space.as_ref().map(Json),
+                &proposal.proposal_type,
+                &proposal.title,
+                &proposal.body,
+                &proposal.discussion,
+                &proposal.author,
+                &proposal.quorum,
+                &proposal.quorum_type,
+                &naive_timestamp(proposal.start),
+                &naive_timestamp(proposal.end),
+                &proposal.snapshot,
+                &Json(&proposal.choices),
+                &Json(&proposal.labels),
+                &Json(&proposal.scores),
+                &proposal.scores_total,
+                &proposal.scores_state,
+                &proposal.state,
+                &Json(&proposal.strategies),
+                &naive_timestamp(proposal.created),
+                &naive_timestamp(proposal.updated),
+                &proposal.votes,
+                &proposal.privacy,
+                &proposal.plugins,
+                &proposal.flagged,
+                &proposal.version(),
+                &proposal.raw_payload,
+            ])
+            .await?;
+    }
+    writer.finish().await?;
+
+    transaction
+        .execute(
+            &format!(
+                "INSERT INTO proposals ({cols}) SELECT {cols} FROM proposals_staging
+                 ON CONFLICT (id) DO UPDATE SET {set}",
+                cols = PROPOSAL_WRITE_COLUMNS,
+                set = PROPOSAL_UPSERT_SET
+            ),
+            &[],
+        )
+        .await?;
+    transaction
+        .execute(
+            r#"
+            INSERT INTO proposal_payloads (proposal_id, version, raw_payload)
+            SELECT id, version, raw_payload FROM proposals_staging
+            WHERE raw_payload IS NOT NULL
+            ON CONFLICT (proposal_id, version) DO UPDATE SET
+                raw_payload = EXCLUDED.raw_payload,
+                fetched_at = NOW()
+            "#,
+            &[],
+        )
+        .await?;
+
+    let batch: Vec<Proposal> = batch.into_iter().cloned().collect();
+    let changed = record_revisions(&transaction, &existing, &batch).await?;
+    transaction.commit().await?;
+    Ok(changed)
+}
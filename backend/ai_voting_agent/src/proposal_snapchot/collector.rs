@@ -1,31 +1,50 @@
-use crate::proposal_snapchot::prop_struct::Proposal;
+use crate::proposal_snapchot::{
+    bulk::{PartialSync, SyncReport},
+    hub::HubClient,
+    prop_struct::Proposal,
+    repository::{row_to_proposal, PROPOSAL_COLUMNS},
+    revision::{diff_proposals, save_revision},
+    watchlist::{get_watched_spaces, mark_backfilled, WatchedSpace},
+};
+use crate::config::config::Config;
+use crate::store::store::Stores;
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
-use chrono::DateTime;
-use log::{error, info};
-use reqwest::Client as HttpClient;
+use chrono::{DateTime, NaiveDateTime};
+use log::{error, info, warn};
 use serde_json::{json, Value};
-use std::{collections::HashMap, error::Error, sync::Arc};
-use tokio_postgres::NoTls;
+use std::{collections::HashMap, error::Error, sync::Arc, time::Instant};
+use tokio_postgres::{types::Json, GenericClient, NoTls};
 
 /// URL GraphQL
-const GRAPHQL_URL: &str = "https://hub.snapshot.org/graphql";
+pub const GRAPHQL_URL: &str = "https://hub.snapshot.org/graphql";
 
-const BATCH_SIZE: i64 = 1000;
+/// Proposals per hub page.
+pub const BATCH_SIZE: i64 = 1000;
 
 const PROPOSALS_QUERY_TEMPLATE: &str = r#"
-query Proposals($first: Int!, $start: Int!) {
+query Proposals($first: Int!, $start: Int!filter_args) {
   proposals(
     first: $first
-     where: { replace_key: $start }
-    orderBy: "created"
+     where: { replace_key: $start filter_where }
+    orderBy: "order_key"
     orderDirection: asc
   ) {
     ...offchainProposalFragment
     start
   }
 }
+"#;
+
+const PROPOSAL_QUERY: &str = r#"
+query Proposal($id: String!) {
+  proposal(id: $id) {
+    ...offchainProposalFragment
+  }
+}
+"#;
 
+const PROPOSAL_FRAGMENT: &str = r#"
 fragment offchainProposalFragment on Proposal {
   id
   ipfs
@@ -71,201 +90,493 @@ fragment offchainProposalFragment on Proposal {
 "#;
 
 pub fn proposals_query_create() -> String {
-    PROPOSALS_QUERY_TEMPLATE.replace("replace_key", "created_gt")
+    PROPOSALS_QUERY_TEMPLATE
+        .replace("replace_key", "created_gt")
+        .replace("order_key", "created")
+        + PROPOSAL_FRAGMENT
 }
 
 pub fn proposals_query_update() -> String {
-    PROPOSALS_QUERY_TEMPLATE.replace("replace_key", "updated_gt")
+    PROPOSALS_QUERY_TEMPLATE
+        .replace("replace_key", "updated_gt")
+        .replace("order_key", "updated")
+        + PROPOSAL_FRAGMENT
+}
+
+/// Narrows which proposals a sync pulls from the hub.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProposalFilter<'a> {
+    /// Only these spaces; every space when `None`.
+    pub spaces: Option<&'a [String]>,
+    /// Only proposals created before this Unix time.
+    pub created_before: Option<i64>,
+}
+
+impl<'a> ProposalFilter<'a> {
+    pub fn spaces(spaces: Option<&'a [String]>) -> Self {
+        ProposalFilter { spaces, created_before: None }
+    }
 }
 
-pub fn proposals_query(key: &str) -> String {
-    match key {
+/// The proposals query by `key`, with the `where` arguments `filter` needs.
+pub fn proposals_query(key: &str, filter: &ProposalFilter) -> String {
+    let query = match key {
         "created" => proposals_query_create(),
         "updated" => proposals_query_update(),
         _ => panic!("Unknown key: {}", key),
+    };
+    let (mut args, mut conditions) = (String::new(), String::new());
+    if filter.spaces.is_some() {
+        args.push_str(", $spaces: [String]");
+        conditions.push_str(", space_in: $spaces");
     }
+    if filter.created_before.is_some() {
+        args.push_str(", $end: Int!");
+        conditions.push_str(", created_lt: $end");
+    }
+    query.replace("filter_args", &args).replace("filter_where", &conditions)
 }
 
-pub async fn get_proposals(
-    max_value: i64,
+/// Fetches one page of proposals whose `key` (`created` or `updated`) is
+/// after `from`, in `key` order, narrowed by `filter`. Proposals the model
+/// cannot read are logged and left out.
+pub async fn fetch_proposals_page(
+    hub: &HubClient,
     key: &str,
+    from: i64,
+    filter: &ProposalFilter<'_>,
 ) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
-    let http_client = HttpClient::new();
-    let mut new_proposals: Vec<Proposal> = Vec::new();
-    let mut last_start = max_value;
-    loop {
-        let query = proposals_query(key);
-
-        let variables = json!({
-            "first": BATCH_SIZE,
-            "start": last_start
-        });
-        let body = json!({
-            "query": query,
-            "variables": variables
-        });
-        let response = http_client.post(GRAPHQL_URL).json(&body).send().await?;
-        
-        if !response.status().is_success() {
-            error!(
-                "GraphQL query for new proposals failed with status: {}",
-                response.status()
-            );
-            break;
-        }
-        let resp_json: Value = response.json().await?;
-        if resp_json.get("errors").is_some() {
-            error!(
-                "GraphQL errors for new proposals: {:?}",
-                resp_json.get("errors")
-            );
-            break;
-        }
-        let proposals_array = resp_json
-            .get("data")
-            .and_then(|data| data.get("proposals"))
-            .and_then(|arr| arr.as_array())
-            .ok_or("No proposals array in new proposals response")?;
-        if proposals_array.is_empty() {
-            info!("No new proposals found; ending collection of new proposals.");
-            break;
-        }
-        for p in proposals_array {
-            if let Some(proposal) = Proposal::from_json(p) {
+    let variables = json!({
+        "first": BATCH_SIZE,
+        "start": from,
+        "spaces": filter.spaces,
+        "end": filter.created_before
+    });
+    let data = hub.query(&proposals_query(key, filter), variables).await?;
+    let proposals_array = data
+        .get("proposals")
+        .and_then(|arr| arr.as_array())
+        .ok_or("No proposals array in new proposals response")?;
 
-                new_proposals.push(proposal);
-            }
-        }
-        if let Some(last) = proposals_array.last() {
-            if let Some(ts) = last.get(key).and_then(|v| v.as_i64()) {
-                last_start = ts;
-            }
-        }
-        if proposals_array.len() < BATCH_SIZE as usize {
-            break;
+    let mut proposals = Vec::with_capacity(proposals_array.len());
+    for p in proposals_array {
+        match Proposal::from_json(p) {
+            Ok(proposal) => proposals.push(proposal),
+            Err(e) => error!("Could not read proposal {}: {}", p["id"], e),
         }
     }
-    info!("Found {} proposals", new_proposals.len());
+    Ok(proposals)
+}
+
+/// Fetches one proposal by id; `None` when the hub does not know it.
+pub async fn fetch_proposal(
+    hub: &HubClient,
+    proposal_id: &str,
+) -> Result<Option<Proposal>, Box<dyn Error + Send + Sync>> {
+    let query = format!("{}{}", PROPOSAL_QUERY, PROPOSAL_FRAGMENT);
+    let data = hub.query(&query, json!({ "id": proposal_id })).await?;
+    match data.get("proposal") {
+        Some(p) if !p.is_null() => Ok(Some(Proposal::from_json(p)?)),
+        _ => Ok(None),
+    }
+}
 
-    Ok(new_proposals)
+/// Marks a proposal deleted on the hub: it is no longer active, and its
+/// recommendations and scheduled votes are dropped. Returns false when the
+/// proposal is unknown or already marked.
+pub async fn mark_proposal_deleted(
+    stores: &Stores,
+    proposal_id: &str,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    if !stores.proposals.mark_deleted(proposal_id).await? {
+        return Ok(false);
+    }
+    let ids = [proposal_id.to_string()];
+    let invalidated = stores.recommendations.invalidate(&ids, "proposal deleted").await?;
+    let cancelled = stores.votes.cancel_pending(&ids).await?;
+    info!(
+        "Proposal {} deleted: {} recommendations invalidated, {} scheduled votes cancelled",
+        proposal_id, invalidated, cancelled
+    );
+    Ok(true)
 }
 
-pub async fn collect_new_proposals(
+/// The latest `key` (`created` or `updated`) stored, where the next sync starts.
+pub async fn last_synced(
     db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
     key: &str,
-) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+) -> Result<i64, Box<dyn Error + Send + Sync>> {
     let db_client = db_client.get().await?;
     let query = format!("SELECT COALESCE(MAX(EXTRACT(EPOCH FROM \"{}\"))::INT8, 0) AS max_value FROM proposals", key);
-    // Извлекаем максимальное значение "start" из базы
-    let row = db_client
-        .query_one(
-            query.as_str(),
-            &[],
-        )
-        .await?;
-    let max_value: i64 = row.get("max_value");
+    let row = db_client.query_one(query.as_str(), &[]).await?;
+    Ok(row.get("max_value"))
+}
 
+/// Pulls every proposal whose `key` is after `from` from the hub, page by
+/// page, narrowed by `filter`. Each page goes through the bulk upsert and is
+/// committed on its own, so an interrupted sync resumes where it stopped; the
+/// error then carries what was committed before it.
+pub async fn sync_proposals(
+    stores: &Stores,
+    hub: &HubClient,
+    key: &str,
+    from: i64,
+    filter: &ProposalFilter<'_>,
+) -> Result<SyncReport, PartialSync> {
+    let mut report = SyncReport::default();
+    let started = Instant::now();
+    info!("Collecting proposals by {}: start from = {}", key, from);
+    let result = sync_pages(stores, hub, key, from, filter, &mut report).await;
+    report.elapsed = started.elapsed();
+    match result {
+        Ok(()) => Ok(report),
+        Err(error) => Err(PartialSync { report, error }),
+    }
+}
+
+async fn sync_pages(
+    stores: &Stores,
+    hub: &HubClient,
+    key: &str,
+    from: i64,
+    filter: &ProposalFilter<'_>,
+    report: &mut SyncReport,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let started = Instant::now();
+    let mut last = from;
+    loop {
+        let page = fetch_proposals_page(hub, key, last, filter).await?;
+        let Some(page_last) = page.last() else {
+            return Ok(());
+        };
+        let next = match key {
+            "updated" => page_last.updated,
+            _ => page_last.created,
+        };
 
-    let new_proposals = get_proposals(max_value, key).await?;
-    info!("Collecting new proposals: start from = {}", max_value);
+        let batch_started = Instant::now();
+        let changed = stores.proposals.upsert(&page).await?;
+        report.batches += 1;
+        report.proposals += page.len() as u64;
+        report.changed.extend(changed);
+        let batch_secs = batch_started.elapsed().as_secs_f64();
+        report.elapsed = started.elapsed();
+        info!(
+            "Synced batch {} by {}: {} proposals in {:.2}s ({:.0}/s), {} total at {:.0}/s",
+            report.batches,
+            key,
+            page.len(),
+            batch_secs,
+            page.len() as f64 / batch_secs.max(f64::EPSILON),
+            report.proposals,
+            report.rate()
+        );
 
-    Ok(new_proposals)
+        match next {
+            Some(next) if next > last => last = next,
+            _ => return Ok(()),
+        }
+        if page.len() < BATCH_SIZE as usize {
+            return Ok(());
+        }
+    }
 }
 
+/// Pulls the whole history of watched spaces that were not backfilled yet.
+/// A space whose backfill fails is retried by the next collection; the
+/// batches committed before the failure are still reported.
+pub async fn backfill_watched_spaces(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    stores: &Stores,
+    hub: &HubClient,
+    watched: &[WatchedSpace],
+) -> SyncReport {
+    let mut report = SyncReport::default();
+    for space in watched.iter().filter(|space| space.backfilled_at.is_none()) {
+        info!("Backfilling proposals of newly watched space {}", space.space_id);
+        let spaces = [space.space_id.clone()];
+        let filter = ProposalFilter::spaces(Some(&spaces));
+        let backfill = match sync_proposals(stores, hub, "created", 0, &filter).await {
+            Ok(backfill) => backfill,
+            Err(partial) => {
+                error!("Backfill of space {} failed: {}", space.space_id, partial);
+                report.merge(partial.report);
+                continue;
+            }
+        };
+        info!(
+            "Backfilled {} proposals of space {}",
+            backfill.proposals, space.space_id
+        );
+        report.merge(backfill);
+        if let Err(e) = mark_backfilled(db_client, &space.space_id).await {
+            error!("Could not mark space {} as backfilled: {}", space.space_id, e);
+        }
+    }
+    report
+}
 
+/// Syncs proposals of the watched spaces, or of every space in
+/// `COLLECT_ALL_SPACES` mode, after backfilling newly watched spaces.
+///
+/// A sync that stops early is an error, but the edits it already committed
+/// still invalidate their recommendations.
 pub async fn run_collect(
     db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    stores: &Stores,
+    config: &Config,
+    hub: &HubClient,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let proposals = collect_proposals(db_client).await?;
-    let _ = upsert_proposals(db_client, &proposals).await?;
+    // Both starting points are read first: the `created` pass moves MAX(updated).
+    let created_from = last_synced(db_client, "created").await?;
+    let updated_from = last_synced(db_client, "updated").await?;
+
+    let watched = get_watched_spaces(db_client).await?;
+    let mut report = backfill_watched_spaces(db_client, stores, hub, &watched).await;
+    let space_ids: Vec<String> = watched.into_iter().map(|space| space.space_id).collect();
+    let filter = ProposalFilter::spaces(match config.collect_all_spaces {
+        true => None,
+        false if space_ids.is_empty() => {
+            warn!("Space watchlist is empty, nothing to collect; add spaces or set COLLECT_ALL_SPACES");
+            return Ok(());
+        }
+        false => Some(space_ids.as_slice()),
+    });
+    let mut failure = None;
+    for (key, from) in [("created", created_from), ("updated", updated_from)] {
+        match sync_proposals(stores, hub, key, from, &filter).await {
+            Ok(synced) => report.merge(synced),
+            Err(partial) => {
+                report.merge(partial.report);
+                failure = Some(partial.error);
+                break;
+            }
+        }
+    }
+    info!(
+        "Collected {} proposals in {} batches, {:.1}s ({:.0}/s)",
+        report.proposals,
+        report.batches,
+        report.elapsed.as_secs_f64(),
+        report.rate()
+    );
+    invalidate_changed(stores, report.changed.clone()).await?;
+    match failure {
+        Some(error) => Err(PartialSync { report, error }.into()),
+        None => Ok(()),
+    }
+}
+
+/// Invalidates the recommendations and cancels the scheduled votes of
+/// proposals with material edits.
+pub async fn invalidate_changed(
+    stores: &Stores,
+    mut changed: Vec<String>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    changed.sort();
+    changed.dedup();
+    if !changed.is_empty() {
+        let invalidated = stores
+            .recommendations
+            .invalidate(&changed, "material proposal edit")
+            .await?;
+        let cancelled = stores.votes.cancel_pending(&changed).await?;
+        info!(
+            "{} proposals changed: {} recommendations invalidated, {} scheduled votes cancelled",
+            changed.len(), invalidated, cancelled
+        );
+    }
     Ok(())
 }
 
+/// Current rows of the given proposals, by id.
+pub async fn existing_proposals(
+    client: &impl GenericClient,
+    ids: &[&String],
+) -> Result<HashMap<String, Proposal>, tokio_postgres::Error> {
+    let query = format!("SELECT {} FROM proposals WHERE id = ANY($1)", PROPOSAL_COLUMNS);
+    let mut existing = HashMap::new();
+    for row in client.query(&query, &[&ids]).await? {
+        let proposal = row_to_proposal(&row)?;
+        existing.insert(proposal.id.clone(), proposal);
+    }
+    Ok(existing)
+}
 
-pub async fn collect_proposals(
-    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
-) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
-    let new_props = collect_new_proposals(db_client,  "created").await?;
-    let updated_props = collect_new_proposals(db_client,  "updated").await?;
-    
-    let mut proposals_map: HashMap<String, Proposal> = HashMap::new();
-    for proposal in new_props.into_iter().chain(updated_props.into_iter()) {
-        proposals_map.insert(proposal.id.clone(), proposal);
+/// Records a revision for every proposal that differs from its `existing`
+/// row and returns the ids of the ones with material edits.
+pub async fn record_revisions(
+    client: &impl GenericClient,
+    existing: &HashMap<String, Proposal>,
+    proposals: &[Proposal],
+) -> Result<Vec<String>, tokio_postgres::Error> {
+    let mut changed = Vec::new();
+    for proposal in proposals {
+        let Some(previous) = existing.get(&proposal.id) else {
+            continue;
+        };
+        let mut diff = diff_proposals(previous, proposal);
+        // A deleted proposal stays deleted whatever state the hub still reports.
+        if previous.state.as_deref() == Some("deleted") {
+            diff.retain(|change| change.field != "state");
+        }
+        if diff.is_empty() {
+            continue;
+        }
+        let revision = save_revision(client, proposal, &diff).await?;
+        if revision.material {
+            changed.push(proposal.id.clone());
+        }
+        if let Some(recommendation_id) = revision.flagged_recommendation_id() {
+            warn!(
+                "ALERT: proposal {} was edited ({}) after recommendation {}",
+                proposal.id,
+                revision.fields().join(", "),
+                recommendation_id
+            );
+        }
     }
-    let proposals_to_upsert: Vec<Proposal> = proposals_map
-        .into_iter()
-        .map(|(_id, proposal)| proposal)
-        .collect();
-    info!("Total proposals to upsert: {}", proposals_to_upsert.len());
-    Ok(proposals_to_upsert)
+    Ok(changed)
 }
 
+/// Upserts proposals, records a revision for every known proposal that changed
+/// and returns the ids of the ones with material edits.
 pub async fn upsert_proposals(
     db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
     proposals: &[Proposal],
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
     if proposals.is_empty() {
         info!("No proposals to upsert.");
-        return Ok(());
+        return Ok(Vec::new());
     }
     let mut db_client = db_client.get().await?;
     let transaction = db_client.transaction().await?;
 
+    let ids: Vec<&String> = proposals.iter().map(|p| &p.id).collect();
+    let existing = existing_proposals(&transaction, &ids).await?;
     for proposal in proposals.iter() {
-        let updated  = proposal.updated.and_then(|ts| DateTime::from_timestamp(ts, 0));
-        let start  = proposal.start.and_then(|ts| DateTime::from_timestamp(ts, 0));
-        let end = proposal.end.and_then(|ts| DateTime::from_timestamp(ts, 0));
-        let created  = proposal.created.and_then(|ts| DateTime::from_timestamp(ts, 0));
-        transaction.execute(
-            "INSERT INTO proposals (
-                id, ipfs, space, \"type\", title, body, discussion, author, quorum, quorum_type,
-                \"start\", \"end\", snapshot, choices, labels, scores, scores_total, scores_state, state,
-                strategies, created, updated, votes, privacy, plugins, flagged
-            ) VALUES (
-                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
-                $11, $12, $13, $14, $15, $16, $17, $18, $19,
-                $20, $21, $22, $23, $24, $25, $26
-            )
-            ON CONFLICT (id) DO UPDATE SET
-                space = EXCLUDED.space,
-                state = EXCLUDED.state,
-                discussion = EXCLUDED.discussion,
-                scores = EXCLUDED.scores,
-                scores_total = EXCLUDED.scores_total, 
-                scores_state = EXCLUDED.scores_state, 
-                votes = EXCLUDED.votes,
-                updated = EXCLUDED.updated",
-            &[
-                &proposal.id,
-                &proposal.ipfs,
-                &proposal.space,
-                &proposal.proposal_type,
-                &proposal.title,
-                &proposal.body,
-                &proposal.discussion,
-                &proposal.author,
-                &proposal.quorum,
-                &proposal.quorum_type,
-                &start.map(|dt| dt.naive_utc()),
-                &end.map(|dt| dt.naive_utc()),
-                &proposal.snapshot,
-                &proposal.choices,
-                &proposal.labels,
-                &proposal.scores,
-                &proposal.scores_total,
-                &proposal.scores_state,
-                &proposal.state,
-                &proposal.strategies,
-                &created.map(|dt| dt.naive_utc()),
-                &updated.map(|dt| dt.naive_utc()),
-                &proposal.votes,
-                &proposal.privacy,
-                &proposal.plugins,
-                &proposal.flagged,
-            ]
-        ).await?;
+        write_proposal(&transaction, proposal).await?;
+        if let Some(raw_payload) = &proposal.raw_payload {
+            save_payload(&transaction, &proposal.id, proposal.version(), raw_payload).await?;
+        }
     }
+    let changed = record_revisions(&transaction, &existing, proposals).await?;
     transaction.commit().await?;
+    Ok(changed)
+}
+
+/// A hub Unix time as stored in the TIMESTAMP columns.
+pub fn naive_timestamp(ts: Option<i64>) -> Option<NaiveDateTime> {
+    ts.and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.naive_utc())
+}
+
+/// Hub-derived columns of `proposals`, in the order they are written.
+pub const PROPOSAL_WRITE_COLUMNS: &str = r#"
+    id, ipfs, space, "type", title, body, discussion, author, quorum, quorum_type,
+    "start", "end", snapshot, choices, labels, scores, scores_total, scores_state, state,
+    strategies, created, updated, votes, privacy, plugins, flagged
+"#;
+
+/// Brings every hub-derived column of a known proposal up to date, except
+/// the state of a proposal marked deleted, which stays `deleted`.
+pub const PROPOSAL_UPSERT_SET: &str = r#"
+    ipfs = EXCLUDED.ipfs,
+    space = EXCLUDED.space,
+    "type" = EXCLUDED."type",
+    title = EXCLUDED.title,
+    body = EXCLUDED.body,
+    discussion = EXCLUDED.discussion,
+    author = EXCLUDED.author,
+    quorum = EXCLUDED.quorum,
+    quorum_type = EXCLUDED.quorum_type,
+    "start" = EXCLUDED."start",
+    "end" = EXCLUDED."end",
+    snapshot = EXCLUDED.snapshot,
+    choices = EXCLUDED.choices,
+    labels = EXCLUDED.labels,
+    scores = EXCLUDED.scores,
+    scores_total = EXCLUDED.scores_total,
+    scores_state = EXCLUDED.scores_state,
+    state = CASE WHEN proposals.deleted_at IS NULL THEN EXCLUDED.state ELSE proposals.state END,
+    strategies = EXCLUDED.strategies,
+    created = EXCLUDED.created,
+    updated = EXCLUDED.updated,
+    votes = EXCLUDED.votes,
+    privacy = EXCLUDED.privacy,
+    plugins = EXCLUDED.plugins,
+    flagged = EXCLUDED.flagged
+"#;
+
+/// Inserts a proposal or brings every hub-derived column of a known one up to
+/// date. Review state such as `verified` is kept.
+pub async fn write_proposal(
+    client: &impl GenericClient,
+    proposal: &Proposal,
+) -> Result<(), tokio_postgres::Error> {
+    let query = format!(
+        "INSERT INTO proposals ({}) VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10,
+            $11, $12, $13, $14, $15, $16, $17, $18, $19,
+            $20, $21, $22, $23, $24, $25, $26
+        )
+        ON CONFLICT (id) DO UPDATE SET {}",
+        PROPOSAL_WRITE_COLUMNS, PROPOSAL_UPSERT_SET
+    );
+    client.execute(
+        &query,
+        &[
+            &proposal.id,
+            &proposal.ipfs,
+            &proposal.space.as_ref().map(Json),
+            &proposal.proposal_type,
+            &proposal.title,
+            &proposal.body,
+            &proposal.discussion,
+            &proposal.author,
+            &proposal.quorum,
+            &proposal.quorum_type,
+            &naive_timestamp(proposal.start),
+            &naive_timestamp(proposal.end),
+            &proposal.snapshot,
+            &Json(&proposal.choices),
+            &Json(&proposal.labels),
+            &Json(&proposal.scores),
+            &proposal.scores_total,
+            &proposal.scores_state,
+            &proposal.state,
+            &Json(&proposal.strategies),
+            &naive_timestamp(proposal.created),
+            &naive_timestamp(proposal.updated),
+            &proposal.votes,
+            &proposal.privacy,
+            &proposal.plugins,
+            &proposal.flagged,
+        ]
+    ).await?;
+    Ok(())
+}
+
+/// Keeps the hub object of a proposal version; a refetch of the same version
+/// replaces it.
+pub async fn save_payload(
+    client: &impl GenericClient,
+    proposal_id: &String,
+    version: i64,
+    raw_payload: &Value,
+) -> Result<(), tokio_postgres::Error> {
+    client
+        .execute(
+            r#"
+            INSERT INTO proposal_payloads (proposal_id, version, raw_payload)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (proposal_id, version) DO UPDATE SET
+                raw_payload = EXCLUDED.raw_payload,
+                fetched_at = NOW()
+            "#,
+            &[proposal_id, &version, raw_payload],
+        )
+        .await?;
     Ok(())
 }
 
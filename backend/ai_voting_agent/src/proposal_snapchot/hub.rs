@@ -0,0 +1,291 @@
+use chrono::{DateTime, Utc};
+use log::warn;
+use reqwest::{header::RETRY_AFTER, Client as HttpClient, StatusCode};
+use serde_json::{json, Value};
+use std::{
+    error::Error,
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::config::config::Config;
+
+/// Attempts per query, the first one included.
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Consecutive failed attempts after which the hub is left alone for `BREAKER_COOLDOWN`.
+const BREAKER_THRESHOLD: u32 = 8;
+const BREAKER_COOLDOWN: Duration = Duration::from_secs(300);
+
+#[derive(Debug)]
+pub enum HubError {
+    /// The hub could not be reached, timed out or returned an unreadable answer.
+    Transport(String),
+    /// The hub answered with a non-2xx status other than 429.
+    Status { status: u16, body: String },
+    /// 429 Too Many Requests, still after the last retry.
+    RateLimited { retry_after: Option<Duration> },
+    /// The query reached the hub and was refused.
+    GraphQl(Value),
+    /// Too many recent failures; requests are not sent until the cooldown passes.
+    CircuitOpen { retry_in: Duration },
+}
+
+impl fmt::Display for HubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HubError::Transport(msg) => write!(f, "Hub transport error: {}", msg),
+            HubError::Status { status, body } => write!(f, "Hub answered {}: {}", status, body),
+            HubError::RateLimited { retry_after: Some(wait) } => {
+                write!(f, "Hub rate limit hit, retry after {}s", wait.as_secs())
+            }
+            HubError::RateLimited { retry_after: None } => write!(f, "Hub rate limit hit"),
+            HubError::GraphQl(errors) => write!(f, "Hub GraphQL errors: {}", errors),
+            HubError::CircuitOpen { retry_in } => {
+                write!(f, "Hub circuit open, next attempt in {}s", retry_in.as_secs())
+            }
+        }
+    }
+}
+
+impl Error for HubError {}
+
+/// Reads a `Retry-After` value, given either as seconds or as an HTTP date.
+/// A date in the past means no wait.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&Utc) - now).to_std().unwrap_or_default())
+}
+
+/// How long to wait before retrying a rate-limited query: as long as the hub
+/// asks, or the current backoff when it does not say. `None` when the hub asks
+/// for more than `MAX_BACKOFF`, which is not worth holding the caller for.
+fn rate_limit_wait(retry_after: Option<Duration>, backoff: Duration) -> Option<Duration> {
+    match retry_after {
+        Some(wait) if wait > MAX_BACKOFF => None,
+        Some(wait) => Some(wait),
+        None => Some(backoff),
+    }
+}
+
+/// Consecutive failures, and until when requests are held back.
+#[derive(Debug, Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>,
+}
+
+/// GraphQL client for the Snapshot hub, meant to be built once and shared.
+///
+/// Queries go through the shared HTTP client and its timeouts, are retried
+/// with exponential backoff (waiting as long as the hub asks on 429), and
+/// carry the hub API key when one is configured. After `BREAKER_THRESHOLD`
+/// consecutive failures the client stops calling the hub for
+/// `BREAKER_COOLDOWN`; the first query after it decides whether the hub is
+/// back. A 429 asking for more than `MAX_BACKOFF` opens the breaker for that
+/// long instead.
+#[derive(Debug)]
+pub struct HubClient {
+    graphql_url: String,
+    api_key: Option<String>,
+    http: HttpClient,
+    breaker: Mutex<Breaker>,
+}
+
+impl HubClient {
+    /// The configured hub, reached through the shared HTTP client.
+    pub fn from_config(config: &Config, http: &HttpClient) -> Self {
+        HubClient {
+            graphql_url: config.hub_graphql_url(),
+            api_key: config.snapshot_api_key.clone(),
+            http: http.clone(),
+            breaker: Mutex::new(Breaker::default()),
+        }
+    }
+
+    /// Runs a GraphQL query and returns its `data`.
+    pub async fn query(&self, query: &str, variables: Value) -> Result<Value, HubError> {
+        let body = json!({ "query": query, "variables": variables });
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            self.check_breaker()?;
+            let err = match self.send(&body).await {
+                Ok(data) => {
+                    self.record_success();
+                    return Ok(data);
+                }
+                Err(err) => err,
+            };
+            let wait = match &err {
+                HubError::RateLimited { retry_after } => match rate_limit_wait(*retry_after, backoff) {
+                    Some(wait) => wait,
+                    None => {
+                        // Honor the hub's wait without sleeping through it here.
+                        self.hold_off(retry_after.unwrap_or(MAX_BACKOFF));
+                        return Err(err);
+                    }
+                },
+                HubError::Transport(_) => {
+                    self.record_failure();
+                    backoff
+                }
+                HubError::Status { status, .. } if *status >= 500 => {
+                    self.record_failure();
+                    backoff
+                }
+                // Retrying a refused query does not help.
+                _ => return Err(err),
+            };
+            if attempt == MAX_ATTEMPTS {
+                // A hub that keeps rate limiting us counts as failing once
+                // the retries are spent.
+                if matches!(err, HubError::RateLimited { .. }) {
+                    self.record_failure();
+                }
+                return Err(err);
+            }
+            warn!(
+                "Hub query failed (attempt {}/{}): {}; retrying in {}s",
+                attempt,
+                MAX_ATTEMPTS,
+                err,
+                wait.as_secs()
+            );
+            tokio::time::sleep(wait).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            attempt += 1;
+        }
+    }
+
+    async fn send(&self, body: &Value) -> Result<Value, HubError> {
+        let mut request = self.http.post(&self.graphql_url).json(body);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("x-api-key", api_key);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|err| HubError::Transport(err.to_string()))?;
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| parse_retry_after(value, Utc::now()));
+            return Err(HubError::RateLimited { retry_after });
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(HubError::Status { status: status.as_u16(), body });
+        }
+        let mut answer: Value = response
+            .json()
+            .await
+            .map_err(|err| HubError::Transport(err.to_string()))?;
+        if let Some(errors) = answer.get("errors") {
+            return Err(HubError::GraphQl(errors.clone()));
+        }
+        match answer.get_mut("data") {
+            Some(data) => Ok(data.take()),
+            None => Err(HubError::Transport("No data in hub answer".to_string())),
+        }
+    }
+
+    fn check_breaker(&self) -> Result<(), HubError> {
+        let breaker = self.breaker.lock().unwrap();
+        match breaker.open_until {
+            Some(until) if until > Instant::now() => Err(HubError::CircuitOpen {
+                retry_in: until - Instant::now(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_success(&self) {
+        *self.breaker.lock().unwrap() = Breaker::default();
+    }
+
+    /// Opens the breaker for `wait`, unless it already stays open longer.
+    fn hold_off(&self, wait: Duration) {
+        let mut breaker = self.breaker.lock().unwrap();
+        let until = Instant::now() + wait;
+        if breaker.open_until.is_none_or(|open_until| open_until < until) {
+            warn!("Hub asked to wait {}s, pausing requests", wait.as_secs());
+            breaker.open_until = Some(until);
+        }
+    }
+
+    fn record_failure(&self) {
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.failures += 1;
+        if breaker.failures >= BREAKER_THRESHOLD {
+            warn!(
+                "Hub failed {} times in a row, pausing requests for {}s",
+                breaker.failures,
+                BREAKER_COOLDOWN.as_secs()
+            );
+            breaker.open_until = Some(Instant::now() + BREAKER_COOLDOWN);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z").unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        assert_eq!(parse_retry_after(" 120 ", now()), Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn retry_after_as_http_date() {
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:30:00 GMT", now()),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now()),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn retry_after_unreadable() {
+        assert_eq!(parse_retry_after("soon", now()), None);
+    }
+
+    #[test]
+    fn rate_limit_waits_as_long_as_the_hub_asks() {
+        let backoff = Duration::from_secs(4);
+        assert_eq!(rate_limit_wait(None, backoff), Some(backoff));
+        assert_eq!(rate_limit_wait(Some(MAX_BACKOFF), backoff), Some(MAX_BACKOFF));
+        assert_eq!(rate_limit_wait(Some(MAX_BACKOFF + Duration::from_secs(1)), backoff), None);
+    }
+
+    #[test]
+    fn long_retry_after_opens_the_breaker() {
+        let hub = HubClient::from_config(&Config::for_tests(), &HttpClient::new());
+        hub.hold_off(Duration::from_secs(600));
+        match hub.check_breaker() {
+            Err(HubError::CircuitOpen { retry_in }) => assert!(retry_in > BREAKER_COOLDOWN),
+            other => panic!("breaker should be open, got {:?}", other),
+        }
+        // A shorter wait does not close it early.
+        hub.hold_off(Duration::from_secs(1));
+        assert!(hub.check_breaker().is_err());
+    }
+}
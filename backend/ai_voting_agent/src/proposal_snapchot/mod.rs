@@ -1,3 +1,11 @@
 pub mod collector;
 pub mod prop_struct;
 pub mod repository;
+pub mod store;
+pub mod reprocess;
+pub mod revision;
+pub mod bulk;
+pub mod watchlist;
+pub mod backfill;
+pub mod hub;
+pub mod webhook;
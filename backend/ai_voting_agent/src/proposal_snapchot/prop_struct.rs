@@ -1,127 +1,148 @@
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
-#[derive(Debug)]
+/// The space a proposal belongs to, as embedded by the hub.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpaceRef {
+    pub id: String,
+    pub name: Option<String>,
+    pub avatar: Option<String>,
+    /// Chain id of the space, e.g. `"1"`.
+    pub network: Option<String>,
+    #[serde(deserialize_with = "null_as_default")]
+    pub admins: Vec<String>,
+    #[serde(deserialize_with = "null_as_default")]
+    pub moderators: Vec<String>,
+    pub symbol: Option<String>,
+    pub terms: Option<String>,
+}
+
+/// One voting option; Snapshot identifies it by its 1-based position.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Choice(pub String);
+
+impl Choice {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A voting power strategy of a proposal.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Strategy {
+    pub name: String,
+    pub network: Option<String>,
+    pub params: Value,
+}
+
+/// A Snapshot proposal.
+///
+/// Deserializes from the hub's GraphQL object and serializes to the shape the
+/// API serves and the analysis reads; database rows map to it in
+/// `repository::row_to_proposal`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Proposal {
     pub id: String,
     pub ipfs: Option<String>,
-    pub space: Option<Value>,
-    pub proposal_type: Option<String>, 
+    pub space: Option<SpaceRef>,
+    #[serde(rename = "type")]
+    pub proposal_type: Option<String>,
     pub title: Option<String>,
     pub body: Option<String>,
     pub discussion: Option<String>,
     pub author: Option<String>,
-    pub quorum: String,
+    #[serde(deserialize_with = "number")]
+    pub quorum: f64,
+    #[serde(alias = "quorumType")]
     pub quorum_type: Option<String>,
     pub start: Option<i64>, // Unix‑time
     pub end: Option<i64>,   // Unix‑time
     pub snapshot: Option<String>,
-    pub choices: Option<Value>,
-    pub labels: Option<Value>,
-    pub scores: Option<Value>,
-    pub scores_total: String,
+    #[serde(deserialize_with = "null_as_default")]
+    pub choices: Vec<Choice>,
+    #[serde(deserialize_with = "null_as_default")]
+    pub labels: Vec<String>,
+    #[serde(deserialize_with = "null_as_default")]
+    pub scores: Vec<f64>,
+    #[serde(deserialize_with = "number")]
+    pub scores_total: f64,
     pub scores_state: Option<String>,
     pub state: Option<String>,
-    pub strategies: Option<Value>,
+    #[serde(deserialize_with = "null_as_default")]
+    pub strategies: Vec<Strategy>,
     pub created: Option<i64>, // Unix‑time
     pub updated: Option<i64>, // Unix‑time
-    pub votes: String,
+    #[serde(deserialize_with = "count")]
+    pub votes: i64,
     pub privacy: Option<String>,
     pub plugins: Option<Value>,
     pub flagged: Option<bool>,
+    /// Whether the proposal matches the envelope its author signed; `None`
+    /// until it is checked.
+    pub verified: Option<bool>,
+    /// The hub object this proposal was read from, kept so the columns can be
+    /// derived again after model changes.
+    #[serde(skip)]
+    pub raw_payload: Option<Value>,
 }
 
 impl Proposal {
-    pub fn from_json(p: &Value) -> Option<Self> {
-        let id = p.get("id")?.as_str()?.to_string();
-        let ipfs = p.get("ipfs").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let space = p.get("space").cloned();
-        let proposal_type = p.get("type").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let title = p.get("title").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let body = p.get("body").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let discussion = p.get("discussion").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let author = p.get("author").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let quorum = match p.get("quorum") {
-            Some(v) => {
-                if let Some(n) = v.as_f64() {
-                    n.to_string()
-                } else if let Some(s) = v.as_str() {
-                    s.to_string()
-                } else {
-                    "0".to_string()
-                }
-            },
-            None => "0".to_string(),
-        };
-        let quorum_type = p.get("quorumType").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let start = p.get("start").and_then(|v| v.as_i64());
-        let end = p.get("end").and_then(|v| v.as_i64());
-        let snapshot = p.get("snapshot").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let choices = p.get("choices").cloned();
-        let labels = p.get("labels").cloned();
-        let scores = p.get("scores").cloned();
-        let scores_total = match p.get("scores_total") {
-            Some(v) => {
-                if let Some(n) = v.as_f64() {
-                    n.to_string()
-                } else if let Some(s) = v.as_str() {
-                    s.to_string()
-                } else {
-                    "0".to_string()
-                }
-            },
-            None => "0".to_string(),
-        };
-        let scores_state = p.get("scores_state").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let state = p.get("state").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let strategies = p.get("strategies").cloned();
-        let created = p.get("created").and_then(|v| v.as_i64());
-        let updated = p.get("updated").and_then(|v| v.as_i64());
-        let votes = match p.get("votes") {
-            Some(v) => {
-                if let Some(n) = v.as_f64() {
-                    n.to_string()
-                } else if let Some(s) = v.as_str() {
-                    s.to_string()
-                } else {
-                    "0".to_string()
-                }
-            },
-            None => "0".to_string(),
-        };
-        let privacy = p.get("privacy").and_then(|v| v.as_str().map(|s| s.to_string()));
-        let plugins = p.get("plugins").cloned();
-        let flagged = p.get("flagged").and_then(|v| v.as_bool());
+    /// Reads a proposal object returned by the hub.
+    pub fn from_json(p: &Value) -> Result<Self, serde_json::Error> {
+        let mut proposal = Proposal::deserialize(p)?;
+        proposal.raw_payload = Some(p.clone());
+        Ok(proposal)
+    }
 
-        Some(Proposal {
-            id,
-            ipfs,
-            space,
-            proposal_type,
-            title,
-            body,
-            discussion,
-            author,
-            quorum,
-            quorum_type,
-            start,
-            end,
-            snapshot,
-            choices,
-            labels,
-            scores,
-            scores_total,
-            scores_state,
-            state,
-            strategies,
-            created,
-            updated,
-            votes,
-            privacy,
-            plugins,
-            flagged,
-        })
+    /// Hub time of this version of the proposal: its last edit, or its creation.
+    pub fn version(&self) -> i64 {
+        self.updated.or(self.created).unwrap_or_default()
+    }
+
+    /// The proposal as served by the API and handed to the analysis.
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+
+    pub fn space_id(&self) -> Option<&str> {
+        self.space.as_ref().map(|space| space.id.as_str())
+    }
+
+    /// Open for votes at `now` (Unix seconds).
+    pub fn is_active(&self, now: i64) -> bool {
+        self.state.as_deref() == Some("active") && self.end.is_some_and(|end| end >= now)
     }
 }
 
+/// `null` reads as the type's default, e.g. an empty list.
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrText {
+    Number(f64),
+    Text(String),
+}
 
+/// A number the hub may send as a string or `null`; anything unreadable is 0.
+fn number<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    Ok(match Option::<NumberOrText>::deserialize(deserializer)? {
+        Some(NumberOrText::Number(n)) => n,
+        Some(NumberOrText::Text(s)) => s.trim().parse().unwrap_or(0.0),
+        None => 0.0,
+    })
+}
+
+fn count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    number(deserializer).map(|n| n as i64)
+}
@@ -1,176 +1,187 @@
+use async_trait::async_trait;
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
 use chrono::{Duration, NaiveDate};
-use serde_json::{json, Value};
 use std::{error::Error, sync::Arc};
-use tokio_postgres::{NoTls, Row};
-
-pub fn row_to_proposal(row: &Row) -> Value {
-    json!({
-        "id": row.get::<_, String>("id"),
-        "ipfs": row.get::<_, Option<String>>("ipfs"),
-        "space": row.get::<_, Option<Value>>("space"),
-        "type": row.get::<_, Option<String>>("type"),
-        "title": row.get::<_, Option<String>>("title"),
-        "body": row.get::<_, Option<String>>("body"),
-        "discussion": row.get::<_, Option<String>>("discussion"),
-        "author": row.get::<_, Option<String>>("author"),
-        "quorum": row.get::<_, String>("quorum"),
-        "quorum_type": row.get::<_, Option<String>>("quorum_type"),
-        "start": row.get::<_, Option<i64>>("start").map(|ts| ts as i64),
-        "end": row.get::<_, Option<i64>>("end").map(|ts| ts as i64),
-        "snapshot": row.get::<_, Option<String>>("snapshot"),
-        "choices": row.get::<_, Option<Value>>("choices"),
-        "labels": row.get::<_, Option<Value>>("labels"),
-        "scores": row.get::<_, Option<Value>>("scores"),
-        "scores_total": row.get::<_, String>("scores_total"),
-        "scores_state": row.get::<_, Option<String>>("scores_state"),
-        "state": row.get::<_, Option<String>>("state"),
-        "strategies": row.get::<_, Option<Value>>("strategies"),
-        "created": row.get::<_, Option<i64>>("created").map(|ts| ts as i64),
-        "updated": row.get::<_, Option<i64>>("updated").map(|ts| ts as i64),
-        "votes": row.get::<_, String>("votes"),
-        "privacy": row.get::<_, Option<String>>("privacy"),
-        "plugins": row.get::<_, Option<Value>>("plugins"),
-        "flagged": row.get::<_, Option<bool>>("flagged"),
-    })
+use tokio_postgres::{types::Json, NoTls, Row};
+
+use crate::proposal_snapchot::{
+    bulk::bulk_upsert_proposals,
+    collector::upsert_proposals,
+    prop_struct::{Proposal, SpaceRef},
+    store::{ProposalStore, SpaceSummary},
+};
+
+pub const PROPOSAL_COLUMNS: &str = r#"
+    id, ipfs, space, "type", title, body, discussion, author, quorum, quorum_type,
+    (EXTRACT(EPOCH FROM "start"))::int8 as start, (EXTRACT(EPOCH FROM "end"))::int8 as end, snapshot, choices, labels, scores, scores_total, scores_state, state,
+    strategies, (EXTRACT(EPOCH FROM created))::int8 as created, (EXTRACT(EPOCH FROM updated))::int8 as updated, votes, privacy, plugins, flagged, verified
+"#;
+
+/// A nullable JSONB column decoded into `T`, the default when unset.
+fn json_column<T>(row: &Row, column: &str) -> Result<T, tokio_postgres::Error>
+where
+    T: Default + for<'de> serde::Deserialize<'de>,
+{
+    Ok(row
+        .try_get::<_, Option<Json<T>>>(column)?
+        .map(|Json(value)| value)
+        .unwrap_or_default())
 }
 
-pub async fn get_proposals_by_date(
-    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
-    date_str: &str,
-) -> Result<Value, Box<dyn Error>> {
-    let conn = db_client.get().await?;
-    let date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")?;
-    let start_dt = date
-        .and_hms_opt(0, 0, 0)
-        .ok_or("Invalid start date")?;
-    let start_unixtime = start_dt.and_utc().timestamp() as f64;
-    let end_unixtime = (start_dt + Duration::days(1)).and_utc().timestamp() as f64;
-
-    let rows = conn
-        .query(
-            "SELECT 
-                id, ipfs, space, \"type\", title, body, discussion, author, quorum, quorum_type,
-                (EXTRACT(EPOCH FROM \"start\"))::int8 as start, (EXTRACT(EPOCH FROM \"end\"))::int8 as end, snapshot, choices, labels, scores, scores_total, scores_state, state,
-                strategies, (EXTRACT(EPOCH FROM created))::int8 as created, (EXTRACT(EPOCH FROM updated))::int8 as updated, votes, privacy, plugins, flagged
-             FROM proposals
-             WHERE \"start\" >= TO_TIMESTAMP($1) AND \"start\" < TO_TIMESTAMP($2)
-             ORDER BY \"start\" DESC",
-            &[&start_unixtime, &end_unixtime],
-        )
-        .await?;
-
-    let proposals: Vec<Value> = rows.into_iter().map(|row| row_to_proposal(&row)).collect();
-
-    Ok(json!(proposals))
+/// Maps a row selected with `PROPOSAL_COLUMNS`.
+pub fn row_to_proposal(row: &Row) -> Result<Proposal, tokio_postgres::Error> {
+    Ok(Proposal {
+        id: row.try_get("id")?,
+        ipfs: row.try_get("ipfs")?,
+        space: row.try_get::<_, Option<Json<SpaceRef>>>("space")?.map(|Json(space)| space),
+        proposal_type: row.try_get("type")?,
+        title: row.try_get("title")?,
+        body: row.try_get("body")?,
+        discussion: row.try_get("discussion")?,
+        author: row.try_get("author")?,
+        quorum: row.try_get("quorum")?,
+        quorum_type: row.try_get("quorum_type")?,
+        start: row.try_get("start")?,
+        end: row.try_get("end")?,
+        snapshot: row.try_get("snapshot")?,
+        choices: json_column(row, "choices")?,
+        labels: json_column(row, "labels")?,
+        scores: json_column(row, "scores")?,
+        scores_total: row.try_get("scores_total")?,
+        scores_state: row.try_get("scores_state")?,
+        state: row.try_get("state")?,
+        strategies: json_column(row, "strategies")?,
+        created: row.try_get("created")?,
+        updated: row.try_get("updated")?,
+        votes: row.try_get("votes")?,
+        privacy: row.try_get("privacy")?,
+        plugins: row.try_get("plugins")?,
+        flagged: row.try_get("flagged")?,
+        verified: row.try_get("verified")?,
+        raw_payload: None,
+    })
 }
 
-pub async fn get_proposals_by_space_id(
-    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
-    space_id: &String,
-) -> Result<Value, Box<dyn Error>> {
-    let conn = db_client.get().await?;
-
-    let rows = conn
-    .query(
-        "SELECT 
-            id, ipfs, space, \"type\", title, body, discussion, author, quorum, quorum_type,
-            (EXTRACT(EPOCH FROM \"start\"))::int8 as start, (EXTRACT(EPOCH FROM \"end\"))::int8 as end, snapshot, choices, labels, scores, scores_total, scores_state, state,
-            strategies, (EXTRACT(EPOCH FROM created))::int8 as created, (EXTRACT(EPOCH FROM updated))::int8 as updated, votes, privacy, plugins, flagged
-         FROM proposals
-         WHERE space->>'id' = $1 AND state = 'active' AND \"end\" >= NOW()
-         ORDER BY \"created\" DESC",
-        &[&space_id],
-    )
-    .await?;
-
-    let proposals: Vec<Value> = rows.into_iter().map(|row| row_to_proposal(&row)).collect();
-
-    Ok(json!(proposals))
+fn row_to_space(row: &Row) -> SpaceSummary {
+    SpaceSummary {
+        space_id: row.get::<_, Option<String>>(0).unwrap_or_default(),
+        space_name: row.get::<_, Option<String>>(1).unwrap_or_default(),
+        space_avatar: row.get::<_, Option<String>>(2).unwrap_or_default(),
+        active_proposals_count: row.get::<_, Option<i64>>(3).unwrap_or(0),
+        proposals_count: row.get::<_, Option<i64>>(4).unwrap_or(0),
+    }
 }
 
-pub async fn get_proposals_by_id(
-    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
-    id: &String,
-) -> Result<Value, Box<dyn Error>> {
-    let conn = db_client.get().await?;
-    let rows = conn
-        .query(
-            "SELECT 
-                id, ipfs, space, \"type\", title, body, discussion, author, quorum, quorum_type,
-                (EXTRACT(EPOCH FROM \"start\"))::int8 as start, (EXTRACT(EPOCH FROM \"end\"))::int8 as end, snapshot, choices, labels, scores, scores_total, scores_state, state,
-                strategies, (EXTRACT(EPOCH FROM created))::int8 as created, (EXTRACT(EPOCH FROM updated))::int8 as updated, votes, privacy, plugins, flagged
-             FROM proposals
-             WHERE id = $1
-             ORDER BY \"start\" DESC",
-            &[&id],
-        )
-        .await?;
-
-    let proposals: Vec<Value> = rows.into_iter().map(|row| row_to_proposal(&row)).collect();
-
-    Ok(json!(proposals))
+/// Proposals stored in the `proposals` table.
+#[derive(Clone)]
+pub struct PgProposalStore {
+    db_client: Arc<Pool<PostgresConnectionManager<NoTls>>>,
 }
 
-pub async fn get_active_proposals_without_rec(
-    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
-) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
-    let conn = db_client.get().await?;
-    let query = r#"
-    SELECT 
-        id, ipfs, space, "type", title, body, discussion, author, quorum, quorum_type,
-        (EXTRACT(EPOCH FROM "start"))::int8 as start, (EXTRACT(EPOCH FROM "end"))::int8 as end, snapshot, choices, labels, scores, scores_total, scores_state, state,
-        strategies, (EXTRACT(EPOCH FROM created))::int8 as created, (EXTRACT(EPOCH FROM updated))::int8 as updated, votes, privacy, plugins, flagged
-    FROM proposals
-    WHERE state = 'active'
-      AND "end" >= NOW()
-      AND id NOT IN (SELECT proposal_id FROM recommendations)
-    "#;
-    // AND "end" <= NOW() + INTERVAL '5 days'
-    println!("query: {}", query);
-    let rows = conn.query(query, &[]).await?;
-
-    println!("Found {} proposals without recommendations", rows.len());
-
-    let proposals: Vec<Value> = rows.into_iter().map(|row| row_to_proposal(&row)).collect();
-
-    Ok(proposals)
+impl PgProposalStore {
+    pub fn new(db_client: Arc<Pool<PostgresConnectionManager<NoTls>>>) -> Self {
+        PgProposalStore { db_client }
+    }
+
+    async fn select(
+        &self,
+        condition: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let query = format!("SELECT {} FROM proposals {}", PROPOSAL_COLUMNS, condition);
+        let rows = conn.query(&query, params).await?;
+        Ok(rows.iter().map(row_to_proposal).collect::<Result<_, _>>()?)
+    }
 }
 
-pub async fn get_spaces_vec(
-    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
-) -> Result<Value, Box<dyn Error>> {
-    let conn = db_client.get().await?;
-
-    let rows = conn
-        .query(
-            "SELECT 
-                space->>'id' AS space_id, 
-                space->>'name' AS space_name, 
-                space->>'avatar' AS space_avatar, 
-                COUNT(*) FILTER (WHERE state = 'active' AND \"end\" >= NOW()) AS active_proposals_count,
-                COUNT(*) AS proposals_count
-            FROM public.proposals
-            GROUP BY space->>'id', space->>'name', space->>'avatar'
-            HAVING COUNT(*) FILTER (WHERE state = 'active' AND \"end\" >= NOW()) > 0
-            ORDER BY proposals_count DESC
-            LIMIT 50",
-            &[],
+#[async_trait]
+impl ProposalStore for PgProposalStore {
+    async fn started_on(&self, date: NaiveDate) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+        let start_dt = date
+            .and_hms_opt(0, 0, 0)
+            .ok_or("Invalid start date")?;
+        let start_unixtime = start_dt.and_utc().timestamp() as f64;
+        let end_unixtime = (start_dt + Duration::days(1)).and_utc().timestamp() as f64;
+        self.select(
+            r#"WHERE "start" >= TO_TIMESTAMP($1) AND "start" < TO_TIMESTAMP($2)
+               ORDER BY "start" DESC"#,
+            &[&start_unixtime, &end_unixtime],
         )
-        .await?;
-
-    let spaces: Vec<Value> = rows.into_iter().map(|row| row_to_space(&row)).collect();
-
-    Ok(json!(spaces))
-}
-
-fn row_to_space(row: &Row) -> Value {
-    json!({
-        "space_id": row.get::<_, Option<String>>(0).unwrap_or_default(),
-        "space_name": row.get::<_, Option<String>>(1).unwrap_or_default(),
-        "space_avatar": row.get::<_, Option<String>>(2).unwrap_or_default(),
-        "active_proposals_count": row.get::<_, Option<i64>>(3).unwrap_or(0),
-        "proposals_count": row.get::<_, Option<i64>>(4).unwrap_or(0),
-    })
+        .await
+    }
+
+    async fn active_in_space(&self, space_id: &str) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+        self.select(
+            r#"WHERE space->>'id' = $1 AND state = 'active' AND "end" >= NOW()
+               ORDER BY "created" DESC"#,
+            &[&space_id],
+        )
+        .await
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Proposal>, Box<dyn Error + Send + Sync>> {
+        let proposals = self.select("WHERE id = $1", &[&id]).await?;
+        Ok(proposals.into_iter().next())
+    }
+
+    async fn active(&self) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+        self.select(r#"WHERE state = 'active' AND "end" >= NOW()"#, &[]).await
+    }
+
+    async fn ending_within(&self, hours: i64) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+        self.select(
+            r#"WHERE state = 'active'
+                 AND "end" >= NOW()
+                 AND "end" <= NOW() + make_interval(hours => $1::INT8::INT4)
+               ORDER BY "end""#,
+            &[&hours],
+        )
+        .await
+    }
+
+    async fn spaces(&self) -> Result<Vec<SpaceSummary>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+
+        let rows = conn
+            .query(
+                "SELECT 
+                    space->>'id' AS space_id, 
+                    space->>'name' AS space_name, 
+                    space->>'avatar' AS space_avatar, 
+                    COUNT(*) FILTER (WHERE state = 'active' AND \"end\" >= NOW()) AS active_proposals_count,
+                    COUNT(*) AS proposals_count
+                FROM public.proposals
+                GROUP BY space->>'id', space->>'name', space->>'avatar'
+                HAVING COUNT(*) FILTER (WHERE state = 'active' AND \"end\" >= NOW()) > 0
+                ORDER BY proposals_count DESC
+                LIMIT 50",
+                &[],
+            )
+            .await?;
+
+        Ok(rows.iter().map(row_to_space).collect())
+    }
+
+    async fn upsert(&self, proposals: &[Proposal]) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        // Hub pages go through the COPY staging table; a single webhook
+        // proposal is not worth one.
+        match proposals.len() {
+            0 | 1 => upsert_proposals(&self.db_client, proposals).await,
+            _ => bulk_upsert_proposals(&self.db_client, proposals).await,
+        }
+    }
+
+    async fn mark_deleted(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let marked = conn
+            .execute(
+                "UPDATE proposals SET state = 'deleted', deleted_at = NOW()
+                 WHERE id = $1 AND deleted_at IS NULL",
+                &[&id],
+            )
+            .await?;
+        Ok(marked > 0)
+    }
 }
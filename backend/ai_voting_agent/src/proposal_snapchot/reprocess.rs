@@ -0,0 +1,74 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use log::{info, warn};
+use serde_json::Value;
+use std::{error::Error, sync::Arc};
+use tokio_postgres::NoTls;
+
+use crate::proposal_snapchot::{collector::write_proposal, prop_struct::Proposal};
+
+const REPROCESS_BATCH: i64 = 500;
+
+/// Outcome of a `reprocess` run.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReprocessReport {
+    pub reprocessed: u64,
+    /// Payloads the current model cannot read; their rows are left as they are.
+    pub failed: u64,
+}
+
+/// Derives the proposal columns again from the latest stored hub payload of
+/// every proposal, without asking the hub. Run it after the model changes.
+///
+/// Recommendations are not invalidated: the payloads are the ones already
+/// analyzed, only their reading changed.
+pub async fn reprocess_proposals(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+) -> Result<ReprocessReport, Box<dyn Error + Send + Sync>> {
+    let mut report = ReprocessReport::default();
+    let mut after = String::new();
+    loop {
+        let mut conn = db_client.get().await?;
+        let transaction = conn.transaction().await?;
+        let rows = transaction
+            .query(
+                r#"
+                SELECT DISTINCT ON (proposal_id) proposal_id, raw_payload
+                FROM proposal_payloads
+                WHERE proposal_id > $1
+                ORDER BY proposal_id, version DESC, id DESC
+                LIMIT $2
+                "#,
+                &[&after, &REPROCESS_BATCH],
+            )
+            .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after = last.get("proposal_id");
+
+        for row in &rows {
+            let proposal_id: String = row.get("proposal_id");
+            let raw_payload: Value = row.get("raw_payload");
+            match Proposal::from_json(&raw_payload) {
+                Ok(proposal) => {
+                    write_proposal(&transaction, &proposal).await?;
+                    report.reprocessed += 1;
+                }
+                Err(e) => {
+                    warn!("Could not reprocess proposal {}: {}", proposal_id, e);
+                    report.failed += 1;
+                }
+            }
+        }
+        transaction.commit().await?;
+        info!(
+            "Reprocessed {} proposals so far, {} failed",
+            report.reprocessed, report.failed
+        );
+        if (rows.len() as i64) < REPROCESS_BATCH {
+            break;
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,135 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{error::Error, sync::Arc};
+use tokio_postgres::{types::Json, GenericClient, NoTls, Row};
+
+use crate::proposal_snapchot::prop_struct::Proposal;
+
+/// Fields that move on their own while voting runs; their changes are not
+/// revisions.
+const TALLY_FIELDS: &[&str] = &["scores", "scores_total", "scores_state", "votes", "updated", "verified"];
+
+/// Edits that change what voters are asked, so an earlier analysis no longer holds.
+pub const MATERIAL_FIELDS: &[&str] = &["title", "body", "choices", "end", "discussion"];
+
+/// One changed field of a proposal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// A recorded change of a proposal.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Revision {
+    pub id: i64,
+    pub proposal_id: String,
+    /// The hub's `updated` of the new version.
+    pub hub_updated: Option<i64>,
+    pub changes: Vec<FieldChange>,
+    pub material: bool,
+    /// Recommendation that was current when the change was seen.
+    pub recommendation_id: Option<i64>,
+    /// A material edit made after `recommendation_id` was generated.
+    pub flagged: bool,
+    pub detected_at: i64,
+}
+
+impl Revision {
+    pub fn fields(&self) -> Vec<&str> {
+        self.changes.iter().map(|change| change.field.as_str()).collect()
+    }
+
+    pub fn flagged_recommendation_id(&self) -> Option<i64> {
+        self.recommendation_id.filter(|_| self.flagged)
+    }
+}
+
+/// Field-level differences between two versions of a proposal, tallies aside.
+pub fn diff_proposals(old: &Proposal, new: &Proposal) -> Vec<FieldChange> {
+    let (old, new) = (old.to_json(), new.to_json());
+    let (Some(old), Some(new)) = (old.as_object(), new.as_object()) else {
+        return Vec::new();
+    };
+    new.iter()
+        .filter(|(field, _)| !TALLY_FIELDS.contains(&field.as_str()))
+        .filter_map(|(field, value)| {
+            let previous = old.get(field).cloned().unwrap_or(Value::Null);
+            (previous != *value).then(|| FieldChange {
+                field: field.clone(),
+                old: previous,
+                new: value.clone(),
+            })
+        })
+        .collect()
+}
+
+fn row_to_revision(row: &Row) -> Revision {
+    Revision {
+        id: row.get("id"),
+        proposal_id: row.get("proposal_id"),
+        hub_updated: row.get("hub_updated"),
+        changes: row.get::<_, Json<Vec<FieldChange>>>("changes").0,
+        material: row.get("material"),
+        recommendation_id: row.get("recommendation_id"),
+        flagged: row.get("flagged"),
+        detected_at: row.get("detected_at"),
+    }
+}
+
+const REVISION_COLUMNS: &str = r#"
+    id, proposal_id, hub_updated, changes, material, recommendation_id, flagged,
+    (EXTRACT(EPOCH FROM detected_at))::int8 AS detected_at
+"#;
+
+/// Appends a revision of `proposal`, linked to its current recommendation.
+pub async fn save_revision(
+    client: &impl GenericClient,
+    proposal: &Proposal,
+    changes: &[FieldChange],
+) -> Result<Revision, tokio_postgres::Error> {
+    let material = changes
+        .iter()
+        .any(|change| MATERIAL_FIELDS.contains(&change.field.as_str()));
+    let query = format!(
+        r#"
+        WITH rec AS (
+            SELECT id FROM recommendations
+            WHERE proposal_id = $1 AND invalidated_at IS NULL
+            ORDER BY created_at DESC, id DESC
+            LIMIT 1
+        )
+        INSERT INTO proposal_revisions
+            (proposal_id, hub_updated, changes, material, recommendation_id, flagged)
+        SELECT $1, $2, $3, $4, rec.id, $4 AND rec.id IS NOT NULL
+        FROM (SELECT 1) AS one LEFT JOIN rec ON TRUE
+        RETURNING {}
+        "#,
+        REVISION_COLUMNS
+    );
+    let row = client
+        .query_one(
+            &query,
+            &[&proposal.id, &proposal.updated, &Json(changes), &material],
+        )
+        .await?;
+    Ok(row_to_revision(&row))
+}
+
+/// Revisions of a proposal, oldest first.
+pub async fn get_proposal_revisions(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: &str,
+) -> Result<Vec<Revision>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let query = format!(
+        "SELECT {} FROM proposal_revisions WHERE proposal_id = $1 ORDER BY id",
+        REVISION_COLUMNS
+    );
+    let rows = conn.query(&query, &[&proposal_id]).await?;
+    Ok(rows.iter().map(row_to_revision).collect())
+}
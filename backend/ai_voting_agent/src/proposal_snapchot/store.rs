@@ -0,0 +1,235 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use std::{error::Error, sync::Mutex};
+
+use crate::proposal_snapchot::{
+    prop_struct::Proposal,
+    revision::{diff_proposals, MATERIAL_FIELDS},
+};
+
+/// A space with active proposals, as listed by the API.
+#[derive(Debug, Clone)]
+pub struct SpaceSummary {
+    pub space_id: String,
+    pub space_name: String,
+    pub space_avatar: String,
+    pub active_proposals_count: i64,
+    pub proposals_count: i64,
+}
+
+impl SpaceSummary {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "space_id": self.space_id,
+            "space_name": self.space_name,
+            "space_avatar": self.space_avatar,
+            "active_proposals_count": self.active_proposals_count,
+            "proposals_count": self.proposals_count,
+        })
+    }
+}
+
+/// The collected proposals.
+#[async_trait]
+pub trait ProposalStore: Send + Sync {
+    /// Proposals that started on `date` (UTC), newest first.
+    async fn started_on(&self, date: NaiveDate) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>>;
+
+    /// Active proposals of a space, newest first.
+    async fn active_in_space(&self, space_id: &str) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>>;
+
+    async fn get(&self, id: &str) -> Result<Option<Proposal>, Box<dyn Error + Send + Sync>>;
+
+    /// Proposals that are open for votes.
+    async fn active(&self) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>>;
+
+    /// Active proposals that close within `hours`, soonest first.
+    async fn ending_within(&self, hours: i64) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>>;
+
+    /// Up to 50 spaces with active proposals, the busiest first.
+    async fn spaces(&self) -> Result<Vec<SpaceSummary>, Box<dyn Error + Send + Sync>>;
+
+    /// Inserts proposals or brings known ones up to date, recording a revision
+    /// for every known proposal that changed. A proposal marked deleted stays
+    /// deleted. Returns the ids of the ones with material edits.
+    async fn upsert(&self, proposals: &[Proposal]) -> Result<Vec<String>, Box<dyn Error + Send + Sync>>;
+
+    /// Marks a proposal deleted on the hub. Returns false when it is unknown
+    /// or already marked.
+    async fn mark_deleted(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>>;
+}
+
+/// Proposals kept in memory, for tests and local runs without a database.
+#[derive(Debug, Default)]
+pub struct MemoryProposalStore {
+    proposals: Mutex<Vec<Proposal>>,
+}
+
+impl MemoryProposalStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a proposal or replaces the one with the same id.
+    pub fn insert(&self, proposal: Proposal) {
+        let mut proposals = self.proposals.lock().unwrap();
+        proposals.retain(|p| p.id != proposal.id);
+        proposals.push(proposal);
+    }
+
+    fn filtered(&self, keep: impl Fn(&Proposal) -> bool) -> Vec<Proposal> {
+        self.proposals
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|p| keep(p))
+            .cloned()
+            .collect()
+    }
+}
+
+#[async_trait]
+impl ProposalStore for MemoryProposalStore {
+    async fn started_on(&self, date: NaiveDate) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+        let from = date.and_hms_opt(0, 0, 0).ok_or("Invalid start date")?.and_utc().timestamp();
+        let to = from + 86_400;
+        let mut proposals = self.filtered(|p| p.start.is_some_and(|start| start >= from && start < to));
+        proposals.sort_by_key(|p| std::cmp::Reverse(p.start));
+        Ok(proposals)
+    }
+
+    async fn active_in_space(&self, space_id: &str) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut proposals = self.filtered(|p| p.space_id() == Some(space_id) && p.is_active(now));
+        proposals.sort_by_key(|p| std::cmp::Reverse(p.created));
+        Ok(proposals)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Proposal>, Box<dyn Error + Send + Sync>> {
+        Ok(self.filtered(|p| p.id == id).into_iter().next())
+    }
+
+    async fn active(&self) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+        let now = chrono::Utc::now().timestamp();
+        Ok(self.filtered(|p| p.is_active(now)))
+    }
+
+    async fn ending_within(&self, hours: i64) -> Result<Vec<Proposal>, Box<dyn Error + Send + Sync>> {
+        let now = chrono::Utc::now().timestamp();
+        let until = now + hours * 3600;
+        let mut proposals =
+            self.filtered(|p| p.is_active(now) && p.end.is_some_and(|end| end <= until));
+        proposals.sort_by_key(|p| p.end);
+        Ok(proposals)
+    }
+
+    async fn spaces(&self) -> Result<Vec<SpaceSummary>, Box<dyn Error + Send + Sync>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut spaces: Vec<SpaceSummary> = Vec::new();
+        for proposal in self.proposals.lock().unwrap().iter() {
+            let space = proposal.space.clone().unwrap_or_default();
+            let (space_id, space_name, space_avatar) = (
+                space.id,
+                space.name.unwrap_or_default(),
+                space.avatar.unwrap_or_default(),
+            );
+            let idx = match spaces.iter().position(|s| {
+                s.space_id == space_id && s.space_name == space_name && s.space_avatar == space_avatar
+            }) {
+                Some(idx) => idx,
+                None => {
+                    spaces.push(SpaceSummary {
+                        space_id,
+                        space_name,
+                        space_avatar,
+                        active_proposals_count: 0,
+                        proposals_count: 0,
+                    });
+                    spaces.len() - 1
+                }
+            };
+            spaces[idx].proposals_count += 1;
+            if proposal.is_active(now) {
+                spaces[idx].active_proposals_count += 1;
+            }
+        }
+        spaces.retain(|space| space.active_proposals_count > 0);
+        spaces.sort_by_key(|space| std::cmp::Reverse(space.proposals_count));
+        spaces.truncate(50);
+        Ok(spaces)
+    }
+
+    async fn upsert(&self, proposals: &[Proposal]) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let mut stored = self.proposals.lock().unwrap();
+        let mut changed = Vec::new();
+        for proposal in proposals {
+            let mut proposal = proposal.clone();
+            if let Some(idx) = stored.iter().position(|p| p.id == proposal.id) {
+                let previous = stored.remove(idx);
+                if previous.state.as_deref() == Some("deleted") {
+                    proposal.state = previous.state.clone();
+                }
+                let material = diff_proposals(&previous, &proposal)
+                    .iter()
+                    .any(|change| MATERIAL_FIELDS.contains(&change.field.as_str()));
+                if material && !changed.contains(&proposal.id) {
+                    changed.push(proposal.id.clone());
+                }
+            }
+            stored.push(proposal);
+        }
+        Ok(changed)
+    }
+
+    async fn mark_deleted(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let mut proposals = self.proposals.lock().unwrap();
+        match proposals
+            .iter_mut()
+            .find(|p| p.id == id && p.state.as_deref() != Some("deleted"))
+        {
+            Some(proposal) => {
+                proposal.state = Some("deleted".to_string());
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proposal(id: &str, title: &str, votes: i64) -> Proposal {
+        Proposal {
+            id: id.to_string(),
+            title: Some(title.to_string()),
+            state: Some("active".to_string()),
+            votes,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn upserts_report_material_edits_only() {
+        let store = MemoryProposalStore::new();
+        assert!(store.upsert(&[proposal("p1", "Fund", 1)]).await.unwrap().is_empty());
+        // New votes are a tally, not an edit.
+        assert!(store.upsert(&[proposal("p1", "Fund", 9)]).await.unwrap().is_empty());
+        assert_eq!(store.upsert(&[proposal("p1", "Fund more", 9)]).await.unwrap(), vec!["p1"]);
+        assert_eq!(store.get("p1").await.unwrap().unwrap().title.as_deref(), Some("Fund more"));
+    }
+
+    #[tokio::test]
+    async fn deleted_proposals_stay_deleted() {
+        let store = MemoryProposalStore::new();
+        store.upsert(&[proposal("p1", "Fund", 1)]).await.unwrap();
+        assert!(store.mark_deleted("p1").await.unwrap());
+        assert!(!store.mark_deleted("p1").await.unwrap());
+        assert!(!store.mark_deleted("unknown").await.unwrap());
+
+        store.upsert(&[proposal("p1", "Fund", 2)]).await.unwrap();
+        assert_eq!(store.get("p1").await.unwrap().unwrap().state.as_deref(), Some("deleted"));
+    }
+}
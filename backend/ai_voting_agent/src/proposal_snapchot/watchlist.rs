@@ -0,0 +1,94 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use serde::Serialize;
+use std::{error::Error, sync::Arc};
+use tokio_postgres::{NoTls, Row};
+
+/// A space the collector syncs.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchedSpace {
+    pub space_id: String,
+    pub added_by: String,
+    pub added_at: i64,
+    /// When its history was pulled; `None` until the collector backfilled it.
+    pub backfilled_at: Option<i64>,
+}
+
+const WATCHED_SPACE_COLUMNS: &str = r#"
+    space_id, added_by,
+    (EXTRACT(EPOCH FROM added_at))::int8 AS added_at,
+    (EXTRACT(EPOCH FROM backfilled_at))::int8 AS backfilled_at
+"#;
+
+fn row_to_watched_space(row: &Row) -> WatchedSpace {
+    WatchedSpace {
+        space_id: row.get("space_id"),
+        added_by: row.get("added_by"),
+        added_at: row.get("added_at"),
+        backfilled_at: row.get("backfilled_at"),
+    }
+}
+
+pub async fn get_watched_spaces(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+) -> Result<Vec<WatchedSpace>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let query = format!("SELECT {} FROM watched_spaces ORDER BY space_id", WATCHED_SPACE_COLUMNS);
+    let rows = conn.query(&query, &[]).await?;
+    Ok(rows.iter().map(row_to_watched_space).collect())
+}
+
+/// Adds a space to the watchlist; `None` when it is already there. Its history
+/// is backfilled by the next collection.
+pub async fn add_watched_space(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    space_id: &str,
+    added_by: &str,
+) -> Result<Option<WatchedSpace>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let query = format!(
+        "INSERT INTO watched_spaces (space_id, added_by) VALUES ($1, $2)
+         ON CONFLICT (space_id) DO NOTHING
+         RETURNING {}",
+        WATCHED_SPACE_COLUMNS
+    );
+    let row = conn.query_opt(&query, &[&space_id, &added_by]).await?;
+    Ok(row.as_ref().map(row_to_watched_space))
+}
+
+/// Stops syncing a space. Its collected proposals are kept.
+pub async fn remove_watched_space(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    space_id: &str,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let removed = conn
+        .execute("DELETE FROM watched_spaces WHERE space_id = $1", &[&space_id])
+        .await?;
+    Ok(removed > 0)
+}
+
+pub async fn mark_backfilled(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    space_id: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    conn.execute(
+        "UPDATE watched_spaces SET backfilled_at = NOW() WHERE space_id = $1",
+        &[&space_id],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn is_watched(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    space_id: &str,
+) -> Result<bool, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_opt("SELECT 1 FROM watched_spaces WHERE space_id = $1", &[&space_id])
+        .await?;
+    Ok(row.is_some())
+}
@@ -0,0 +1,79 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, sync::Arc};
+use tokio_postgres::NoTls;
+
+use crate::config::config::Config;
+use crate::proposal_snapchot::{
+    collector::{fetch_proposal, invalidate_changed, mark_proposal_deleted},
+    hub::HubClient,
+    watchlist::is_watched,
+};
+use crate::store::store::Stores;
+
+/// A Snapshot webhook delivery, e.g.
+/// `{"id": "proposal/0x…", "event": "proposal/created", "space": "ens.eth", "expire": 1700000000}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotEvent {
+    pub id: String,
+    pub event: String,
+    pub space: String,
+    pub expire: Option<i64>,
+}
+
+impl SnapshotEvent {
+    pub fn proposal_id(&self) -> Option<&str> {
+        self.id.strip_prefix("proposal/")
+    }
+}
+
+/// What an event did to the stored proposals.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventOutcome {
+    Upserted,
+    Deleted,
+    /// The hub did not return the proposal; the polling sync picks it up later.
+    NotFound,
+    /// Not a proposal event, or a space outside the watchlist.
+    Ignored,
+}
+
+/// Applies a webhook event right away: the proposal is fetched and upserted,
+/// or marked deleted. Polling stays in place and catches whatever a missed or
+/// failed delivery leaves behind.
+pub async fn handle_event(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    stores: &Stores,
+    config: &Config,
+    hub: &HubClient,
+    event: &SnapshotEvent,
+) -> Result<EventOutcome, Box<dyn Error + Send + Sync>> {
+    let Some(proposal_id) = event.proposal_id() else {
+        return Ok(EventOutcome::Ignored);
+    };
+    if !config.collect_all_spaces && !is_watched(db_client, &event.space).await? {
+        return Ok(EventOutcome::Ignored);
+    }
+    match event.event.as_str() {
+        "proposal/created" | "proposal/start" | "proposal/end" => {
+            let Some(proposal) = fetch_proposal(hub, proposal_id).await? else {
+                warn!("Webhook {} for {}: proposal not found on the hub", event.event, proposal_id);
+                return Ok(EventOutcome::NotFound);
+            };
+            let changed = stores.proposals.upsert(&[proposal]).await?;
+            invalidate_changed(stores, changed).await?;
+            info!("Webhook {}: proposal {} upserted", event.event, proposal_id);
+            Ok(EventOutcome::Upserted)
+        }
+        "proposal/deleted" => {
+            if !mark_proposal_deleted(stores, proposal_id).await? {
+                info!("Webhook {}: proposal {} unknown or already deleted", event.event, proposal_id);
+            }
+            Ok(EventOutcome::Deleted)
+        }
+        _ => Ok(EventOutcome::Ignored),
+    }
+}
@@ -5,15 +5,9 @@ use rig::{
 use serde_json::Value;
 use std::error::Error;
 
-pub async fn get_analysis_response(proposal_json: &String) -> Result<Value, Box<dyn Error + Send + Sync>> {
-    // Initialize the OpenAI client using environment variables
-    let client = openai::Client::from_env();
-
-    // Create a model instance
-    let model = client.completion_model(openai::GPT_35_TURBO);
-
-    let system_message = "You act as an expert with deep knowledge in blockchain technologies, decentralized organizations, and DAO management, particularly regarding the Arbitrum DAO.";
-
+/// Prompt asking for the analysis of a proposal, with the summary of its
+/// forum discussion when one is available.
+pub fn analysis_prompt(proposal_json: &str, discussion: Option<&Value>) -> String {
     let user_prompt = format!("Please help analyze the proposal in the Arbitrum DAO. Here is its brief description: [{}].\n\n\
         Analyze the proposal from the following perspectives:
         1. Technical impact on protocol development.
@@ -29,6 +23,32 @@ pub async fn get_analysis_response(proposal_json: &String) -> Result<Value, Box<
         - recommendation: An object with suggested voting options and their weights (the sum of weights must equal 1), for example:
         \"recommendation\": {{ \"For\": 0.8, \"Against\": 0.2 }}", proposal_json );
 
+    match discussion {
+        Some(discussion) => format!(
+            "{}\n\nThe community discussed the proposal on the forum. Take this discussion summary, \
+            sentiment breakdown and the concerns raised into account: [{}].",
+            user_prompt, discussion
+        ),
+        None => user_prompt,
+    }
+}
+
+/// Analyzes a proposal, taking into account the summary of its forum
+/// discussion when one is available.
+pub async fn get_analysis_response(
+    proposal_json: &str,
+    discussion: Option<&Value>,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    // Initialize the OpenAI client using environment variables
+    let client = openai::Client::from_env();
+
+    // Create a model instance
+    let model = client.completion_model(openai::GPT_35_TURBO);
+
+    let system_message = "You act as an expert with deep knowledge in blockchain technologies, decentralized organizations, and DAO management, particularly regarding the Arbitrum DAO.";
+
+    let user_prompt = analysis_prompt(proposal_json, discussion);
+
     
         let request = CompletionRequest {
             preamble: Some(system_message.to_string()),
@@ -81,3 +101,84 @@ fn clean_markdown(text: &str) -> String {
     };
     without_end.trim().to_string()
 }
+
+/// Prompt asking to summarize a forum thread; `transcript` has one post per line.
+pub fn discussion_prompt(transcript: &str) -> String {
+    format!("Please summarize the forum discussion of a DAO proposal. Each line is one post, prefixed with its number and author: [{}].\n\n\
+        Provide the following data in JSON format with key-value pairs:
+        - summary: A short summary of the discussion.
+        - sentiment: An object counting the posts by their stance on the proposal, for example:
+        \"sentiment\": {{ \"supportive\": 5, \"opposed\": 2, \"neutral\": 3 }}
+        - concerns: A list of the key concerns raised by participants.", transcript)
+}
+
+/// Summarizes a forum thread: its content, the sentiment of its posts and the
+/// concerns raised. `transcript` has one post per line.
+pub async fn get_discussion_analysis(transcript: &str) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let client = openai::Client::from_env();
+    let model = client.completion_model(openai::GPT_35_TURBO);
+
+    let system_message = "You act as an expert with deep knowledge in blockchain technologies, decentralized organizations, and DAO management, particularly regarding the Arbitrum DAO.";
+
+    let user_prompt = discussion_prompt(transcript);
+
+    let request = CompletionRequest {
+        preamble: Some(system_message.to_string()),
+        chat_history: Vec::new(),
+        prompt: user_prompt,
+        temperature: Some(0.3),
+        additional_params: None,
+        tools: Vec::new(),
+        documents: Vec::new(),
+        max_tokens: Some(512),
+    };
+
+    let response = model.completion(request).await?;
+
+    let answer_str = match response.choice {
+        ModelChoice::Message(text) => text,
+        ModelChoice::ToolCall(_, _placeholder, args) => args.to_string(),
+    };
+
+    let json_response: Value = serde_json::from_str(&clean_markdown(&answer_str))?;
+
+    Ok(json_response)
+}
+
+/// Asks the model to review a proposal draft before we submit it.
+pub async fn get_draft_review(draft_json: &String) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let client = openai::Client::from_env();
+    let model = client.completion_model(openai::GPT_35_TURBO);
+
+    let system_message = "You act as an expert with deep knowledge in blockchain technologies, decentralized organizations, and DAO management, particularly regarding the Arbitrum DAO.";
+
+    let user_prompt = format!("Please review a draft of a proposal our team is about to submit to a Snapshot space. Here is the draft: [{}].\n\n\
+        Review it before submission and provide the following data in JSON format with key-value pairs:
+        - clarity: An assessment of how clear the title, body and choices are to voters.
+        - risks: A list of the risks the proposal carries or fails to address.
+        - ambiguities: A list of statements or choices voters could read in more than one way.
+        - suggestions: A list of concrete edits that would improve the draft.
+        - readyToSubmit: true if the draft can be submitted as is, false otherwise.", draft_json);
+
+    let request = CompletionRequest {
+        preamble: Some(system_message.to_string()),
+        chat_history: Vec::new(),
+        prompt: user_prompt,
+        temperature: Some(0.3),
+        additional_params: None,
+        tools: Vec::new(),
+        documents: Vec::new(),
+        max_tokens: Some(512),
+    };
+
+    let response = model.completion(request).await?;
+
+    let answer_str = match response.choice {
+        ModelChoice::Message(text) => text,
+        ModelChoice::ToolCall(_, _placeholder, args) => args.to_string(),
+    };
+
+    let json_response: Value = serde_json::from_str(&clean_markdown(&answer_str))?;
+
+    Ok(json_response)
+}
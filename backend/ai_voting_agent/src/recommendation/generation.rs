@@ -1,20 +1,43 @@
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
-use log::{error, info};
-use std::sync::Arc;
+use log::{error, info, warn};
+use reqwest::Client as HttpClient;
+use serde_json::Value;
+use std::{error::Error, sync::Arc};
 use tokio::time::Duration;
 use tokio_postgres::NoTls;
 
 use crate::{
-    proposal_snapchot::repository::get_active_proposals_without_rec,
-    recommendation::{ai::get_analysis_response, repository::save_recommendation},
+    config::config::Config,
+    forum::ingestion::ingest_discussion,
+    ipfs::ipfs::verify_proposal,
+    recommendation::{ai::get_analysis_response, store::Recommendation},
+    identity::identity::IdentityRegistry,
+    store::store::Stores,
+    voting::schedule::queue_revote,
+    voting_power::power::ensure_voting_power,
 };
 
-/// A function that selects active proposals without a recommendation, runs the analyzer 
-/// (get_analysis_response), and saves the recommendation.
-pub async fn run_recommendation_creator(db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>) {
-    let proposals = get_active_proposals_without_rec(db_client).await;
+/// A function that selects active proposals without a valid recommendation, runs the analyzer 
+/// (get_analysis_response) with the summary of the proposal's forum thread, and saves the
+/// recommendation. If the new recommendation
+/// changes a vote we already cast, a replacement vote is queued per the re-vote policy.
+///
+/// Proposals are first checked against their IPFS pin: tampered ones are reported
+/// and never analyzed, and ones whose pin cannot be read wait for the next run.
+///
+/// Proposals where our voting power is zero are skipped, or analyzed last when
+/// `skip_zero_voting_power` is off.
+pub async fn run_recommendation_creator(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    stores: &Stores,
+    config: &Config,
+    registry: &IdentityRegistry,
+    http: &HttpClient,
+) {
+    let proposals = get_active_proposals_without_rec(stores).await;
     if let Ok(proposals) = proposals {
+        let proposals = prioritize_by_voting_power(db_client, config, http, registry, proposals).await;
         info!(
             "Found {} proposals without recommendations",
             proposals.len()
@@ -23,18 +46,53 @@ pub async fn run_recommendation_creator(db_client: &Arc<Pool<PostgresConnectionM
         for proposal in proposals {
             let mut interval = tokio::time::interval(Duration::from_secs(5));
             interval.tick().await;
-            match get_analysis_response(&proposal.to_string()).await {
+            match verify_proposal(db_client, config, http, &proposal).await {
+                Ok(Some(check)) if check.is_tampered() => {
+                    error!(
+                        "ALERT: proposal {} differs from its IPFS pin {}: {:?}",
+                        proposal["id"], check.ipfs, check.mismatches
+                    );
+                    continue;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Could not check the IPFS pin of {}: {}", proposal["id"], e);
+                    continue;
+                }
+            }
+            let discussion = match ingest_discussion(db_client, config, http, &proposal).await {
+                Ok(discussion) => discussion,
+                Err(e) => {
+                    warn!("Could not read the discussion of {}: {}", proposal["id"], e);
+                    None
+                }
+            };
+            match get_analysis_response(&proposal.to_string(), discussion.as_ref()).await {
                 Ok(recommendation) => {
                     let proposal_id = proposal["id"].as_str();
                     if let Some(proposal_id) = proposal_id {
-                        match save_recommendation(
-                            db_client,
-                            &proposal_id.to_string(),
-                            &recommendation,
-                        )
-                        .await {
-                            Ok(_) => {
+                        match stores
+                            .recommendations
+                            .save(&Recommendation::from_analysis(proposal_id, &recommendation))
+                            .await {
+                            Ok(recommendation_id) => {
                                 info!("Recommendation created for proposal {}", proposal_id);
+                                let rec_value = recommendation
+                                    .get("recommendation")
+                                    .cloned()
+                                    .unwrap_or_default();
+                                if let Err(e) = queue_revote(
+                                    stores,
+                                    config,
+                                    registry,
+                                    &proposal_id.to_string(),
+                                    recommendation_id,
+                                    &rec_value,
+                                )
+                                .await
+                                {
+                                    error!("Error checking re-vote for {}: {}", proposal_id, e);
+                                }
                             }
                             Err(e) => {
                                 error!("Error saving recommendation: {}", e);
@@ -47,8 +105,64 @@ pub async fn run_recommendation_creator(db_client: &Arc<Pool<PostgresConnectionM
                 }
             }
         }
-    } else {
-        error!("Error fetching proposals");
+    } else if let Err(e) = proposals {
+        error!("Error fetching proposals: {}", e);
     };
     println!("Recommendation creator finished");
 }
+
+/// Active proposals without a valid recommendation.
+async fn get_active_proposals_without_rec(
+    stores: &Stores,
+) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+    let active = stores.proposals.active().await?;
+    let ids: Vec<String> = active.iter().map(|proposal| proposal.id.clone()).collect();
+    let recommended = stores.recommendations.with_valid_recommendation(&ids).await?;
+    Ok(active
+        .into_iter()
+        .filter(|proposal| !recommended.contains(&proposal.id))
+        .map(|proposal| proposal.to_json())
+        .collect())
+}
+
+async fn prioritize_by_voting_power(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    http: &HttpClient,
+    registry: &IdentityRegistry,
+    proposals: Vec<Value>,
+) -> Vec<Value> {
+    let mut with_power = Vec::with_capacity(proposals.len());
+    for proposal in proposals {
+        // Combined power of every identity voting in the proposal's space.
+        let space = proposal["space"]["id"].as_str().unwrap_or_default();
+        let mut vp = Some(0.0);
+        for identity in registry.for_space(space) {
+            match ensure_voting_power(db_client, config, http, identity.address, &proposal, false).await {
+                Ok(power) => vp = vp.zip(power["vp"].as_f64()).map(|(total, vp)| total + vp),
+                Err(e) => {
+                    warn!(
+                        "Could not get voting power of {} for {}: {}",
+                        identity.name, proposal["id"], e
+                    );
+                    vp = None;
+                }
+            }
+        }
+        with_power.push((proposal, vp));
+    }
+
+    if config.skip_zero_voting_power {
+        with_power.retain(|(proposal, vp)| {
+            let keep = *vp != Some(0.0);
+            if !keep {
+                info!("Skipping proposal {}: no voting power", proposal["id"]);
+            }
+            keep
+        });
+    } else {
+        // Unknown power stays ahead of zero power.
+        with_power.sort_by_key(|(_, vp)| *vp == Some(0.0));
+    }
+    with_power.into_iter().map(|(proposal, _)| proposal).collect()
+}
@@ -1,3 +1,4 @@
 pub mod ai;
 pub mod repository;
-pub mod generation;
\ No newline at end of file
+pub mod generation;
+pub mod store;
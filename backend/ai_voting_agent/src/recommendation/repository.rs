@@ -1,133 +1,195 @@
+use async_trait::async_trait;
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
-use serde_json::{json, Value};
-use tokio_postgres::types::Json;
-use std::error::Error;
-use std::sync::Arc;
-use tokio_postgres::NoTls;
-
-pub async fn get_recommendation_by_id(
-    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
-    id: &String, 
-) -> Result<Value, Box<dyn Error>> {
-    let conn = db_client.get().await?;
-    
-    let row = conn
-        .query_one(
-            r#"
-            SELECT 
-                proposal_id, 
-                technical_impact, 
-                economic_consequences, 
-                governance_and_decentralization, 
-                advantages, 
-                risks, 
-                recommendation, 
-                created_at
-            FROM recommendations
-            WHERE proposal_id = $1
-            ORDER BY created_at DESC
-            LIMIT 1
-            "#,
-            &[id],
-        )
-        .await?;
-    
-    let proposal_id: String = row.get("proposal_id");
-    let technical_impact: Value = row.get("technical_impact");
-    let economic_consequences: Value = row.get("economic_consequences");
-    let governance_and_decentralization: Value = row.get("governance_and_decentralization");
-    let advantages: Value = row.get("advantages");
-    let risks: Value = row.get("risks");
-    let recommendation: Value = row.get("recommendation");
-    let created_at: i64 = row.get("created_at");
-    
-    let result = json!({
-        "proposalId": proposal_id,
-        "technicalImpact": technical_impact,
-        "economicConsequences": economic_consequences,
-        "governanceAndDecentralization": governance_and_decentralization,
-        "advantages": advantages,
-        "risks": risks,
-        "recommendation": recommendation,
-        "createdAt": created_at,
-    });
-    
-    Ok(result)
+use serde_json::Value;
+use std::{collections::HashSet, error::Error, sync::Arc};
+use tokio_postgres::{types::Json, NoTls, Row};
+
+use crate::recommendation::store::{Recommendation, RecommendationStore};
+
+const RECOMMENDATION_COLUMNS: &str = r#"
+    id,
+    proposal_id,
+    technical_impact,
+    economic_consequences,
+    governance_and_decentralization,
+    advantages,
+    risks,
+    recommendation,
+    new_flag,
+    created_at,
+    approved_by,
+    (EXTRACT(EPOCH FROM approved_at))::int8 AS approved_at,
+    approval_comment,
+    (EXTRACT(EPOCH FROM escalated_at))::int8 AS escalated_at,
+    (EXTRACT(EPOCH FROM invalidated_at))::int8 AS invalidated_at,
+    invalidation_reason,
+    supersedes_id
+"#;
+
+/// A nullable JSONB column, `null` when unset.
+fn json_column(row: &Row, column: &str) -> Value {
+    row.get::<_, Option<Value>>(column).unwrap_or(Value::Null)
+}
+
+fn row_to_recommendation(row: &Row) -> Recommendation {
+    Recommendation {
+        id: row.get("id"),
+        proposal_id: row.get("proposal_id"),
+        technical_impact: json_column(row, "technical_impact"),
+        economic_consequences: json_column(row, "economic_consequences"),
+        governance_and_decentralization: json_column(row, "governance_and_decentralization"),
+        advantages: json_column(row, "advantages"),
+        risks: json_column(row, "risks"),
+        recommendation: json_column(row, "recommendation"),
+        new_flag: row.get("new_flag"),
+        created_at: row.get("created_at"),
+        approved_by: row.get("approved_by"),
+        approved_at: row.get("approved_at"),
+        approval_comment: row.get("approval_comment"),
+        escalated_at: row.get("escalated_at"),
+        invalidated_at: row.get("invalidated_at"),
+        invalidation_reason: row.get("invalidation_reason"),
+        supersedes_id: row.get("supersedes_id"),
+    }
 }
 
+/// Recommendations stored in the `recommendations` table.
+#[derive(Clone)]
+pub struct PgRecommendationStore {
+    db_client: Arc<Pool<PostgresConnectionManager<NoTls>>>,
+}
+
+impl PgRecommendationStore {
+    pub fn new(db_client: Arc<Pool<PostgresConnectionManager<NoTls>>>) -> Self {
+        PgRecommendationStore { db_client }
+    }
 
-pub async fn get_new_recommendation(
-    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
-    id: &String, 
-) -> Result<Value, Box<dyn Error>> {
-    let conn = db_client.get().await?;
-    
-    let row = conn
-        .query_one(
+    async fn select_one(
+        &self,
+        condition: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let query = format!(
+            "SELECT {} FROM recommendations {} ORDER BY created_at DESC, id DESC LIMIT 1",
+            RECOMMENDATION_COLUMNS, condition
+        );
+        let row = conn.query_opt(&query, params).await?;
+        Ok(row.as_ref().map(row_to_recommendation))
+    }
+}
+
+#[async_trait]
+impl RecommendationStore for PgRecommendationStore {
+    async fn latest(&self, proposal_id: &str) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>> {
+        self.select_one("WHERE proposal_id = $1", &[&proposal_id]).await
+    }
+
+    async fn latest_valid(&self, proposal_id: &str) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>> {
+        self.select_one("WHERE proposal_id = $1 AND invalidated_at IS NULL", &[&proposal_id])
+            .await
+    }
+
+    async fn newest_flagged(&self) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>> {
+        self.select_one("WHERE new_flag = true", &[]).await
+    }
+
+    async fn approve_latest(
+        &self,
+        proposal_id: &str,
+        approved_by: &str,
+        comment: Option<&str>,
+    ) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let query = format!(
             r#"
-            SELECT 
-                proposal_id, 
-                recommendation, 
-            FROM recommendations
-            WHERE new_flag = true
-            ORDER BY created_at DESC
-            LIMIT 1
+            UPDATE recommendations
+            SET approved_by = $2, approved_at = NOW(), approval_comment = $3
+            WHERE id = (
+                SELECT id FROM recommendations
+                WHERE proposal_id = $1 AND invalidated_at IS NULL
+                ORDER BY created_at DESC, id DESC
+                LIMIT 1
+            )
+            RETURNING {}
             "#,
-            &[id],
+            RECOMMENDATION_COLUMNS
+        );
+        let row = conn.query_opt(&query, &[&proposal_id, &approved_by, &comment]).await?;
+        Ok(row.as_ref().map(row_to_recommendation))
+    }
+
+    async fn mark_escalated(&self, id: i64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        conn.execute(
+            "UPDATE recommendations SET escalated_at = NOW() WHERE id = $1",
+            &[&id],
         )
         .await?;
-    
-    let proposal_id: String = row.get("proposal_id");
-    let recommendation: Value = row.get("recommendation");
-    
-    let result = json!({
-        "proposalId": proposal_id,
-        "voteOption": recommendation,
-    });
-    
-    Ok(result)
-}
+        Ok(())
+    }
 
+    async fn invalidate(&self, proposal_ids: &[String], reason: &str) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let invalidated = conn
+            .execute(
+                r#"
+                UPDATE recommendations
+                SET invalidated_at = NOW(), invalidation_reason = $2
+                WHERE proposal_id = ANY($1) AND invalidated_at IS NULL
+                "#,
+                &[&proposal_ids, &reason],
+            )
+            .await?;
+        Ok(invalidated)
+    }
+
+    async fn save(&self, recommendation: &Recommendation) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+
+        let query = r#"
+            INSERT INTO recommendations
+                (proposal_id, technical_impact, economic_consequences, governance_and_decentralization, advantages, risks, recommendation,
+                 supersedes_id)
+            VALUES
+                ($1, $2, $3, $4, $5, $6, $7,
+                 (SELECT id FROM recommendations WHERE proposal_id = $1 ORDER BY created_at DESC, id DESC LIMIT 1))
+            RETURNING id
+        "#;
+
+        let row = conn.query_one(
+            query,
+            &[
+                &recommendation.proposal_id,
+                &Json(&recommendation.technical_impact),
+                &Json(&recommendation.economic_consequences),
+                &Json(&recommendation.governance_and_decentralization),
+                &Json(&recommendation.advantages),
+                &Json(&recommendation.risks),
+                &Json(&recommendation.recommendation),
+            ],
+        )
+        .await?;
 
+        Ok(row.get("id"))
+    }
 
-pub async fn save_recommendation(
-    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
-    proposal_id: &String,
-    recommendation: &Value,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let conn = db_client.get().await?;
-    
-    let technical_impact = recommendation.get("technicalImpact").cloned().unwrap_or(Value::Null);
-    let economic_consequences = recommendation.get("economicConsequences").cloned().unwrap_or(Value::Null);
-    let governance_and_decentralization = recommendation
-        .get("governanceAndDecentralization")
-        .cloned()
-        .unwrap_or(Value::Null);
-    let advantages = recommendation.get("advantages").cloned().unwrap_or(Value::Null);
-    let risks = recommendation.get("risks").cloned().unwrap_or(Value::Null);
-    let rec_value = recommendation.get("recommendation").cloned().unwrap_or(Value::Null);
-
-    let query = r#"
-        INSERT INTO recommendations 
-            (proposal_id, technical_impact, economic_consequences, governance_and_decentralization, advantages, risks, recommendation)
-        VALUES
-            ($1, $2, $3, $4, $5, $6, $7)
-    "#;
-
-    conn.execute(
-        query,
-        &[
-            proposal_id,
-            &Json(technical_impact),
-            &Json(economic_consequences),
-            &Json(governance_and_decentralization),
-            &Json(advantages),
-            &Json(risks),
-            &Json(rec_value),
-        ],
-    )
-    .await?;
-
-    Ok(())
+    async fn with_valid_recommendation(
+        &self,
+        proposal_ids: &[String],
+    ) -> Result<HashSet<String>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let rows = conn
+            .query(
+                r#"
+                SELECT DISTINCT proposal_id
+                FROM recommendations
+                WHERE proposal_id = ANY($1) AND invalidated_at IS NULL
+                "#,
+                &[&proposal_ids],
+            )
+            .await?;
+        Ok(rows.iter().map(|row| row.get("proposal_id")).collect())
+    }
 }
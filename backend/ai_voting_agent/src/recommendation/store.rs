@@ -0,0 +1,229 @@
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::{collections::HashSet, error::Error, sync::Mutex};
+
+/// A stored analysis of a proposal with its review state.
+#[derive(Debug, Clone, Default)]
+pub struct Recommendation {
+    pub id: i64,
+    pub proposal_id: String,
+    pub technical_impact: Value,
+    pub economic_consequences: Value,
+    pub governance_and_decentralization: Value,
+    pub advantages: Value,
+    pub risks: Value,
+    /// Weight per voting option, e.g. `{ "For": 0.8, "Against": 0.2 }`.
+    pub recommendation: Value,
+    pub new_flag: bool,
+    pub created_at: i64, // Unix‑time
+    pub approved_by: Option<String>,
+    pub approved_at: Option<i64>,
+    pub approval_comment: Option<String>,
+    pub escalated_at: Option<i64>,
+    pub invalidated_at: Option<i64>,
+    pub invalidation_reason: Option<String>,
+    pub supersedes_id: Option<i64>,
+}
+
+impl Recommendation {
+    /// Takes the analysis fields from an LLM answer.
+    pub fn from_analysis(proposal_id: &str, analysis: &Value) -> Self {
+        let field = |key: &str| analysis.get(key).cloned().unwrap_or(Value::Null);
+        Recommendation {
+            proposal_id: proposal_id.to_string(),
+            technical_impact: field("technicalImpact"),
+            economic_consequences: field("economicConsequences"),
+            governance_and_decentralization: field("governanceAndDecentralization"),
+            advantages: field("advantages"),
+            risks: field("risks"),
+            recommendation: field("recommendation"),
+            new_flag: true,
+            ..Default::default()
+        }
+    }
+
+    /// The analysis fields, keyed as in the LLM answer.
+    pub fn analysis(&self) -> Value {
+        json!({
+            "technicalImpact": self.technical_impact,
+            "economicConsequences": self.economic_consequences,
+            "governanceAndDecentralization": self.governance_and_decentralization,
+            "advantages": self.advantages,
+            "risks": self.risks,
+            "recommendation": self.recommendation,
+        })
+    }
+
+    /// The recommendation as served by the API.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "proposalId": self.proposal_id,
+            "technicalImpact": self.technical_impact,
+            "economicConsequences": self.economic_consequences,
+            "governanceAndDecentralization": self.governance_and_decentralization,
+            "advantages": self.advantages,
+            "risks": self.risks,
+            "recommendation": self.recommendation,
+            "createdAt": self.created_at,
+            "approvedBy": self.approved_by,
+            "approvedAt": self.approved_at,
+            "escalatedAt": self.escalated_at,
+            "invalidatedAt": self.invalidated_at,
+            "invalidationReason": self.invalidation_reason,
+            "supersedesId": self.supersedes_id,
+        })
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.invalidated_at.is_none()
+    }
+}
+
+/// Storage of the recommendations produced for proposals.
+#[async_trait]
+pub trait RecommendationStore: Send + Sync {
+    /// The newest recommendation for a proposal, invalidated or not.
+    async fn latest(&self, proposal_id: &str) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>>;
+
+    /// The newest recommendation for a proposal that was not invalidated.
+    async fn latest_valid(&self, proposal_id: &str) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>>;
+
+    /// The newest recommendation still flagged as new.
+    async fn newest_flagged(&self) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>>;
+
+    /// Marks the latest valid recommendation for a proposal as approved and returns it.
+    async fn approve_latest(
+        &self,
+        proposal_id: &str,
+        approved_by: &str,
+        comment: Option<&str>,
+    ) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>>;
+
+    async fn mark_escalated(&self, id: i64) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Invalidates the current recommendations of the given proposals so they are
+    /// analyzed again. Returns the number of invalidated recommendations.
+    async fn invalidate(&self, proposal_ids: &[String], reason: &str) -> Result<u64, Box<dyn Error + Send + Sync>>;
+
+    /// Saves a recommendation and returns its id. The new recommendation records
+    /// the one it supersedes, if any.
+    async fn save(&self, recommendation: &Recommendation) -> Result<i64, Box<dyn Error + Send + Sync>>;
+
+    /// The proposals among `proposal_ids` that have a valid recommendation.
+    async fn with_valid_recommendation(
+        &self,
+        proposal_ids: &[String],
+    ) -> Result<HashSet<String>, Box<dyn Error + Send + Sync>>;
+}
+
+/// Recommendations kept in memory, for tests and local runs without a database.
+#[derive(Debug, Default)]
+pub struct MemoryRecommendationStore {
+    recommendations: Mutex<Vec<Recommendation>>,
+}
+
+impl MemoryRecommendationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Newest first; ids grow with every save, so they break ties within a second.
+    fn newest(&self, keep: impl Fn(&Recommendation) -> bool) -> Option<Recommendation> {
+        self.recommendations
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| keep(r))
+            .max_by_key(|r| (r.created_at, r.id))
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl RecommendationStore for MemoryRecommendationStore {
+    async fn latest(&self, proposal_id: &str) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>> {
+        Ok(self.newest(|r| r.proposal_id == proposal_id))
+    }
+
+    async fn latest_valid(&self, proposal_id: &str) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>> {
+        Ok(self.newest(|r| r.proposal_id == proposal_id && r.is_valid()))
+    }
+
+    async fn newest_flagged(&self) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>> {
+        Ok(self.newest(|r| r.new_flag))
+    }
+
+    async fn approve_latest(
+        &self,
+        proposal_id: &str,
+        approved_by: &str,
+        comment: Option<&str>,
+    ) -> Result<Option<Recommendation>, Box<dyn Error + Send + Sync>> {
+        let Some(latest) = self.newest(|r| r.proposal_id == proposal_id && r.is_valid()) else {
+            return Ok(None);
+        };
+        let mut recommendations = self.recommendations.lock().unwrap();
+        let Some(rec) = recommendations.iter_mut().find(|r| r.id == latest.id) else {
+            return Ok(None);
+        };
+        rec.approved_by = Some(approved_by.to_string());
+        rec.approved_at = Some(chrono::Utc::now().timestamp());
+        rec.approval_comment = comment.map(str::to_string);
+        Ok(Some(rec.clone()))
+    }
+
+    async fn mark_escalated(&self, id: i64) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut recommendations = self.recommendations.lock().unwrap();
+        if let Some(rec) = recommendations.iter_mut().find(|r| r.id == id) {
+            rec.escalated_at = Some(chrono::Utc::now().timestamp());
+        }
+        Ok(())
+    }
+
+    async fn invalidate(&self, proposal_ids: &[String], reason: &str) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut invalidated = 0;
+        for rec in self.recommendations.lock().unwrap().iter_mut() {
+            if rec.is_valid() && proposal_ids.contains(&rec.proposal_id) {
+                rec.invalidated_at = Some(now);
+                rec.invalidation_reason = Some(reason.to_string());
+                invalidated += 1;
+            }
+        }
+        Ok(invalidated)
+    }
+
+    async fn save(&self, recommendation: &Recommendation) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let supersedes_id = self.newest(|r| r.proposal_id == recommendation.proposal_id).map(|r| r.id);
+        let mut recommendations = self.recommendations.lock().unwrap();
+        let id = recommendations.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+        recommendations.push(Recommendation {
+            id,
+            created_at: chrono::Utc::now().timestamp(),
+            new_flag: true,
+            approved_by: None,
+            approved_at: None,
+            approval_comment: None,
+            escalated_at: None,
+            invalidated_at: None,
+            invalidation_reason: None,
+            supersedes_id,
+            ..recommendation.clone()
+        });
+        Ok(id)
+    }
+
+    async fn with_valid_recommendation(
+        &self,
+        proposal_ids: &[String],
+    ) -> Result<HashSet<String>, Box<dyn Error + Send + Sync>> {
+        Ok(self
+            .recommendations
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.is_valid() && proposal_ids.contains(&r.proposal_id))
+            .map(|r| r.proposal_id.clone())
+            .collect())
+    }
+}
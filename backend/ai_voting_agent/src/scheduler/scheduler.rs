@@ -1,21 +1,40 @@
 use bb8::Pool;
 use bb8_postgres::PostgresConnectionManager;
 use log::info;
+use reqwest::Client as HttpClient;
 use std::sync::Arc;
 use tokio::time::Duration;
 use tokio_postgres::NoTls;
 
 use crate::{
-    proposal_snapchot::collector::run_collect,
+    config::config::Config,
+    proposal_snapchot::{collector::run_collect, hub::HubClient},
     recommendation::generation::run_recommendation_creator,
+    identity::identity::IdentityRegistry,
+    store::store::Stores,
+    verification::verification::run_verification,
+    voting::{
+        reconciler::run_reconciler,
+        safe::run_safe_signature_collection,
+        schedule::{escalate_unapproved, run_vote_executions},
+    },
 };
 
-pub async fn start_scheduler(pool: Arc<Pool<PostgresConnectionManager<NoTls>>>) {
+pub async fn start_scheduler(
+    pool: Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    stores: Stores,
+    config: Config,
+    registry: Arc<IdentityRegistry>,
+    hub: Arc<HubClient>,
+    http: HttpClient,
+) {
     let mut interval = tokio::time::interval(Duration::from_secs(300));
     loop {
+        // Webhook events are applied as they arrive; polling still runs and
+        // catches up on deliveries that were missed or failed.
         {
             info!("Scheduler: collecting proposals");
-            let res = run_collect(&pool).await;
+            let res = run_collect(&pool, &stores, &config, &hub).await;
             match res {
                 Ok(_) => {
                     println!("Scheduler: collecting proposals finished");
@@ -27,7 +46,21 @@ pub async fn start_scheduler(pool: Arc<Pool<PostgresConnectionManager<NoTls>>>)
         }
         println!("Scheduler: generating recommendations ");
         {
-            let _ = run_recommendation_creator(&pool).await;
+            let _ = run_recommendation_creator(&pool, &stores, &config, &registry, &http).await;
+        }
+        println!("Scheduler: running scheduled votes");
+        {
+            escalate_unapproved(&stores, &config).await;
+            run_vote_executions(&stores, &config, &registry, &http).await;
+            run_safe_signature_collection(&stores, &config, &registry, &http).await;
+        }
+        println!("Scheduler: reconciling submitted votes");
+        {
+            run_reconciler(&stores, &config, &registry, &hub, &http).await;
+        }
+        println!("Scheduler: verifying signatures");
+        {
+            run_verification(&pool, &config, &http).await;
         }
         interval.tick().await;
     }
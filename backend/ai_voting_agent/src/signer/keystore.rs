@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Signature, H256};
+use std::fmt;
+
+use crate::signer::signer::{SignerError, VoteSigner};
+
+/// Signer backed by an encrypted JSON keystore (Web3 Secret Storage).
+pub struct KeystoreSigner {
+    wallet: LocalWallet,
+}
+
+impl KeystoreSigner {
+    /// Decrypts the keystore at `path`. Chain id 1 is used, as Snapshot
+    /// messages are signed off-chain.
+    pub fn from_file(path: &str, password: &str) -> Result<Self, SignerError> {
+        let wallet = LocalWallet::decrypt_keystore(path, password)
+            .map_err(|err| SignerError(format!("Cannot decrypt keystore {}: {}", path, err)))?;
+        Ok(KeystoreSigner {
+            wallet: wallet.with_chain_id(1u64),
+        })
+    }
+}
+
+impl fmt::Debug for KeystoreSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeystoreSigner")
+            .field("address", &self.wallet.address())
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl VoteSigner for KeystoreSigner {
+    fn address(&self) -> Address {
+        self.wallet.address()
+    }
+
+    async fn sign_hash(&self, hash: H256) -> Result<Signature, SignerError> {
+        self.wallet
+            .sign_hash(hash)
+            .map_err(|err| SignerError(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/keystore.json");
+    const PASSWORD: &str = "fixture-password";
+    const ADDRESS: &str = "0x2c7536E3605D9C16a7a3D7b1898e529396a65c23";
+
+    #[tokio::test]
+    async fn decrypts_keystore_and_signs_as_its_address() {
+        let signer = KeystoreSigner::from_file(FIXTURE, PASSWORD).unwrap();
+        assert_eq!(signer.address(), ADDRESS.parse::<Address>().unwrap());
+
+        let hash = H256::repeat_byte(0x42);
+        let signature = signer.sign_hash(hash).await.unwrap();
+        assert_eq!(signature.recover(hash).unwrap(), signer.address());
+    }
+
+    #[test]
+    fn rejects_wrong_password_and_missing_file() {
+        assert!(KeystoreSigner::from_file(FIXTURE, "wrong-password").is_err());
+        assert!(KeystoreSigner::from_file("/nonexistent/keystore.json", PASSWORD).is_err());
+    }
+
+    #[test]
+    fn debug_output_hides_the_key() {
+        let signer = KeystoreSigner::from_file(FIXTURE, PASSWORD).unwrap();
+        let debug = format!("{:?}", signer);
+        assert!(debug.contains("KeystoreSigner"));
+        assert!(!debug.contains("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"));
+    }
+}
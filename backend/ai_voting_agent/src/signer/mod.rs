@@ -0,0 +1,3 @@
+pub mod signer;
+pub mod keystore;
+pub mod remote;
\ No newline at end of file
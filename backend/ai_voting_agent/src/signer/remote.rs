@@ -0,0 +1,209 @@
+use async_trait::async_trait;
+use ethers::types::{Address, Signature, H256};
+use reqwest::Client as HttpClient;
+use serde_json::{json, Value};
+use std::{fmt, str::FromStr, time::Duration};
+
+use crate::signer::signer::{SignerError, VoteSigner};
+
+/// Signer that delegates to a remote signing service over HTTP.
+///
+/// The protocol is deliberately small so that a local stand-in can serve it:
+///
+/// * `GET {url}/address` answers `{ "address": "0x…" }`.
+/// * `POST {url}/sign` with `{ "address": "0x…", "hash": "0x…" }` answers
+///   `{ "signature": "0x…" }`, a 65-byte signature over the raw hash.
+///
+/// When a token is configured it is sent as `Authorization: Bearer <token>`.
+pub struct RemoteSigner {
+    url: String,
+    token: Option<String>,
+    address: Address,
+    client: HttpClient,
+}
+
+impl RemoteSigner {
+    /// Connects to the service. The address is asked from the service unless
+    /// `address` is given, in which case the service must agree with it.
+    pub async fn connect(
+        url: &str,
+        token: Option<String>,
+        address: Option<&str>,
+    ) -> Result<Self, SignerError> {
+        let client = HttpClient::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|err| SignerError(err.to_string()))?;
+        let mut signer = RemoteSigner {
+            url: url.trim_end_matches('/').to_string(),
+            token,
+            address: Address::zero(),
+            client,
+        };
+
+        let remote_address = signer.fetch_address().await?;
+        if let Some(address) = address {
+            let expected = Address::from_str(address)
+                .map_err(|err| SignerError(format!("Invalid signer address {}: {}", address, err)))?;
+            if expected != remote_address {
+                return Err(SignerError(format!(
+                    "Remote signer serves {:?}, expected {:?}",
+                    remote_address, expected
+                )));
+            }
+        }
+        signer.address = remote_address;
+        Ok(signer)
+    }
+
+    fn request(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    async fn fetch_address(&self) -> Result<Address, SignerError> {
+        let response = self
+            .request(self.client.get(format!("{}/address", self.url)))
+            .send()
+            .await
+            .map_err(|err| SignerError(err.to_string()))?;
+        if !response.status().is_success() {
+            return Err(SignerError(format!(
+                "Remote signer returned {} for /address",
+                response.status()
+            )));
+        }
+        let body: Value = response.json().await.map_err(|err| SignerError(err.to_string()))?;
+        let address = body["address"]
+            .as_str()
+            .ok_or_else(|| SignerError("No address in remote signer response".to_string()))?;
+        Address::from_str(address).map_err(|err| SignerError(err.to_string()))
+    }
+}
+
+impl fmt::Debug for RemoteSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteSigner")
+            .field("url", &self.url)
+            .field("address", &self.address)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl VoteSigner for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_hash(&self, hash: H256) -> Result<Signature, SignerError> {
+        let body = json!({
+            "address": ethers::utils::to_checksum(&self.address, None),
+            "hash": format!("{:?}", hash),
+        });
+        let response = self
+            .request(self.client.post(format!("{}/sign", self.url)))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|err| SignerError(err.to_string()))?;
+        if !response.status().is_success() {
+            return Err(SignerError(format!(
+                "Remote signer returned {} for /sign",
+                response.status()
+            )));
+        }
+        let body: Value = response.json().await.map_err(|err| SignerError(err.to_string()))?;
+        let signature = body["signature"]
+            .as_str()
+            .ok_or_else(|| SignerError("No signature in remote signer response".to_string()))?;
+        let signature =
+            Signature::from_str(signature).map_err(|err| SignerError(err.to_string()))?;
+
+        // Never trust a signature that does not come from the expected key.
+        let recovered = signature
+            .recover(ethers::types::RecoveryMessage::Hash(hash))
+            .map_err(|err| SignerError(err.to_string()))?;
+        if recovered != self.address {
+            return Err(SignerError(format!(
+                "Remote signature recovers to {:?} instead of {:?}",
+                recovered, self.address
+            )));
+        }
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::stub::StubServer;
+    use ethers::signers::{LocalWallet, Signer};
+
+    fn wallet(key: &str) -> LocalWallet {
+        key.parse().unwrap()
+    }
+
+    fn our_wallet() -> LocalWallet {
+        wallet("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+    }
+
+    fn other_wallet() -> LocalWallet {
+        wallet("0123456789012345678901234567890123456789012345678901234567890123")
+    }
+
+    /// A signing service that reports `served`'s address and signs with `signing`.
+    async fn service(served: LocalWallet, signing: LocalWallet) -> StubServer {
+        StubServer::start(move |method, path, body| match (method, path) {
+            ("GET", "/address") => (200, json!({ "address": format!("{:?}", served.address()) }).to_string()),
+            ("POST", "/sign") => {
+                let body: Value = serde_json::from_str(body).unwrap();
+                let hash = H256::from_str(body["hash"].as_str().unwrap()).unwrap();
+                let signature = signing.sign_hash(hash).unwrap();
+                (200, json!({ "signature": format!("0x{}", signature) }).to_string())
+            }
+            _ => (404, "{}".to_string()),
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn signs_through_the_service() {
+        let server = service(our_wallet(), our_wallet()).await;
+        let signer = RemoteSigner::connect(&server.url, Some("token".to_string()), None)
+            .await
+            .unwrap();
+        assert_eq!(signer.address(), our_wallet().address());
+
+        let hash = H256::repeat_byte(0x42);
+        let signature = signer.sign_hash(hash).await.unwrap();
+        assert_eq!(signature.recover(hash).unwrap(), our_wallet().address());
+    }
+
+    #[tokio::test]
+    async fn rejects_a_service_serving_another_address() {
+        let server = service(other_wallet(), other_wallet()).await;
+        let expected = format!("{:?}", our_wallet().address());
+        let err = RemoteSigner::connect(&server.url, None, Some(&expected))
+            .await
+            .unwrap_err();
+        assert!(err.0.contains("expected"));
+    }
+
+    #[tokio::test]
+    async fn rejects_signatures_recovering_to_another_address() {
+        let server = service(our_wallet(), other_wallet()).await;
+        let signer = RemoteSigner::connect(&server.url, None, None).await.unwrap();
+        let err = signer.sign_hash(H256::repeat_byte(0x42)).await.unwrap_err();
+        assert!(err.0.contains("recovers to"));
+    }
+
+    #[tokio::test]
+    async fn reports_service_errors() {
+        let server = StubServer::start(|_, _, _| (500, "{}".to_string())).await;
+        let err = RemoteSigner::connect(&server.url, None, None).await.unwrap_err();
+        assert!(err.0.contains("/address"));
+    }
+}
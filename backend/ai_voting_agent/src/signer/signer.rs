@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+use ethers::signers::to_eip155_v;
+use ethers::types::{transaction::eip2718::TypedTransaction, Address, Bytes, Signature, H256};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::config::config::SignerConfig;
+use crate::signer::{keystore::KeystoreSigner, remote::RemoteSigner};
+
+#[derive(Debug)]
+pub struct SignerError(pub String);
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signer error: {}", self.0)
+    }
+}
+
+impl Error for SignerError {}
+
+/// Signs vote hashes on behalf of one address.
+///
+/// Implementations hold or reach the key; the key material itself never
+/// leaves them and their `Debug` output never contains it.
+#[async_trait]
+pub trait VoteSigner: Send + Sync + fmt::Debug {
+    /// Address the signatures recover to.
+    fn address(&self) -> Address;
+
+    /// Signs a 32-byte hash as is, without the `eth_sign` prefix.
+    async fn sign_hash(&self, hash: H256) -> Result<Signature, SignerError>;
+}
+
+/// Signs a transaction whose chain id is set and returns it RLP-encoded,
+/// ready for `eth_sendRawTransaction`.
+pub async fn sign_transaction(
+    signer: &Arc<dyn VoteSigner>,
+    tx: &TypedTransaction,
+) -> Result<Bytes, SignerError> {
+    let chain_id = tx
+        .chain_id()
+        .ok_or_else(|| SignerError("Transaction has no chain id".to_string()))?
+        .as_u64();
+    let mut signature = signer.sign_hash(tx.sighash()).await?;
+    // Signers give v = 27 + recovery id, or the bare recovery id as some remote
+    // signers do; transactions expect EIP-155 v.
+    let recovery_id = match signature.v {
+        0 | 1 => signature.v,
+        27 | 28 => signature.v - 27,
+        v => return Err(SignerError(format!("Unexpected signature v {}", v))),
+    };
+    signature.v = to_eip155_v(recovery_id as u8, chain_id);
+    Ok(tx.rlp_signed(&signature))
+}
+
+/// Loads the signer described by the configuration. Called once at startup.
+pub async fn load_signer(config: &SignerConfig) -> Result<Arc<dyn VoteSigner>, SignerError> {
+    match config {
+        SignerConfig::Keystore {
+            path,
+            password_file,
+            password,
+        } => {
+            let password = match (password_file, password) {
+                (Some(file), _) => std::fs::read_to_string(file)
+                    .map(|p| p.trim_end_matches(['\r', '\n']).to_string())
+                    .map_err(|err| SignerError(format!("Cannot read keystore password file: {}", err)))?,
+                (None, Some(password)) => password.clone(),
+                (None, None) => {
+                    return Err(SignerError("No keystore password configured".to_string()))
+                }
+            };
+            Ok(Arc::new(KeystoreSigner::from_file(path, &password)?))
+        }
+        SignerConfig::Remote {
+            url,
+            token,
+            address,
+        } => Ok(Arc::new(
+            RemoteSigner::connect(url, token.clone(), address.as_deref()).await?,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::TransactionRequest;
+
+    /// Signs with a local key and reports v as `offset` + recovery id.
+    #[derive(Debug)]
+    struct OffsetSigner {
+        wallet: LocalWallet,
+        offset: u64,
+    }
+
+    #[async_trait]
+    impl VoteSigner for OffsetSigner {
+        fn address(&self) -> Address {
+            self.wallet.address()
+        }
+
+        async fn sign_hash(&self, hash: H256) -> Result<Signature, SignerError> {
+            let mut signature = self
+                .wallet
+                .sign_hash(hash)
+                .map_err(|err| SignerError(err.to_string()))?;
+            signature.v = signature.v - 27 + self.offset;
+            Ok(signature)
+        }
+    }
+
+    fn signer(offset: u64) -> Arc<dyn VoteSigner> {
+        let wallet: LocalWallet = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"
+            .parse()
+            .unwrap();
+        Arc::new(OffsetSigner { wallet, offset })
+    }
+
+    fn transaction() -> TypedTransaction {
+        TransactionRequest::new()
+            .to(Address::repeat_byte(0x11))
+            .value(1)
+            .nonce(0)
+            .gas(21_000)
+            .gas_price(1)
+            .chain_id(42161)
+            .into()
+    }
+
+    #[tokio::test]
+    async fn accepts_electrum_and_raw_recovery_ids() {
+        for offset in [27, 0] {
+            let signer = signer(offset);
+            let raw = sign_transaction(&signer, &transaction()).await.unwrap();
+            let rlp = ethers::utils::rlp::Rlp::new(&raw);
+            let (_, signature) = TypedTransaction::decode_signed(&rlp).unwrap();
+            assert!(signature.v == 42161 * 2 + 35 || signature.v == 42161 * 2 + 36);
+            assert_eq!(signature.recover(transaction().sighash()).unwrap(), signer.address());
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_other_v_values() {
+        let error = sign_transaction(&signer(5), &transaction()).await.unwrap_err();
+        assert!(error.0.contains("Unexpected signature v"));
+    }
+}
@@ -0,0 +1,56 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use std::sync::Arc;
+use tokio_postgres::NoTls;
+
+use crate::{
+    proposal_snapchot::{
+        prop_struct::Proposal,
+        repository::PgProposalStore,
+        store::{MemoryProposalStore, ProposalStore},
+    },
+    recommendation::{
+        repository::PgRecommendationStore,
+        store::{MemoryRecommendationStore, RecommendationStore},
+    },
+    voting::{
+        repository::PgVoteStore,
+        store::{MemoryVoteStore, VoteStore},
+    },
+};
+
+/// The stores the scheduler, the API and the analysis read and write through.
+#[derive(Clone)]
+pub struct Stores {
+    pub proposals: Arc<dyn ProposalStore>,
+    pub recommendations: Arc<dyn RecommendationStore>,
+    pub votes: Arc<dyn VoteStore>,
+}
+
+impl Stores {
+    pub fn postgres(db_client: Arc<Pool<PostgresConnectionManager<NoTls>>>) -> Self {
+        Stores {
+            proposals: Arc::new(PgProposalStore::new(db_client.clone())),
+            recommendations: Arc::new(PgRecommendationStore::new(db_client.clone())),
+            votes: Arc::new(PgVoteStore::new(db_client)),
+        }
+    }
+
+    pub fn memory() -> Self {
+        Self::memory_with_proposals(Vec::new())
+    }
+
+    /// In-memory stores holding the given proposals.
+    pub fn memory_with_proposals(proposals: Vec<Proposal>) -> Self {
+        let store = MemoryProposalStore::new();
+        for proposal in proposals {
+            store.insert(proposal);
+        }
+        let proposals: Arc<dyn ProposalStore> = Arc::new(store);
+        Stores {
+            proposals: proposals.clone(),
+            recommendations: Arc::new(MemoryRecommendationStore::new()),
+            votes: Arc::new(MemoryVoteStore::new(proposals)),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use ethers::prelude::*;
+use log::{error, info, warn};
+use reqwest::Client as HttpClient;
+use serde_json::{json, Value};
+use std::{error::Error, str::FromStr, sync::Arc};
+use tokio_postgres::NoTls;
+
+use crate::{
+    config::config::Config,
+    contracts::contracts::{http_provider, Erc1271, EIP1271_MAGIC_VALUE},
+    ipfs::{ipfs::verify_proposal, repository::get_unchecked_proposals},
+    voting::{
+        repository::{get_unverified_votes, set_vote_verified, UnverifiedVote},
+        snapshot::{hex, vote_hash},
+    },
+};
+
+/// The signature recovers to the signer.
+pub const SIGNATURE_ECDSA: &str = "ecdsa";
+/// The signer is a contract that accepted the signature through `isValidSignature`.
+pub const SIGNATURE_EIP1271: &str = "eip1271";
+
+/// Rows of each kind checked per run.
+const VERIFICATION_BATCH: i64 = 100;
+
+/// Checks that `signer` signed `hash`, first as an externally owned account and
+/// then, when `signer` is a contract on `network`, through EIP-1271.
+///
+/// Returns how the signature was validated, or `None` when it is not valid.
+pub async fn verify_signature(
+    config: &Config,
+    network: &str,
+    signer: Address,
+    hash: H256,
+    signature: &str,
+) -> Result<Option<&'static str>, Box<dyn Error + Send + Sync>> {
+    if let Ok(parsed) = signature.parse::<Signature>() {
+        if parsed.recover(RecoveryMessage::Hash(hash)).ok() == Some(signer) {
+            return Ok(Some(SIGNATURE_ECDSA));
+        }
+    }
+
+    let Some(rpc_url) = config.rpc_url(network) else {
+        warn!(
+            "No RPC for network {}, EIP-1271 signature of {:?} not checked",
+            network, signer
+        );
+        return Ok(None);
+    };
+    let provider = http_provider(rpc_url)?;
+    if provider.get_code(signer, None).await?.is_empty() {
+        return Ok(None);
+    }
+    let Ok(bytes) = Bytes::from_str(signature) else {
+        return Ok(None);
+    };
+    match Erc1271::new(signer, provider)
+        .is_valid_signature(hash.into(), bytes)
+        .call()
+        .await
+    {
+        Ok(magic) => Ok((magic == EIP1271_MAGIC_VALUE).then_some(SIGNATURE_EIP1271)),
+        Err(err) if err.is_revert() => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Recomputes the EIP-712 hash of one of our signed votes and checks its signature.
+async fn verify_vote(
+    config: &Config,
+    vote: &UnverifiedVote,
+) -> Result<(bool, Value), Box<dyn Error + Send + Sync>> {
+    let hash = match vote_hash(&vote.signed_message) {
+        Ok(hash) => hash,
+        Err(err) => return Ok((false, json!({ "error": err.to_string() }))),
+    };
+    let Ok(signer) = vote.signer_address.parse::<Address>() else {
+        return Ok((false, json!({ "error": "unreadable signer address" })));
+    };
+    let network = vote.network.as_deref().unwrap_or("1");
+    let method = verify_signature(config, network, signer, hash, &vote.signature).await?;
+    Ok((
+        method.is_some(),
+        json!({
+            "hash": hex(hash.as_bytes()),
+            "signer": vote.signer_address,
+            "network": network,
+            "method": method,
+        }),
+    ))
+}
+
+/// Checks the signatures behind the data we store: proposals against the
+/// envelope their author signed, and our submitted votes against their
+/// signed message. Each row gets a `verified` flag.
+pub async fn run_verification(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    http: &HttpClient,
+) {
+    match get_unchecked_proposals(db_client, VERIFICATION_BATCH).await {
+        Ok(proposals) => {
+            info!("Verifying {} proposals", proposals.len());
+            for proposal in proposals {
+                if let Err(e) = verify_proposal(db_client, config, http, &proposal).await {
+                    warn!("Could not verify proposal {}: {}", proposal["id"], e);
+                }
+            }
+        }
+        Err(e) => error!("Error fetching proposals to verify: {}", e),
+    }
+
+    match get_unverified_votes(db_client, VERIFICATION_BATCH).await {
+        Ok(votes) => {
+            info!("Verifying {} votes", votes.len());
+            for vote in votes {
+                let (verified, details) = match verify_vote(config, &vote).await {
+                    Ok(result) => result,
+                    Err(e) => {
+                        warn!("Could not verify vote {}: {}", vote.id, e);
+                        continue;
+                    }
+                };
+                if !verified {
+                    error!("ALERT: signature of vote {} does not verify: {}", vote.id, details);
+                }
+                if let Err(e) = set_vote_verified(db_client, vote.id, verified, &details).await {
+                    error!("Error saving verification of vote {}: {}", vote.id, e);
+                }
+            }
+        }
+        Err(e) => error!("Error fetching votes to verify: {}", e),
+    }
+}
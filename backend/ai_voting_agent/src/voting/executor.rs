@@ -0,0 +1,301 @@
+use log::{error, info};
+use reqwest::Client as HttpClient;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{error::Error, sync::Arc};
+
+use crate::{
+    config::config::Config,
+    identity::identity::{IdentityRegistry, VotingIdentity},
+    store::store::Stores,
+    voting::{
+        repository::{
+            VoteAction, CHANNEL_SNAPSHOT, STATUS_AWAITING_SIGNATURES,
+            STATUS_FAILED, STATUS_REJECTED, STATUS_SIMULATED, STATUS_SUBMITTED,
+        },
+        rationale::{build_rationale, publish_vote_rationale},
+        safe::{prepare_safe_vote, simulate_safe_vote},
+        snapshot::{sign_vote, simulate_vote, submit_vote, SignedVote, VoteError, VoteReceipt},
+    },
+};
+
+/// Result of a vote execution, as returned by the API.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteExecution {
+    pub action_id: i64,
+    pub proposal_id: String,
+    pub identity: String,
+    pub choice: u32,
+    pub status: String,
+    pub receipt: Option<VoteReceipt>,
+    /// Validation evidence when the vote ran in dry-run mode.
+    pub simulation: Option<Value>,
+    pub error: Option<String>,
+}
+
+/// What to vote on and under which authorization.
+#[derive(Debug, Clone)]
+pub struct VoteOrder {
+    pub proposal_id: String,
+    /// 1-based choice; resolved from the latest recommendation when `None`.
+    pub choice: Option<u32>,
+    /// Approval trail that authorized this vote, stored as is.
+    pub approval: Value,
+    /// Audit entry this vote re-submits, if any.
+    pub resubmission_of: Option<i64>,
+    pub attempt: i32,
+    /// Earlier vote this one replaces after the recommendation changed.
+    pub replaces_action_id: Option<i64>,
+    /// Build, sign and validate the vote without sending it. `DRY_RUN`
+    /// forces this for every vote.
+    pub dry_run: bool,
+}
+
+impl VoteOrder {
+    pub fn new(proposal_id: &str, choice: Option<u32>, approval: Value) -> Self {
+        VoteOrder {
+            proposal_id: proposal_id.to_string(),
+            choice,
+            approval,
+            resubmission_of: None,
+            attempt: 1,
+            replaces_action_id: None,
+            dry_run: false,
+        }
+    }
+}
+
+/// Picks the proposal choice (1-based, as Snapshot expects) with the highest
+/// weight in a recommendation such as `{ "For": 0.8, "Against": 0.2 }`.
+pub fn resolve_choice(choices: &Value, recommendation: &Value) -> Option<u32> {
+    let choices = choices.as_array()?;
+    let (best, _) = recommendation
+        .as_object()?
+        .iter()
+        .filter_map(|(option, weight)| weight.as_f64().map(|w| (option, w)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    choices
+        .iter()
+        .position(|c| {
+            c.as_str()
+                .map(|c| c.trim().eq_ignore_ascii_case(best.trim()))
+                .unwrap_or(false)
+        })
+        .map(|idx| idx as u32 + 1)
+}
+
+/// Identities that vote on a proposal, according to its space.
+pub async fn identities_for_proposal(
+    stores: &Stores,
+    registry: &IdentityRegistry,
+    proposal_id: &str,
+) -> Result<Vec<Arc<VotingIdentity>>, Box<dyn Error + Send + Sync>> {
+    let proposal = stores.proposals.get(proposal_id).await?;
+    let space = proposal
+        .as_ref()
+        .and_then(|proposal| proposal.space_id())
+        .ok_or("Proposal not found")?;
+    Ok(registry.for_space(space))
+}
+
+/// Sets the status and hub response of an audit entry from a submission result
+/// and returns the receipt or the error message.
+pub fn apply_submission(
+    action: &mut VoteAction,
+    result: Result<VoteReceipt, VoteError>,
+) -> Result<(Option<VoteReceipt>, Option<String>), Box<dyn Error + Send + Sync>> {
+    match result {
+        Ok(receipt) => {
+            action.status = STATUS_SUBMITTED.to_string();
+            action.hub_response = Some(serde_json::to_value(&receipt)?);
+            Ok((Some(receipt), None))
+        }
+        Err(VoteError::Rejected { status, response }) => {
+            action.status = STATUS_REJECTED.to_string();
+            action.hub_response = Some(json!({ "status": status, "response": response }));
+            let err = VoteError::Rejected { status, response };
+            Ok((None, Some(err.to_string())))
+        }
+        Err(err) => {
+            action.status = STATUS_FAILED.to_string();
+            Ok((None, Some(err.to_string())))
+        }
+    }
+}
+
+/// Builds and signs a vote as `identity` and validates it without sending.
+async fn simulate_snapshot_vote(
+    config: &Config,
+    identity: &VotingIdentity,
+    space: &str,
+    proposal_id: &str,
+    choice: u32,
+    reason: &str,
+) -> Result<(SignedVote, Value), VoteError> {
+    match identity.safe {
+        None => {
+            let signed_vote = sign_vote(&identity.signer, space, proposal_id, choice, reason).await?;
+            let simulation = simulate_vote(&signed_vote, identity.address)?;
+            Ok((signed_vote, simulation))
+        }
+        Some(_) => simulate_safe_vote(config, identity, space, proposal_id, choice, reason).await,
+    }
+}
+
+/// Signs and submits a vote for a proposal as one identity and records the
+/// attempt in `vote_actions`.
+///
+/// Safe identities vote as the Safe; when its signatures are not complete yet
+/// the entry is recorded as `awaiting_signatures` and submitted later by
+/// `safe::run_safe_signature_collection`. In dry-run mode the vote is signed
+/// and validated, then recorded as `simulated` instead of being sent.
+///
+/// The vote's `reason` comes from the rationale built from the recommendation;
+/// with `PUBLISH_RATIONALE` the rationale is also posted to the forum once
+/// the hub accepts the vote.
+pub async fn execute_vote(
+    stores: &Stores,
+    config: &Config,
+    http: &HttpClient,
+    identity: &VotingIdentity,
+    order: VoteOrder,
+) -> Result<VoteExecution, Box<dyn Error + Send + Sync>> {
+    let proposal_id = &order.proposal_id;
+    let proposal = stores
+        .proposals
+        .get(proposal_id)
+        .await?
+        .ok_or("Proposal not found")?
+        .to_json();
+    let proposal = &proposal;
+    let space = proposal["space"]["id"]
+        .as_str()
+        .ok_or("Proposal has no space")?
+        .to_string();
+
+    let recommendation = stores.recommendations.latest_valid(proposal_id).await?;
+    let recommendation_id = recommendation.as_ref().map(|rec| rec.id);
+    let choice = match order.choice {
+        Some(choice) => choice,
+        None => recommendation
+            .as_ref()
+            .and_then(|rec| resolve_choice(&proposal["choices"], &rec.recommendation))
+            .ok_or("No choice given and none could be resolved from the recommendation")?,
+    };
+
+    let rationale = recommendation
+        .as_ref()
+        .map(|rec| build_rationale(proposal, &rec.analysis(), choice));
+    let reason = rationale.as_ref().map(|r| r.reason.clone()).unwrap_or_default();
+
+    let mut action = VoteAction {
+        proposal_id: proposal_id.clone(),
+        identity: identity.name.clone(),
+        recommendation_id,
+        space: space.clone(),
+        choice,
+        signer_address: None,
+        signed_message: None,
+        signature: None,
+        hub_response: None,
+        status: STATUS_FAILED.to_string(),
+        approval: order.approval,
+        attempt: order.attempt,
+        resubmission_of: order.resubmission_of,
+        replaces_action_id: order.replaces_action_id,
+        safe_message_hash: None,
+        channel: CHANNEL_SNAPSHOT.to_string(),
+        simulation: None,
+        rationale: rationale.map(|r| r.post),
+    };
+
+    if config.dry_run || order.dry_run {
+        let (receipt, error) =
+            match simulate_snapshot_vote(config, identity, &space, proposal_id, choice, &reason).await {
+                Ok((signed_vote, simulation)) => {
+                    action.signer_address = Some(signed_vote.address.clone());
+                    action.signed_message = Some(signed_vote.typed_data.clone());
+                    action.signature =
+                        Some(signed_vote.signature.clone()).filter(|sig| !sig.is_empty());
+                    action.safe_message_hash = simulation["safeMessageHash"].as_str().map(String::from);
+                    action.status = STATUS_SIMULATED.to_string();
+                    action.simulation = Some(simulation);
+                    (None, None)
+                }
+                Err(err) => apply_submission(&mut action, Err(err))?,
+            };
+        return record_vote(stores, identity, action, receipt, error).await;
+    }
+
+    let signed_vote = match identity.safe {
+        None => sign_vote(&identity.signer, &space, proposal_id, choice, &reason).await,
+        Some(_) => match prepare_safe_vote(config, http, identity, &space, proposal_id, choice, &reason).await {
+            Ok(safe_vote) => {
+                action.safe_message_hash = Some(format!("{:?}", safe_vote.safe_message_hash));
+                if !safe_vote.ready {
+                    action.signer_address = Some(safe_vote.signed.address.clone());
+                    action.signed_message = Some(safe_vote.signed.typed_data.clone());
+                    action.status = STATUS_AWAITING_SIGNATURES.to_string();
+                    info!(
+                        "Safe vote on proposal {} is waiting: {}",
+                        proposal_id, safe_vote.progress
+                    );
+                }
+                Ok(safe_vote.signed)
+            }
+            Err(err) => Err(err),
+        },
+    };
+    let (receipt, error) = match signed_vote {
+        Ok(_) if action.status == STATUS_AWAITING_SIGNATURES => (None, None),
+        Ok(signed_vote) => {
+            action.signer_address = Some(signed_vote.address.clone());
+            action.signed_message = Some(signed_vote.typed_data.clone());
+            action.signature = Some(signed_vote.signature.clone());
+            apply_submission(&mut action, submit_vote(http, &config.hub_message_url(), &signed_vote).await)?
+        }
+        Err(err) => apply_submission(&mut action, Err(err))?,
+    };
+    let execution = record_vote(stores, identity, action.clone(), receipt, error).await?;
+    if config.publish_rationale && execution.status == STATUS_SUBMITTED {
+        if let Err(e) = publish_vote_rationale(stores, config, http, execution.action_id, &action).await {
+            error!("Error publishing rationale of vote {}: {}", execution.action_id, e);
+        }
+    }
+    Ok(execution)
+}
+
+/// Writes the audit entry of a vote attempt and returns its summary.
+pub async fn record_vote(
+    stores: &Stores,
+    identity: &VotingIdentity,
+    action: VoteAction,
+    receipt: Option<VoteReceipt>,
+    error: Option<String>,
+) -> Result<VoteExecution, Box<dyn Error + Send + Sync>> {
+    let proposal_id = &action.proposal_id;
+    let action_id = stores.votes.save_action(&action).await?;
+    match &error {
+        None => info!(
+            "Vote {} recorded for proposal {} as {}",
+            action_id, proposal_id, identity.name
+        ),
+        Some(err) => error!(
+            "Vote {} for proposal {} as {} failed: {}",
+            action_id, proposal_id, identity.name, err
+        ),
+    }
+
+    Ok(VoteExecution {
+        action_id,
+        proposal_id: proposal_id.clone(),
+        identity: identity.name.clone(),
+        choice: action.choice,
+        status: action.status,
+        receipt,
+        simulation: action.simulation,
+        error,
+    })
+}
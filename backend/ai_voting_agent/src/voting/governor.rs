@@ -0,0 +1,131 @@
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use serde_json::{json, Value};
+use std::{error::Error, str::FromStr};
+
+use crate::{
+    config::config::Config,
+    contracts::contracts::{http_provider, prepare_transaction, Governor, HttpProvider},
+    identity::identity::VotingIdentity,
+    signer::signer::sign_transaction,
+    store::store::Stores,
+    voting::{
+        executor::{record_vote, VoteExecution},
+        repository::{
+            VoteAction, CHANNEL_GOVERNOR, STATUS_FAILED, STATUS_SIMULATED, STATUS_SUBMITTED,
+        },
+        snapshot::hex,
+    },
+};
+
+/// An on-chain vote on a Governor proposal.
+#[derive(Debug, Clone)]
+pub struct GovernorVoteOrder {
+    pub governor: String,
+    /// Chain id the governor is deployed on, see `IdentityRegistry::governor_network`.
+    pub network: String,
+    pub proposal_id: U256,
+    /// 0 = against, 1 = for, 2 = abstain.
+    pub support: u8,
+    pub reason: String,
+    pub approval: Value,
+    pub dry_run: bool,
+}
+
+/// Builds the `castVoteWithReason` transaction from `identity`, with nonce,
+/// gas and fees filled in by the node.
+async fn build_vote_transaction(
+    provider: &HttpProvider,
+    identity: &VotingIdentity,
+    order: &GovernorVoteOrder,
+) -> Result<TypedTransaction, Box<dyn Error + Send + Sync>> {
+    let governor = Governor::new(Address::from_str(&order.governor)?, provider.clone());
+    let mut tx = governor
+        .cast_vote_with_reason(order.proposal_id, order.support, order.reason.clone())
+        .from(identity.address)
+        .tx;
+    prepare_transaction(provider, &mut tx).await?;
+    Ok(tx)
+}
+
+/// Signs and sends (or, in dry-run mode, simulates with `eth_call`) a vote on
+/// a Governor proposal and records it in `vote_actions`.
+pub async fn cast_governor_vote(
+    stores: &Stores,
+    config: &Config,
+    identity: &VotingIdentity,
+    order: GovernorVoteOrder,
+) -> Result<VoteExecution, Box<dyn Error + Send + Sync>> {
+    if identity.safe.is_some() {
+        return Err(format!("Safe identity {} cannot cast Governor votes", identity.name).into());
+    }
+    let rpc_url = config
+        .rpc_url(&order.network)
+        .ok_or_else(|| format!("No RPC URL configured for chain {}", order.network))?;
+    let provider = http_provider(rpc_url)?;
+    let dry_run = config.dry_run || order.dry_run;
+
+    let mut action = VoteAction {
+        proposal_id: order.proposal_id.to_string(),
+        identity: identity.name.clone(),
+        recommendation_id: None,
+        space: ethers::utils::to_checksum(&Address::from_str(&order.governor)?, None),
+        choice: order.support as u32,
+        signer_address: Some(identity.checksum_address()),
+        signed_message: None,
+        signature: None,
+        hub_response: None,
+        status: STATUS_FAILED.to_string(),
+        approval: order.approval.clone(),
+        attempt: 1,
+        resubmission_of: None,
+        replaces_action_id: None,
+        safe_message_hash: None,
+        channel: CHANNEL_GOVERNOR.to_string(),
+        simulation: None,
+        rationale: None,
+    };
+
+    let signed = match build_vote_transaction(&provider, identity, &order).await {
+        Ok(tx) => {
+            action.signed_message = Some(serde_json::to_value(&tx)?);
+            sign_transaction(&identity.signer, &tx)
+                .await
+                .map(|raw| (tx, raw))
+                .map_err(|err| err.to_string())
+        }
+        Err(err) => Err(err.to_string()),
+    };
+
+    let error = match signed {
+        Ok((tx, raw)) => {
+            action.signature = Some(hex(&raw));
+            let tx_hash = H256::from(ethers::utils::keccak256(&raw));
+            if dry_run {
+                match provider.call(&tx, None).await {
+                    Ok(output) => {
+                        action.status = STATUS_SIMULATED.to_string();
+                        action.simulation = Some(json!({
+                            "txHash": format!("{:?}", tx_hash),
+                            "returnData": hex(&output),
+                        }));
+                        None
+                    }
+                    Err(err) => Some(format!("Simulation reverted: {}", err)),
+                }
+            } else {
+                match provider.send_raw_transaction(raw).await {
+                    Ok(pending) => {
+                        action.status = STATUS_SUBMITTED.to_string();
+                        action.hub_response = Some(json!({ "txHash": format!("{:?}", pending.tx_hash()) }));
+                        None
+                    }
+                    Err(err) => Some(err.to_string()),
+                }
+            }
+        }
+        Err(err) => Some(err),
+    };
+
+    record_vote(stores, identity, action, None, error).await
+}
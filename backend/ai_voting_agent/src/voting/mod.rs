@@ -1 +1,9 @@
-pub mod snapshot;
\ No newline at end of file
+pub mod snapshot;
+pub mod repository;
+pub mod executor;
+pub mod reconciler;
+pub mod schedule;
+pub mod safe;
+pub mod governor;
+pub mod rationale;
+pub mod store;
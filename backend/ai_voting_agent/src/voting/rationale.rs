@@ -0,0 +1,247 @@
+use log::{info, warn};
+use reqwest::Client as HttpClient;
+use serde_json::{json, Value};
+use std::error::Error;
+
+use crate::{
+    config::config::Config,
+    forum::discourse::{DiscourseClient, ForumClient},
+    store::store::Stores,
+    voting::repository::VoteAction,
+};
+
+/// Longest `reason` we put in a Snapshot vote.
+pub const REASON_LIMIT: usize = 140;
+
+/// Why we voted the way we did, built from a stored recommendation.
+#[derive(Debug, Clone)]
+pub struct Rationale {
+    /// Short text for the vote's `reason` field.
+    pub reason: String,
+    /// Markdown post for the proposal's forum thread.
+    pub post: String,
+}
+
+/// Text of an analysis field, which the LLM returns as a string or a list.
+fn field_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+        Value::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::String(text) => Some(text.trim().to_string()),
+                    Value::Null => None,
+                    other => Some(other.to_string()),
+                })
+                .filter(|text| !text.is_empty())
+                .map(|text| format!("- {}", text))
+                .collect();
+            (!items.is_empty()).then(|| items.join("\n"))
+        }
+        Value::Null => None,
+        other => Some(other.to_string()),
+    }
+}
+
+fn first_item(value: &Value) -> Option<String> {
+    match value {
+        Value::Array(items) => items.first().and_then(|item| item.as_str()).map(str::to_string),
+        Value::String(text) => text.split(". ").next().map(str::to_string),
+        _ => None,
+    }
+    .map(|text| text.trim().trim_end_matches('.').to_string())
+    .filter(|text| !text.is_empty())
+}
+
+fn truncate(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(limit - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Builds the rationale for voting `choice` (1-based) on `proposal` from the
+/// analysis of its recommendation.
+pub fn build_rationale(proposal: &Value, analysis: &Value, choice: u32) -> Rationale {
+    let label = proposal["choices"]
+        .as_array()
+        .and_then(|choices| choices.get((choice as usize).checked_sub(1)?))
+        .and_then(|label| label.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("choice {}", choice));
+
+    let mut reason = label.clone();
+    if let Some(advantage) = first_item(&analysis["advantages"]) {
+        reason.push_str(&format!(": {}", advantage));
+    }
+    if let Some(risk) = first_item(&analysis["risks"]) {
+        reason.push_str(&format!(". Main risk: {}", risk));
+    }
+
+    let mut post = format!(
+        "**We voted {}** on [{}](https://snapshot.org/#/{}/proposal/{}).\n",
+        label,
+        proposal["title"].as_str().unwrap_or_default(),
+        proposal["space"]["id"].as_str().unwrap_or_default(),
+        proposal["id"].as_str().unwrap_or_default()
+    );
+    for (title, key) in [
+        ("Technical impact", "technicalImpact"),
+        ("Economic consequences", "economicConsequences"),
+        ("Governance and decentralization", "governanceAndDecentralization"),
+        ("Advantages", "advantages"),
+        ("Risks", "risks"),
+    ] {
+        if let Some(text) = field_text(&analysis[key]) {
+            post.push_str(&format!("\n### {}\n\n{}\n", title, text));
+        }
+    }
+
+    Rationale {
+        reason: truncate(&reason, REASON_LIMIT),
+        post,
+    }
+}
+
+/// Replies with the rationale of a submitted vote in its proposal's
+/// `discussion` thread and records the outcome on the vote.
+///
+/// Votes without a rationale, or whose proposal links to another forum, are
+/// skipped.
+pub async fn publish_vote_rationale(
+    stores: &Stores,
+    config: &Config,
+    http: &HttpClient,
+    action_id: i64,
+    action: &VoteAction,
+) -> Result<Option<Value>, Box<dyn Error + Send + Sync>> {
+    let Some(rationale) = &action.rationale else {
+        return Ok(None);
+    };
+    let forum = DiscourseClient::from_config(config, http).ok_or("FORUM_URL is not set")?;
+    let discussion = stores
+        .proposals
+        .get(&action.proposal_id)
+        .await?
+        .and_then(|proposal| proposal.discussion)
+        .unwrap_or_default();
+    let Some(outcome) = post_rationale(&forum, &discussion, action_id, rationale).await else {
+        info!(
+            "Proposal {} has no thread on the forum ({:?}), rationale of vote {} not published",
+            action.proposal_id, discussion, action_id
+        );
+        return Ok(None);
+    };
+    stores.votes.save_rationale_post(action_id, &outcome).await?;
+    Ok(Some(outcome))
+}
+
+/// Replies with a rationale in the thread `discussion` links to and returns
+/// the outcome stored on the vote; `None` when the link is not on `forum`.
+/// A rejected post is reported in the outcome rather than as an error.
+pub async fn post_rationale(
+    forum: &dyn ForumClient,
+    discussion: &str,
+    action_id: i64,
+    rationale: &str,
+) -> Option<Value> {
+    let topic_id = forum.topic_id(discussion)?;
+    let outcome = match forum.create_post(topic_id, rationale).await {
+        Ok(post) => json!({
+            "topicId": topic_id,
+            "postId": post["id"],
+            "url": forum.post_url(&post),
+            "publishedAt": chrono::Utc::now().timestamp(),
+        }),
+        Err(err) => {
+            warn!("Rationale of vote {} was not published: {}", action_id, err);
+            json!({ "topicId": topic_id, "error": err.to_string() })
+        }
+    };
+    Some(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forum::stub::StubForum;
+
+    fn proposal() -> Value {
+        json!({
+            "id": "0xabc",
+            "title": "Fund the security council",
+            "space": { "id": "arbitrumfoundation.eth" },
+            "choices": ["For", "Against", "Abstain"],
+        })
+    }
+
+    fn analysis() -> Value {
+        json!({
+            "technicalImpact": "Adds a second signer set.",
+            "economicConsequences": null,
+            "governanceAndDecentralization": "",
+            "advantages": ["Faster incident response.", "Clear accountability"],
+            "risks": "Key holder collusion. Higher operating costs.",
+        })
+    }
+
+    #[test]
+    fn rationale_for_a_fixed_recommendation() {
+        let rationale = build_rationale(&proposal(), &analysis(), 1);
+        assert_eq!(
+            rationale.reason,
+            "For: Faster incident response. Main risk: Key holder collusion"
+        );
+        assert_eq!(
+            rationale.post,
+            "**We voted For** on [Fund the security council]\
+             (https://snapshot.org/#/arbitrumfoundation.eth/proposal/0xabc).\n\
+             \n### Technical impact\n\nAdds a second signer set.\n\
+             \n### Advantages\n\n- Faster incident response.\n- Clear accountability\n\
+             \n### Risks\n\nKey holder collusion. Higher operating costs.\n"
+        );
+    }
+
+    #[test]
+    fn reason_is_truncated_and_unknown_choices_are_numbered() {
+        let analysis = json!({ "advantages": ["x".repeat(300)] });
+        let rationale = build_rationale(&proposal(), &analysis, 7);
+        assert!(rationale.reason.starts_with("choice 7: xxx"));
+        assert_eq!(rationale.reason.chars().count(), REASON_LIMIT);
+        assert!(rationale.reason.ends_with('…'));
+        assert!(rationale.post.starts_with("**We voted choice 7**"));
+    }
+
+    #[tokio::test]
+    async fn rationale_is_posted_in_the_discussion_thread() {
+        let forum = StubForum::default();
+        let outcome = post_rationale(&forum, "https://forum.test/t/42", 7, "We voted For")
+            .await
+            .unwrap();
+        assert_eq!(*forum.created.lock().unwrap(), vec![(42, "We voted For".to_string())]);
+        assert_eq!(outcome["topicId"], 42);
+        assert_eq!(outcome["postId"], 99);
+        assert_eq!(outcome["url"], "https://forum.test/t/topic/42/2");
+    }
+
+    #[tokio::test]
+    async fn rejected_posts_are_reported_in_the_outcome() {
+        let forum = StubForum { reject_posts: true, ..Default::default() };
+        let outcome = post_rationale(&forum, "https://forum.test/t/42", 7, "We voted For")
+            .await
+            .unwrap();
+        assert_eq!(outcome["topicId"], 42);
+        assert_eq!(outcome["error"], "Forum error: post rejected (403)");
+    }
+
+    #[tokio::test]
+    async fn threads_on_other_forums_are_skipped() {
+        let forum = StubForum::default();
+        let outcome = post_rationale(&forum, "https://elsewhere.test/t/42", 7, "We voted For").await;
+        assert!(outcome.is_none());
+        assert!(forum.created.lock().unwrap().is_empty());
+    }
+}
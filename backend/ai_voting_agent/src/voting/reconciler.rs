@@ -0,0 +1,329 @@
+use log::{error, info, warn};
+use reqwest::Client as HttpClient;
+use serde_json::{json, Value};
+use std::error::Error;
+
+use crate::{
+    config::config::Config,
+    ipfs::ipfs::fetch_envelope,
+    proposal_snapchot::hub::HubClient,
+    store::store::Stores,
+    identity::identity::IdentityRegistry,
+    voting::{
+        executor::{execute_vote, VoteOrder},
+        repository::{
+            SubmittedVote, RECONCILE_CONFIRMED, RECONCILE_ABANDONED, RECONCILE_MISMATCHED,
+            RECONCILE_MISSING,
+        },
+    },
+};
+
+/// Seconds the hub gets to index a vote before it is considered missing.
+const RECONCILE_GRACE_SECS: i64 = 120;
+
+/// Attempts (the original vote included) before we stop re-submitting and alert.
+const MAX_VOTE_ATTEMPTS: i32 = 3;
+
+const VOTES_QUERY: &str = r#"
+query Votes($voter: String!, $proposal: String!) {
+  votes(
+    first: 10
+    where: { voter: $voter, proposal: $proposal }
+    orderBy: "created"
+    orderDirection: desc
+  ) {
+    id
+    ipfs
+    voter
+    choice
+    created
+  }
+}
+"#;
+
+/// Fetches the votes recorded by the hub for `voter` on `proposal`, newest first.
+pub async fn get_hub_votes(
+    hub: &HubClient,
+    voter: &str,
+    proposal: &str,
+) -> Result<Vec<Value>, Box<dyn Error + Send + Sync>> {
+    let data = hub
+        .query(VOTES_QUERY, json!({ "voter": voter, "proposal": proposal }))
+        .await?;
+    let votes = data
+        .get("votes")
+        .and_then(|votes| votes.as_array())
+        .ok_or("No votes array in votes response")?;
+
+    Ok(votes.clone())
+}
+
+/// Why the IPFS receipt pinned for a vote does not hold our signature and
+/// choice, if it does not.
+fn receipt_mismatch(envelope: &Value, cid: &str, vote: &SubmittedVote) -> Option<String> {
+    let signature = envelope.get("sig").and_then(|sig| sig.as_str());
+    if signature.map(str::to_lowercase) != vote.signature.as_ref().map(|sig| sig.to_lowercase()) {
+        return Some(format!("IPFS receipt {} carries a different signature", cid));
+    }
+    let choice = envelope["data"]["message"]["choice"].as_u64();
+    if choice != Some(vote.choice as u64) {
+        return Some(format!("IPFS receipt {} carries choice {:?}", cid, choice));
+    }
+    None
+}
+
+/// Compares one submitted vote with what the hub recorded and returns the
+/// reconciliation status together with the evidence for it.
+async fn check_vote(
+    config: &Config,
+    hub: &HubClient,
+    http: &HttpClient,
+    vote: &SubmittedVote,
+) -> Result<(&'static str, Value), Box<dyn Error + Send + Sync>> {
+    let hub_votes = get_hub_votes(hub, &vote.signer_address, &vote.proposal_id).await?;
+    check_hub_votes(config, http, vote, &hub_votes).await
+}
+
+/// Checks a vote against the votes the hub returned for its signer, newest
+/// first. Failing to fetch the IPFS receipt is an error, not a mismatch, so
+/// the vote keeps its status and is checked again on the next run.
+async fn check_hub_votes(
+    config: &Config,
+    http: &HttpClient,
+    vote: &SubmittedVote,
+    hub_votes: &[Value],
+) -> Result<(&'static str, Value), Box<dyn Error + Send + Sync>> {
+    let Some(hub_vote) = hub_votes.first() else {
+        return Ok((RECONCILE_MISSING, json!({ "hubVotes": [] })));
+    };
+
+    let hub_choice = hub_vote.get("choice").and_then(|c| c.as_u64());
+    if hub_choice != Some(vote.choice as u64) {
+        return Ok((
+            RECONCILE_MISMATCHED,
+            json!({ "hubVote": hub_vote, "reason": "hub recorded a different choice" }),
+        ));
+    }
+
+    // The receipt we got at submission must be the one the hub now serves.
+    let receipt_ipfs = vote
+        .hub_response
+        .as_ref()
+        .and_then(|r| r.get("ipfs"))
+        .and_then(|ipfs| ipfs.as_str());
+    let hub_ipfs = hub_vote.get("ipfs").and_then(|ipfs| ipfs.as_str());
+    let Some(cid) = hub_ipfs else {
+        return Ok((
+            RECONCILE_MISMATCHED,
+            json!({ "hubVote": hub_vote, "reason": "hub vote has no IPFS receipt" }),
+        ));
+    };
+    if receipt_ipfs.is_some() && receipt_ipfs != hub_ipfs {
+        return Ok((
+            RECONCILE_MISMATCHED,
+            json!({ "hubVote": hub_vote, "reason": "IPFS receipt differs from submission" }),
+        ));
+    }
+    let envelope = fetch_envelope(config, http, cid).await?;
+    if let Some(reason) = receipt_mismatch(&envelope, cid, vote) {
+        return Ok((RECONCILE_MISMATCHED, json!({ "hubVote": hub_vote, "reason": reason })));
+    }
+
+    Ok((RECONCILE_CONFIRMED, json!({ "hubVote": hub_vote })))
+}
+
+/// Re-submits a vote that did not land. When that is no longer possible the
+/// vote is marked abandoned, so it is alerted on once and not checked again.
+async fn resubmit_or_alert(
+    stores: &Stores,
+    config: &Config,
+    registry: &IdentityRegistry,
+    http: &HttpClient,
+    vote: &SubmittedVote,
+    status: &str,
+    details: &Value,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let is_active = stores
+        .proposals
+        .get(&vote.proposal_id)
+        .await?
+        .is_some_and(|p| {
+            p.state.as_deref() == Some("active")
+                && p.end.unwrap_or(0) > chrono::Utc::now().timestamp()
+        });
+
+    if !is_active || vote.attempt >= MAX_VOTE_ATTEMPTS {
+        error!(
+            "ALERT: vote {} on proposal {} is {} after {} attempt(s) and will not be re-submitted",
+            vote.id, vote.proposal_id, status, vote.attempt
+        );
+        let reason = if is_active { "attempts exhausted" } else { "proposal closed" };
+        let details = json!({ "lastStatus": status, "reason": reason, "evidence": details });
+        stores.votes.set_reconcile_status(vote.id, RECONCILE_ABANDONED, &details).await?;
+        return Ok(());
+    }
+
+    let identity = registry
+        .get(&vote.identity)
+        .ok_or_else(|| format!("Unknown identity {}", vote.identity))?;
+    let mut approval = vote.approval.clone();
+    if let Some(trail) = approval.as_array_mut() {
+        trail.push(json!({
+            "approvedBy": "reconciler",
+            "approvedAt": chrono::Utc::now().timestamp(),
+            "comment": format!("re-submission of vote {} ({})", vote.id, status),
+        }));
+    }
+    let order = VoteOrder {
+        proposal_id: vote.proposal_id.clone(),
+        choice: Some(vote.choice),
+        approval,
+        resubmission_of: Some(vote.id),
+        attempt: vote.attempt + 1,
+        replaces_action_id: None,
+        dry_run: false,
+    };
+    let execution = execute_vote(stores, config, http, &identity, order).await?;
+    warn!(
+        "Vote {} on proposal {} was {}; re-submitted as vote {} ({})",
+        vote.id, vote.proposal_id, status, execution.action_id, execution.status
+    );
+    Ok(())
+}
+
+/// Confirms that submitted votes landed on the hub, marks each audit entry as
+/// confirmed, missing or mismatched, and re-submits the ones that did not land
+/// or abandons them when they cannot be.
+pub async fn run_reconciler(
+    stores: &Stores,
+    config: &Config,
+    registry: &IdentityRegistry,
+    hub: &HubClient,
+    http: &HttpClient,
+) {
+    let votes = match stores.votes.to_reconcile(RECONCILE_GRACE_SECS).await {
+        Ok(votes) => votes,
+        Err(e) => {
+            error!("Error fetching votes to reconcile: {}", e);
+            return;
+        }
+    };
+    info!("Reconciling {} submitted votes", votes.len());
+
+    for vote in votes {
+        let (status, details) = match check_vote(config, hub, http, &vote).await {
+            Ok(result) => result,
+            Err(e) => {
+                error!("Error reconciling vote {}: {}", vote.id, e);
+                continue;
+            }
+        };
+        if let Err(e) = stores.votes.set_reconcile_status(vote.id, status, &details).await {
+            error!("Error saving reconciliation of vote {}: {}", vote.id, e);
+            continue;
+        }
+        if status == RECONCILE_CONFIRMED {
+            info!("Vote {} on proposal {} confirmed", vote.id, vote.proposal_id);
+            continue;
+        }
+        // A vote seen as missing once may still be indexing; give it one more round.
+        if status == RECONCILE_MISSING && vote.reconcile_status.is_none() {
+            warn!("Vote {} on proposal {} not found on the hub yet", vote.id, vote.proposal_id);
+            continue;
+        }
+        if let Err(e) = resubmit_or_alert(stores, config, registry, http, &vote, status, &details).await {
+            error!("Error re-submitting vote {}: {}", vote.id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_client::{http_client::build_http_client, stub::StubServer};
+
+    const CID: &str = "bafkreireceipt";
+
+    fn vote() -> SubmittedVote {
+        SubmittedVote {
+            id: 7,
+            proposal_id: "0xproposal".to_string(),
+            identity: "default".to_string(),
+            choice: 1,
+            signer_address: "0xvoter".to_string(),
+            signature: Some("0xABCD".to_string()),
+            hub_response: Some(json!({ "id": "0xreceipt", "ipfs": CID })),
+            approval: json!([]),
+            attempt: 1,
+            reconcile_status: None,
+        }
+    }
+
+    fn hub_votes() -> Vec<Value> {
+        vec![json!({ "id": "0xreceipt", "ipfs": CID, "voter": "0xvoter", "choice": 1 })]
+    }
+
+    /// A config whose IPFS gateway answers every request with `status` and `body`.
+    async fn gateway(status: u16, body: Value) -> Config {
+        let server = StubServer::start(move |_, _, _| (status, body.to_string())).await;
+        Config {
+            ipfs_gateway_url: format!("{}/ipfs", server.url),
+            ..Config::for_tests()
+        }
+    }
+
+    fn envelope(sig: &str, choice: u64) -> Value {
+        json!({ "sig": sig, "data": { "message": { "choice": choice } } })
+    }
+
+    #[tokio::test]
+    async fn matching_receipt_confirms_the_vote() {
+        let config = gateway(200, envelope("0xabcd", 1)).await;
+        let http = build_http_client().unwrap();
+        let (status, _) = check_hub_votes(&config, &http, &vote(), &hub_votes()).await.unwrap();
+        assert_eq!(status, RECONCILE_CONFIRMED);
+    }
+
+    #[tokio::test]
+    async fn receipt_with_other_content_is_a_mismatch() {
+        let http = build_http_client().unwrap();
+        for receipt in [envelope("0xabcd", 2), envelope("0xother", 1)] {
+            let config = gateway(200, receipt).await;
+            let (status, details) = check_hub_votes(&config, &http, &vote(), &hub_votes()).await.unwrap();
+            assert_eq!(status, RECONCILE_MISMATCHED);
+            assert!(details["reason"].as_str().unwrap().contains(CID));
+        }
+    }
+
+    #[tokio::test]
+    async fn gateway_failure_leaves_the_vote_for_the_next_run() {
+        let http = build_http_client().unwrap();
+        let config = gateway(502, json!({ "error": "bad gateway" })).await;
+        assert!(check_hub_votes(&config, &http, &vote(), &hub_votes()).await.is_err());
+
+        // Nothing listens on port 1: the gateway cannot be reached at all.
+        let config = Config {
+            ipfs_gateway_url: "http://127.0.0.1:1/ipfs".to_string(),
+            ..Config::for_tests()
+        };
+        assert!(check_hub_votes(&config, &http, &vote(), &hub_votes()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn hub_disagreements_are_mismatches_without_fetching() {
+        // The gateway is unreachable, so any fetch would fail the check.
+        let config = Config {
+            ipfs_gateway_url: "http://127.0.0.1:1/ipfs".to_string(),
+            ..Config::for_tests()
+        };
+        let http = build_http_client().unwrap();
+        let other_choice = vec![json!({ "ipfs": CID, "choice": 2 })];
+        let other_receipt = vec![json!({ "ipfs": "bafkreiother", "choice": 1 })];
+        for hub_votes in [other_choice, other_receipt] {
+            let (status, _) = check_hub_votes(&config, &http, &vote(), &hub_votes).await.unwrap();
+            assert_eq!(status, RECONCILE_MISMATCHED);
+        }
+        let (status, _) = check_hub_votes(&config, &http, &vote(), &[]).await.unwrap();
+        assert_eq!(status, RECONCILE_MISSING);
+    }
+}
@@ -0,0 +1,717 @@
+use async_trait::async_trait;
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use serde_json::{json, Value};
+use std::{error::Error, sync::Arc};
+use tokio_postgres::{types::Json, NoTls, Row};
+
+use crate::voting::{
+    schedule::{is_vote_due, vote_due_at},
+    store::VoteStore,
+};
+
+/// The hub accepted the vote.
+pub const STATUS_SUBMITTED: &str = "submitted";
+/// The hub answered with an error.
+pub const STATUS_REJECTED: &str = "rejected";
+/// The vote never reached the hub (signing or network failure).
+pub const STATUS_FAILED: &str = "failed";
+/// A vote from the Safe waiting for owner signatures or on-chain approval.
+pub const STATUS_AWAITING_SIGNATURES: &str = "awaiting_signatures";
+/// A dry-run vote: built, signed and validated, but never sent.
+pub const STATUS_SIMULATED: &str = "simulated";
+
+/// A Snapshot vote sent to the hub.
+pub const CHANNEL_SNAPSHOT: &str = "snapshot";
+/// An on-chain `castVoteWithReason` on a Governor contract.
+pub const CHANNEL_GOVERNOR: &str = "governor";
+
+/// An approved vote waiting for its voting window.
+pub const EXECUTION_PENDING: &str = "pending";
+/// The scheduled vote was accepted by the hub, or handed to the Safe owners
+/// for signatures.
+pub const EXECUTION_EXECUTED: &str = "executed";
+/// The scheduled vote was attempted and failed.
+pub const EXECUTION_FAILED: &str = "failed";
+/// The scheduled vote ran in dry-run mode.
+pub const EXECUTION_SIMULATED: &str = "simulated";
+/// The proposal closed before the scheduled vote ran.
+pub const EXECUTION_EXPIRED: &str = "expired";
+/// The recommendation behind the scheduled vote was invalidated.
+pub const EXECUTION_CANCELLED: &str = "cancelled";
+
+/// The hub has the vote with the choice we sent.
+pub const RECONCILE_CONFIRMED: &str = "confirmed";
+/// The hub has no vote from us on the proposal.
+pub const RECONCILE_MISSING: &str = "missing";
+/// The hub recorded a different choice, or the IPFS receipt does not match.
+pub const RECONCILE_MISMATCHED: &str = "mismatched";
+/// Did not land and can no longer be re-submitted; reconciliation stops.
+pub const RECONCILE_ABANDONED: &str = "abandoned";
+
+/// One attempt to cast a vote, as written to the audit log.
+#[derive(Debug, Clone)]
+pub struct VoteAction {
+    pub proposal_id: String,
+    /// Name of the voting identity, see `identity::identity`.
+    pub identity: String,
+    pub recommendation_id: Option<i64>,
+    pub space: String,
+    pub choice: u32,
+    pub signer_address: Option<String>,
+    pub signed_message: Option<Value>,
+    pub signature: Option<String>,
+    pub hub_response: Option<Value>,
+    pub status: String,
+    pub approval: Value,
+    pub attempt: i32,
+    pub resubmission_of: Option<i64>,
+    /// Earlier vote this one changes after a re-analysis.
+    pub replaces_action_id: Option<i64>,
+    /// Safe message hash of a vote cast as the Safe.
+    pub safe_message_hash: Option<String>,
+    /// `snapshot` or `governor`.
+    pub channel: String,
+    /// Validation evidence of a dry-run vote.
+    pub simulation: Option<Value>,
+    /// Forum post explaining the vote, built from the recommendation.
+    pub rationale: Option<String>,
+}
+
+/// A Safe vote waiting for signatures.
+#[derive(Debug, Clone)]
+pub struct AwaitingVote {
+    pub id: i64,
+    pub action: VoteAction,
+    pub proposal_end: i64,
+}
+
+/// A submitted vote awaiting confirmation from the hub.
+#[derive(Debug, Clone)]
+pub struct SubmittedVote {
+    pub id: i64,
+    pub proposal_id: String,
+    pub identity: String,
+    pub choice: u32,
+    pub signer_address: String,
+    pub signature: Option<String>,
+    pub hub_response: Option<Value>,
+    pub approval: Value,
+    pub attempt: i32,
+    pub reconcile_status: Option<String>,
+}
+
+/// A signed Snapshot vote whose signature has not been verified yet.
+#[derive(Debug, Clone)]
+pub struct UnverifiedVote {
+    pub id: i64,
+    pub signer_address: String,
+    pub signed_message: Value,
+    pub signature: String,
+    /// Network of the proposal's space, where contract signers are checked.
+    pub network: Option<String>,
+}
+
+/// A vote scheduled to run inside the proposal's voting window.
+#[derive(Debug, Clone)]
+pub struct VoteExecutionEntry {
+    pub id: i64,
+    pub proposal_id: String,
+    pub identity: String,
+    pub recommendation_id: Option<i64>,
+    pub choice: Option<u32>,
+    pub approval: Value,
+    /// Voting window before `end`; `None` votes immediately.
+    pub window_secs: Option<i64>,
+    pub jitter_secs: i64,
+    pub replaces_action_id: Option<i64>,
+    pub dry_run: bool,
+}
+
+/// Votes stored in the `vote_actions` and `vote_executions` tables.
+#[derive(Clone)]
+pub struct PgVoteStore {
+    db_client: Arc<Pool<PostgresConnectionManager<NoTls>>>,
+}
+
+impl PgVoteStore {
+    pub fn new(db_client: Arc<Pool<PostgresConnectionManager<NoTls>>>) -> Self {
+        PgVoteStore { db_client }
+    }
+}
+
+#[async_trait]
+impl VoteStore for PgVoteStore {
+    async fn save_action(
+        &self,
+        action: &VoteAction,
+    ) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let row = conn
+            .query_one(
+                r#"
+                INSERT INTO vote_actions
+                    (proposal_id, recommendation_id, space, choice, signer_address,
+                     signed_message, signature, hub_response, status, approval,
+                     attempt, resubmission_of, replaces_action_id, safe_message_hash, identity,
+                     channel, simulation, rationale)
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                     $18)
+                RETURNING id
+                "#,
+                &[
+                    &action.proposal_id,
+                    &action.recommendation_id,
+                    &action.space,
+                    &(action.choice as i32),
+                    &action.signer_address,
+                    &action.signed_message.clone().map(Json),
+                    &action.signature,
+                    &action.hub_response.clone().map(Json),
+                    &action.status,
+                    &Json(action.approval.clone()),
+                    &action.attempt,
+                    &action.resubmission_of,
+                    &action.replaces_action_id,
+                    &action.safe_message_hash,
+                    &action.identity,
+                    &action.channel,
+                    &action.simulation.clone().map(Json),
+                    &action.rationale,
+                ],
+            )
+            .await?;
+
+        Ok(row.get("id"))
+    }
+
+    async fn action(
+        &self,
+        id: i64,
+    ) -> Result<Option<VoteAction>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT
+                    proposal_id, recommendation_id, space, choice, signer_address, signed_message,
+                    signature, hub_response, status, approval, attempt, resubmission_of,
+                    replaces_action_id, safe_message_hash, identity, channel, simulation, rationale
+                FROM vote_actions
+                WHERE id = $1
+                "#,
+                &[&id],
+            )
+            .await?;
+        Ok(row.as_ref().map(row_to_action))
+    }
+
+    async fn save_rationale_post(
+        &self,
+        id: i64,
+        post: &Value,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        conn.execute(
+            "UPDATE vote_actions SET rationale_post = $2 WHERE id = $1",
+            &[&id, &Json(post)],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn awaiting_signatures(
+        &self,
+    ) -> Result<Vec<AwaitingVote>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let rows = conn
+            .query(
+                r#"
+                SELECT
+                    va.id, va.proposal_id, va.recommendation_id, va.space, va.choice,
+                    va.signer_address, va.signed_message, va.signature, va.hub_response,
+                    va.status, va.approval, va.attempt, va.resubmission_of,
+                    va.replaces_action_id, va.safe_message_hash, va.identity, va.channel,
+                    va.simulation, va.rationale,
+                    COALESCE((EXTRACT(EPOCH FROM p."end"))::int8, 0) AS proposal_end
+                FROM vote_actions va
+                LEFT JOIN proposals p ON p.id = va.proposal_id
+                WHERE va.status = $1
+                ORDER BY va.created_at
+                "#,
+                &[&STATUS_AWAITING_SIGNATURES],
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| AwaitingVote {
+                id: row.get("id"),
+                proposal_end: row.get("proposal_end"),
+                action: row_to_action(&row),
+            })
+            .collect())
+    }
+
+    async fn finish_awaiting(
+        &self,
+        id: i64,
+        action: &VoteAction,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        conn.execute(
+            r#"
+            UPDATE vote_actions
+            SET status = $2, signature = $3, hub_response = $4
+            WHERE id = $1 AND status = $5
+            "#,
+            &[
+                &id,
+                &action.status,
+                &action.signature,
+                &action.hub_response.clone().map(Json),
+                &STATUS_AWAITING_SIGNATURES,
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn to_reconcile(
+        &self,
+        grace_secs: i64,
+    ) -> Result<Vec<SubmittedVote>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let rows = conn
+            .query(
+                r#"
+                SELECT
+                    id, proposal_id, identity, choice, signer_address, signature, hub_response,
+                    approval, attempt, reconcile_status
+                FROM vote_actions va
+                WHERE status = $1
+                  AND channel = $4
+                  AND signer_address IS NOT NULL
+                  AND (reconcile_status IS NULL OR reconcile_status = $2)
+                  AND created_at <= NOW() - make_interval(secs => $3::INT8::FLOAT8)
+                  AND NOT EXISTS (SELECT 1 FROM vote_actions r WHERE r.resubmission_of = va.id)
+                  AND NOT EXISTS (
+                      SELECT 1 FROM vote_actions n
+                      WHERE n.proposal_id = va.proposal_id
+                        AND n.signer_address = va.signer_address
+                        AND n.status = $1
+                        AND n.id > va.id
+                  )
+                ORDER BY created_at
+                "#,
+                &[&STATUS_SUBMITTED, &RECONCILE_MISSING, &grace_secs, &CHANNEL_SNAPSHOT],
+            )
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SubmittedVote {
+                id: row.get("id"),
+                proposal_id: row.get("proposal_id"),
+                identity: row.get("identity"),
+                choice: row.get::<_, i32>("choice") as u32,
+                signer_address: row.get("signer_address"),
+                signature: row.get("signature"),
+                hub_response: row.get("hub_response"),
+                approval: row.get("approval"),
+                attempt: row.get("attempt"),
+                reconcile_status: row.get("reconcile_status"),
+            })
+            .collect())
+    }
+
+    async fn set_reconcile_status(
+        &self,
+        id: i64,
+        reconcile_status: &str,
+        details: &Value,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        conn.execute(
+            r#"
+            UPDATE vote_actions
+            SET reconcile_status = $2, reconcile_details = $3, reconciled_at = NOW()
+            WHERE id = $1
+            "#,
+            &[&id, &reconcile_status, &Json(details)],
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn schedule(
+        &self,
+        entry: &VoteExecutionEntry,
+    ) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let row = conn
+            .query_one(
+                r#"
+                INSERT INTO vote_executions
+                    (proposal_id, recommendation_id, choice, approval, window_secs, jitter_secs,
+                     replaces_action_id, identity, dry_run)
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (proposal_id, identity) WHERE status = 'pending' DO UPDATE SET
+                    recommendation_id = EXCLUDED.recommendation_id,
+                    choice = EXCLUDED.choice,
+                    approval = EXCLUDED.approval,
+                    window_secs = EXCLUDED.window_secs,
+                    jitter_secs = EXCLUDED.jitter_secs,
+                    replaces_action_id = EXCLUDED.replaces_action_id,
+                    dry_run = EXCLUDED.dry_run,
+                    created_at = NOW(),
+                    updated_at = NOW()
+                RETURNING id
+                "#,
+                &[
+                    &entry.proposal_id,
+                    &entry.recommendation_id,
+                    &entry.choice.map(|c| c as i32),
+                    &Json(entry.approval.clone()),
+                    &entry.window_secs,
+                    &entry.jitter_secs,
+                    &entry.replaces_action_id,
+                    &entry.identity,
+                    &entry.dry_run,
+                ],
+            )
+            .await?;
+
+        Ok(row.get("id"))
+    }
+
+    async fn due_executions(
+        &self,
+        lead_secs: i64,
+    ) -> Result<Vec<VoteExecutionEntry>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let rows = conn
+            .query(
+                r#"
+                SELECT e.id, e.proposal_id, e.identity, e.recommendation_id, e.choice, e.approval,
+                       e.window_secs, e.jitter_secs, e.replaces_action_id, e.dry_run,
+                       (EXTRACT(EPOCH FROM e.created_at))::int8 AS created_at,
+                       (EXTRACT(EPOCH FROM p."start"))::int8 AS start,
+                       (EXTRACT(EPOCH FROM p."end"))::int8 AS "end"
+                FROM vote_executions e
+                JOIN proposals p ON p.id = e.proposal_id
+                WHERE e.status = 'pending'
+                  AND p."start" <= NOW()
+                  AND p."end" > NOW()
+                  AND p.state IS DISTINCT FROM 'closed'
+                ORDER BY p."end"
+                "#,
+                &[],
+            )
+            .await?;
+
+        let now = chrono::Utc::now().timestamp();
+        Ok(rows
+            .into_iter()
+            .filter(|row| {
+                let end: i64 = row.get("end");
+                let due_at = vote_due_at(
+                    row.get("created_at"),
+                    end,
+                    row.get("window_secs"),
+                    row.get("jitter_secs"),
+                    lead_secs,
+                );
+                is_vote_due(now, row.get("start"), end, due_at)
+            })
+            .map(|row| VoteExecutionEntry {
+                id: row.get("id"),
+                proposal_id: row.get("proposal_id"),
+                identity: row.get("identity"),
+                recommendation_id: row.get("recommendation_id"),
+                choice: row.get::<_, Option<i32>>("choice").map(|c| c as u32),
+                approval: row.get("approval"),
+                window_secs: row.get("window_secs"),
+                jitter_secs: row.get("jitter_secs"),
+                replaces_action_id: row.get("replaces_action_id"),
+                dry_run: row.get("dry_run"),
+            })
+            .collect())
+    }
+
+    async fn expire_executions(
+        &self,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let rows = conn
+            .query(
+                r#"
+                UPDATE vote_executions e
+                SET status = $1, updated_at = NOW()
+                FROM proposals p
+                WHERE p.id = e.proposal_id
+                  AND e.status = 'pending'
+                  AND (p."end" <= NOW() OR p.state = 'closed')
+                RETURNING e.proposal_id
+                "#,
+                &[&EXECUTION_EXPIRED],
+            )
+            .await?;
+
+        Ok(rows.into_iter().map(|row| row.get("proposal_id")).collect())
+    }
+
+    async fn cancel_pending(
+        &self,
+        proposal_ids: &[String],
+    ) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let cancelled = conn
+            .execute(
+                r#"
+                UPDATE vote_executions
+                SET status = $2, updated_at = NOW()
+                WHERE proposal_id = ANY($1) AND status = 'pending'
+                "#,
+                &[&proposal_ids, &EXECUTION_CANCELLED],
+            )
+            .await?;
+        Ok(cancelled)
+    }
+
+    async fn last_submitted(
+        &self,
+        proposal_id: &str,
+        identity: &str,
+    ) -> Result<Option<(i64, u32)>, Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        let row = conn
+            .query_opt(
+                r#"
+                SELECT id, choice
+                FROM vote_actions
+                WHERE proposal_id = $1 AND status = $2 AND identity = $3
+                ORDER BY created_at DESC, id DESC
+                LIMIT 1
+                "#,
+                &[&proposal_id, &STATUS_SUBMITTED, &identity],
+            )
+            .await?;
+
+        Ok(row.map(|row| (row.get("id"), row.get::<_, i32>("choice") as u32)))
+    }
+
+    async fn finish_execution(
+        &self,
+        id: i64,
+        status: &str,
+        vote_action_id: Option<i64>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let conn = self.db_client.get().await?;
+        conn.execute(
+            r#"
+            UPDATE vote_executions
+            SET status = $2, vote_action_id = $3, updated_at = NOW()
+            WHERE id = $1
+            "#,
+            &[&id, &status, &vote_action_id],
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+fn row_to_action(row: &Row) -> VoteAction {
+    VoteAction {
+        proposal_id: row.get("proposal_id"),
+        identity: row.get("identity"),
+        recommendation_id: row.get("recommendation_id"),
+        space: row.get("space"),
+        choice: row.get::<_, i32>("choice") as u32,
+        signer_address: row.get("signer_address"),
+        signed_message: row.get("signed_message"),
+        signature: row.get("signature"),
+        hub_response: row.get("hub_response"),
+        status: row.get("status"),
+        approval: row.get("approval"),
+        attempt: row.get("attempt"),
+        resubmission_of: row.get("resubmission_of"),
+        replaces_action_id: row.get("replaces_action_id"),
+        safe_message_hash: row.get("safe_message_hash"),
+        channel: row.get("channel"),
+        simulation: row.get("simulation"),
+        rationale: row.get("rationale"),
+    }
+}
+
+/// Submitted and simulated Snapshot votes with a signature that was not verified yet.
+pub async fn get_unverified_votes(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    limit: i64,
+) -> Result<Vec<UnverifiedVote>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let rows = conn
+        .query(
+            r#"
+            SELECT va.id, va.signer_address, va.signed_message, va.signature,
+                p.space->>'network' AS network
+            FROM vote_actions va
+            LEFT JOIN proposals p ON p.id = va.proposal_id
+            WHERE va.verified IS NULL
+              AND va.status IN ($1, $2)
+              AND va.channel = $3
+              AND va.signer_address IS NOT NULL
+              AND va.signed_message IS NOT NULL
+              AND va.signature IS NOT NULL
+              AND va.signature <> ''
+            ORDER BY va.id
+            LIMIT $4
+            "#,
+            &[&STATUS_SUBMITTED, &STATUS_SIMULATED, &CHANNEL_SNAPSHOT, &limit],
+        )
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| UnverifiedVote {
+            id: row.get("id"),
+            signer_address: row.get("signer_address"),
+            signed_message: row.get("signed_message"),
+            signature: row.get("signature"),
+            network: row.get("network"),
+        })
+        .collect())
+}
+
+pub async fn set_vote_verified(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    id: i64,
+    verified: bool,
+    details: &Value,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    conn.execute(
+        r#"
+        UPDATE vote_actions
+        SET verified = $2, verification = $3, verified_at = NOW()
+        WHERE id = $1
+        "#,
+        &[&id, &verified, &Json(details)],
+    )
+    .await?;
+    Ok(())
+}
+
+pub async fn get_vote_executions(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    status: Option<&String>,
+) -> Result<Value, Box<dyn Error>> {
+    let conn = db_client.get().await?;
+    let rows = conn
+        .query(
+            r#"
+            SELECT
+                e.id, e.proposal_id, e.identity, e.recommendation_id, e.choice, e.approval,
+                e.window_secs, e.jitter_secs, e.status, e.vote_action_id, e.replaces_action_id,
+                e.dry_run,
+                (EXTRACT(EPOCH FROM p."end"))::int8 AS proposal_end,
+                (EXTRACT(EPOCH FROM e.created_at))::int8 AS created_at,
+                (EXTRACT(EPOCH FROM e.updated_at))::int8 AS updated_at
+            FROM vote_executions e
+            LEFT JOIN proposals p ON p.id = e.proposal_id
+            WHERE $1::TEXT IS NULL OR e.status = $1
+            ORDER BY e.created_at DESC
+            LIMIT 500
+            "#,
+            &[&status],
+        )
+        .await?;
+
+    let executions: Vec<Value> = rows
+        .into_iter()
+        .map(|row| {
+            json!({
+                "id": row.get::<_, i64>("id"),
+                "proposalId": row.get::<_, String>("proposal_id"),
+                "identity": row.get::<_, String>("identity"),
+                "recommendationId": row.get::<_, Option<i64>>("recommendation_id"),
+                "choice": row.get::<_, Option<i32>>("choice"),
+                "approval": row.get::<_, Value>("approval"),
+                "windowSecs": row.get::<_, Option<i64>>("window_secs"),
+                "jitterSecs": row.get::<_, i64>("jitter_secs"),
+                "status": row.get::<_, String>("status"),
+                "voteActionId": row.get::<_, Option<i64>>("vote_action_id"),
+                "replacesActionId": row.get::<_, Option<i64>>("replaces_action_id"),
+                "dryRun": row.get::<_, bool>("dry_run"),
+                "proposalEnd": row.get::<_, Option<i64>>("proposal_end"),
+                "createdAt": row.get::<_, i64>("created_at"),
+                "updatedAt": row.get::<_, i64>("updated_at"),
+            })
+        })
+        .collect();
+
+    Ok(json!(executions))
+}
+
+pub async fn get_vote_history(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: Option<&String>,
+    limit: i64,
+) -> Result<Value, Box<dyn Error>> {
+    let conn = db_client.get().await?;
+    let rows = conn
+        .query(
+            r#"
+            SELECT
+                id, proposal_id, identity, recommendation_id, space, choice, signer_address,
+                signed_message, signature, hub_response, status, approval,
+                attempt, resubmission_of, replaces_action_id, safe_message_hash, channel,
+                simulation, rationale, rationale_post, reconcile_status, reconcile_details,
+                (EXTRACT(EPOCH FROM reconciled_at))::int8 AS reconciled_at, verified, verification,
+                (EXTRACT(EPOCH FROM created_at))::int8 AS created_at
+            FROM vote_actions
+            WHERE $1::TEXT IS NULL OR proposal_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2
+            "#,
+            &[&proposal_id, &limit],
+        )
+        .await?;
+
+    let actions: Vec<Value> = rows.into_iter().map(|row| row_to_vote_action(&row)).collect();
+
+    Ok(json!(actions))
+}
+
+fn row_to_vote_action(row: &Row) -> Value {
+    json!({
+        "id": row.get::<_, i64>("id"),
+        "proposalId": row.get::<_, String>("proposal_id"),
+        "identity": row.get::<_, String>("identity"),
+        "recommendationId": row.get::<_, Option<i64>>("recommendation_id"),
+        "space": row.get::<_, String>("space"),
+        "choice": row.get::<_, i32>("choice"),
+        "signerAddress": row.get::<_, Option<String>>("signer_address"),
+        "signedMessage": row.get::<_, Option<Value>>("signed_message"),
+        "signature": row.get::<_, Option<String>>("signature"),
+        "hubResponse": row.get::<_, Option<Value>>("hub_response"),
+        "status": row.get::<_, String>("status"),
+        "approval": row.get::<_, Value>("approval"),
+        "attempt": row.get::<_, i32>("attempt"),
+        "resubmissionOf": row.get::<_, Option<i64>>("resubmission_of"),
+        "replacesActionId": row.get::<_, Option<i64>>("replaces_action_id"),
+        "safeMessageHash": row.get::<_, Option<String>>("safe_message_hash"),
+        "channel": row.get::<_, String>("channel"),
+        "simulation": row.get::<_, Option<Value>>("simulation"),
+        "rationale": row.get::<_, Option<String>>("rationale"),
+        "rationalePost": row.get::<_, Option<Value>>("rationale_post"),
+        "reconcileStatus": row.get::<_, Option<String>>("reconcile_status"),
+        "reconcileDetails": row.get::<_, Option<Value>>("reconcile_details"),
+        "reconciledAt": row.get::<_, Option<i64>>("reconciled_at"),
+        "verified": row.get::<_, Option<bool>>("verified"),
+        "verification": row.get::<_, Option<Value>>("verification"),
+        "createdAt": row.get::<_, i64>("created_at"),
+    })
+}
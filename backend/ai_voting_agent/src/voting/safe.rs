@@ -0,0 +1,473 @@
+use ethers::abi::{encode, Token};
+use ethers::prelude::*;
+use ethers::utils::keccak256;
+use log::{error, info, warn};
+use reqwest::{Client as HttpClient, StatusCode};
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+use crate::{
+    config::config::{Config, SafeVoteMode},
+    contracts::contracts::{http_provider, GnosisSafe, EIP1271_MAGIC_VALUE},
+    identity::identity::{IdentityRegistry, SafeSettings, VotingIdentity},
+    store::store::Stores,
+    voting::{
+        executor::apply_submission,
+        rationale::publish_vote_rationale,
+        repository::{STATUS_FAILED, STATUS_SUBMITTED},
+        snapshot::{hex, new_vote, submit_vote, vote_hash, vote_typed_data, SignedVote, VoteError},
+    },
+};
+
+const SAFE_DOMAIN_TYPE: &str = "EIP712Domain(uint256 chainId,address verifyingContract)";
+const SAFE_MESSAGE_TYPE: &str = "SafeMessage(bytes message)";
+
+/// A vote from the Safe and how far its signature has come.
+#[derive(Debug, Clone)]
+pub struct SafeVote {
+    /// Signature is empty until `ready`.
+    pub signed: SignedVote,
+    /// Hash the owners sign (or the Safe approves), as shown in the Safe UI.
+    pub safe_message_hash: H256,
+    pub ready: bool,
+    /// What is still missing, for logs and the audit entry.
+    pub progress: String,
+}
+
+/// Hash of a message as the Safe's CompatibilityFallbackHandler checks it in
+/// `isValidSignature(bytes32,bytes)`: an EIP-712 `SafeMessage` over
+/// `abi.encode(message_hash)` in the Safe's own domain.
+pub fn safe_message_hash(chain_id: u64, safe: Address, message_hash: H256) -> H256 {
+    let domain_separator = keccak256(encode(&[
+        Token::FixedBytes(keccak256(SAFE_DOMAIN_TYPE).to_vec()),
+        Token::Uint(U256::from(chain_id)),
+        Token::Address(safe),
+    ]));
+    let struct_hash = keccak256(encode(&[
+        Token::FixedBytes(keccak256(SAFE_MESSAGE_TYPE).to_vec()),
+        Token::FixedBytes(keccak256(message_hash.as_bytes()).to_vec()),
+    ]));
+    let mut data = Vec::with_capacity(66);
+    data.extend_from_slice(&[0x19, 0x01]);
+    data.extend_from_slice(&domain_separator);
+    data.extend_from_slice(&struct_hash);
+    H256::from(keccak256(data))
+}
+
+/// Client for the message endpoints of the Safe Transaction Service.
+struct SafeTxService {
+    base_url: String,
+    client: HttpClient,
+}
+
+impl SafeTxService {
+    fn new(http: &HttpClient, base_url: &str) -> Self {
+        SafeTxService {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: http.clone(),
+        }
+    }
+
+    async fn get_message(&self, safe_message_hash: H256) -> Result<Option<Value>, VoteError> {
+        let response = self
+            .client
+            .get(format!("{}/api/v1/messages/{:?}/", self.base_url, safe_message_hash))
+            .send()
+            .await
+            .map_err(|err| VoteError::Transport(err.to_string()))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(VoteError::Transport(format!(
+                "Safe Transaction Service returned {} for message {:?}",
+                response.status(),
+                safe_message_hash
+            )));
+        }
+        let message = response
+            .json()
+            .await
+            .map_err(|err| VoteError::Transport(err.to_string()))?;
+        Ok(Some(message))
+    }
+
+    async fn post(&self, url: String, body: Value) -> Result<(), VoteError> {
+        let response = self
+            .client
+            .post(url)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|err| VoteError::Transport(err.to_string()))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(VoteError::Transport(format!(
+                "Safe Transaction Service returned {}: {}",
+                status, text
+            )));
+        }
+        Ok(())
+    }
+
+    /// Creates the message for the Safe with the first owner signature.
+    async fn propose_message(
+        &self,
+        safe: Address,
+        typed_data: &Value,
+        signature: &str,
+    ) -> Result<(), VoteError> {
+        let url = format!(
+            "{}/api/v1/safes/{}/messages/",
+            self.base_url,
+            ethers::utils::to_checksum(&safe, None)
+        );
+        self.post(url, json!({ "message": typed_data, "signature": signature, "safeAppId": null }))
+            .await
+    }
+
+    async fn add_signature(&self, safe_message_hash: H256, signature: &str) -> Result<(), VoteError> {
+        let url = format!(
+            "{}/api/v1/messages/{:?}/signatures/",
+            self.base_url, safe_message_hash
+        );
+        self.post(url, json!({ "signature": signature })).await
+    }
+}
+
+fn safe_settings(identity: &VotingIdentity) -> Result<&SafeSettings, VoteError> {
+    identity
+        .safe
+        .as_ref()
+        .ok_or_else(|| VoteError::Signing(format!("Identity {} is not a Safe", identity.name)))
+}
+
+fn safe_contract(
+    config: &Config,
+    identity: &VotingIdentity,
+) -> Result<(GnosisSafe<Provider<Http>>, u64), VoteError> {
+    let settings = safe_settings(identity)?;
+    let chain_id = settings
+        .network
+        .parse()
+        .map_err(|_| VoteError::Signing(format!("Invalid Safe network: {}", settings.network)))?;
+    let rpc_url = config
+        .rpc_url(&settings.network)
+        .ok_or_else(|| VoteError::Signing(format!("No RPC URL for network {}", settings.network)))?;
+    let provider = http_provider(rpc_url).map_err(|err| VoteError::Transport(err.to_string()))?;
+    Ok((GnosisSafe::new(identity.address, provider), chain_id))
+}
+
+/// Joins owner signatures the way `checkSignatures` reads them: 65 bytes
+/// each, ordered by owner address.
+fn join_owner_signatures(confirmations: &[Value]) -> Result<String, VoteError> {
+    let mut signatures = confirmations
+        .iter()
+        .map(|confirmation| {
+            let owner = confirmation["owner"]
+                .as_str()
+                .and_then(|owner| Address::from_str(owner).ok())
+                .ok_or_else(|| VoteError::Transport("Confirmation without owner".to_string()))?;
+            let signature = confirmation["signature"]
+                .as_str()
+                .map(|sig| sig.trim_start_matches("0x").to_string())
+                .ok_or_else(|| VoteError::Transport("Confirmation without signature".to_string()))?;
+            Ok((owner, signature))
+        })
+        .collect::<Result<Vec<_>, VoteError>>()?;
+    signatures.sort_by_key(|(owner, _)| *owner);
+    Ok(format!(
+        "0x{}",
+        signatures.into_iter().map(|(_, sig)| sig).collect::<String>()
+    ))
+}
+
+/// Adds our owner signature if the Safe Transaction Service lacks it and
+/// returns the joined signatures once the threshold is met.
+async fn collect_owner_signatures(
+    http: &HttpClient,
+    identity: &VotingIdentity,
+    safe: &GnosisSafe<Provider<Http>>,
+    typed_data: &Value,
+    safe_message_hash: H256,
+) -> Result<(Option<String>, String), VoteError> {
+    let signer = &identity.signer;
+    let service = SafeTxService::new(http, &safe_settings(identity)?.tx_service_url);
+    let call_error = |err: ContractError<Provider<Http>>| VoteError::Transport(err.to_string());
+
+    let message = service.get_message(safe_message_hash).await?;
+    let confirmed_by_us = message
+        .as_ref()
+        .and_then(|m| m["confirmations"].as_array())
+        .map(|confirmations| {
+            confirmations.iter().any(|c| {
+                c["owner"].as_str().and_then(|o| Address::from_str(o).ok()) == Some(signer.address())
+            })
+        })
+        .unwrap_or(false);
+    let is_owner = safe.is_owner(signer.address()).call().await.map_err(call_error)?;
+
+    if is_owner && !confirmed_by_us {
+        let signature = signer
+            .sign_hash(safe_message_hash)
+            .await
+            .map_err(|err| VoteError::Signing(err.to_string()))?;
+        let signature = hex(signature.to_vec());
+        match message {
+            None => service.propose_message(safe.address(), typed_data, &signature).await?,
+            Some(_) => service.add_signature(safe_message_hash, &signature).await?,
+        }
+    } else if !is_owner && message.is_none() {
+        return Err(VoteError::Signing(format!(
+            "{:?} is not an owner of the Safe and cannot propose the vote",
+            signer.address()
+        )));
+    }
+
+    let message = service
+        .get_message(safe_message_hash)
+        .await?
+        .ok_or_else(|| VoteError::Transport("Safe message disappeared".to_string()))?;
+    let confirmations = message["confirmations"].as_array().cloned().unwrap_or_default();
+    let threshold = safe.get_threshold().call().await.map_err(call_error)?.as_usize();
+    if confirmations.len() < threshold {
+        return Ok((
+            None,
+            format!("{} of {} owner signatures", confirmations.len(), threshold),
+        ));
+    }
+    let signature = match message["preparedSignature"].as_str() {
+        Some(prepared) => prepared.to_string(),
+        None => join_owner_signatures(&confirmations)?,
+    };
+    Ok((Some(signature), format!("{} of {} owner signatures", confirmations.len(), threshold)))
+}
+
+/// Brings the Safe signature on a vote message as far as possible.
+///
+/// In `owners` mode our signer adds its owner signature through the Safe
+/// Transaction Service and the joined signatures are used once the threshold
+/// is met. In `preapproved` mode the vote is ready once the Safe marked the
+/// message as signed on chain, and an empty signature (`0x`) is sent. Either
+/// way the hub verifies the vote with EIP-1271 `isValidSignature` on the Safe,
+/// which is checked here first.
+pub async fn collect_safe_signature(
+    config: &Config,
+    http: &HttpClient,
+    identity: &VotingIdentity,
+    typed_data: Value,
+) -> Result<SafeVote, VoteError> {
+    let (safe, chain_id) = safe_contract(config, identity)?;
+    let message_hash = vote_hash(&typed_data)?;
+    let safe_message_hash = safe_message_hash(chain_id, safe.address(), message_hash);
+
+    let (signature, progress) = match safe_settings(identity)?.mode {
+        SafeVoteMode::Owners => {
+            collect_owner_signatures(http, identity, &safe, &typed_data, safe_message_hash).await?
+        }
+        SafeVoteMode::PreApproved => {
+            let signed = safe
+                .signed_messages(safe_message_hash.into())
+                .call()
+                .await
+                .map_err(|err| VoteError::Transport(err.to_string()))?;
+            match signed.is_zero() {
+                true => (None, "waiting for the Safe to sign the message on chain".to_string()),
+                false => (Some("0x".to_string()), "signed on chain".to_string()),
+            }
+        }
+        SafeVoteMode::Disabled => {
+            return Err(VoteError::Signing("Safe voting is disabled".to_string()))
+        }
+    };
+
+    if let Some(signature) = &signature {
+        let bytes = Bytes::from_str(signature).map_err(|err| VoteError::Signing(err.to_string()))?;
+        let magic = safe
+            .is_valid_signature(message_hash.into(), bytes)
+            .call()
+            .await
+            .map_err(|err| VoteError::Signing(format!("Safe rejected the signature: {}", err)))?;
+        if magic != EIP1271_MAGIC_VALUE {
+            return Err(VoteError::Signing("Safe rejected the signature".to_string()));
+        }
+    }
+
+    Ok(SafeVote {
+        signed: SignedVote {
+            address: ethers::utils::to_checksum(&safe.address(), None),
+            typed_data,
+            signature: signature.clone().unwrap_or_default(),
+        },
+        safe_message_hash,
+        ready: signature.is_some(),
+        progress,
+    })
+}
+
+/// Builds a vote from the Safe and collects what signatures are available now.
+pub async fn prepare_safe_vote(
+    config: &Config,
+    http: &HttpClient,
+    identity: &VotingIdentity,
+    space: &str,
+    proposal: &str,
+    choice: u32,
+    reason: &str,
+) -> Result<SafeVote, VoteError> {
+    let typed_data = vote_typed_data(&new_vote(identity.address, space, proposal, choice, reason));
+    collect_safe_signature(config, http, identity, typed_data).await
+}
+
+/// Builds a vote from the Safe and signs our owner signature on it without
+/// proposing it to the Safe Transaction Service. The owner signature must
+/// recover to our signer, which must be an owner; with a threshold of one the
+/// Safe's `isValidSignature` is called as the hub would.
+///
+/// Returns the vote, carrying our owner signature when it alone is valid, and
+/// the evidence stored with a simulated vote.
+pub async fn simulate_safe_vote(
+    config: &Config,
+    identity: &VotingIdentity,
+    space: &str,
+    proposal: &str,
+    choice: u32,
+    reason: &str,
+) -> Result<(SignedVote, Value), VoteError> {
+    let (safe, chain_id) = safe_contract(config, identity)?;
+    let call_error = |err: ContractError<Provider<Http>>| VoteError::Transport(err.to_string());
+    let typed_data = vote_typed_data(&new_vote(identity.address, space, proposal, choice, reason));
+    let message_hash = vote_hash(&typed_data)?;
+    let safe_message_hash = safe_message_hash(chain_id, safe.address(), message_hash);
+
+    let signer_address = identity.signer.address();
+    let signature = identity
+        .signer
+        .sign_hash(safe_message_hash)
+        .await
+        .map_err(|err| VoteError::Signing(err.to_string()))?;
+    let recovered = signature
+        .recover(RecoveryMessage::Hash(safe_message_hash))
+        .map_err(|err| VoteError::Signing(err.to_string()))?;
+    if recovered != signer_address {
+        return Err(VoteError::Signing(format!(
+            "Owner signature recovers to {:?} instead of {:?}",
+            recovered, signer_address
+        )));
+    }
+    let is_owner = safe.is_owner(signer_address).call().await.map_err(call_error)?;
+    if !is_owner {
+        return Err(VoteError::Signing(format!(
+            "{:?} is not an owner of the Safe",
+            signer_address
+        )));
+    }
+    let threshold = safe.get_threshold().call().await.map_err(call_error)?.as_usize();
+    let signature = hex(signature.to_vec());
+    let is_valid = match threshold {
+        1 => {
+            let bytes =
+                Bytes::from_str(&signature).map_err(|err| VoteError::Signing(err.to_string()))?;
+            let magic = safe
+                .is_valid_signature(message_hash.into(), bytes)
+                .call()
+                .await
+                .map_err(|err| VoteError::Signing(format!("Safe rejected the signature: {}", err)))?;
+            Some(magic == EIP1271_MAGIC_VALUE)
+        }
+        _ => None,
+    };
+    if is_valid == Some(false) {
+        return Err(VoteError::Signing("Safe rejected the signature".to_string()));
+    }
+
+    let signed = SignedVote {
+        address: ethers::utils::to_checksum(&safe.address(), None),
+        typed_data,
+        signature: if is_valid == Some(true) { signature.clone() } else { String::new() },
+    };
+    let details = json!({
+        "voteHash": format!("{:?}", message_hash),
+        "safeMessageHash": format!("{:?}", safe_message_hash),
+        "ownerSignature": signature,
+        "threshold": threshold,
+        "isValidSignature": is_valid,
+        "payload": signed.payload(),
+    });
+    Ok((signed, details))
+}
+
+/// Follows up on Safe votes waiting for signatures: submits the ones that are
+/// now fully signed and gives up on the ones whose proposal closed.
+pub async fn run_safe_signature_collection(
+    stores: &Stores,
+    config: &Config,
+    registry: &IdentityRegistry,
+    http: &HttpClient,
+) {
+    let awaiting = match stores.votes.awaiting_signatures().await {
+        Ok(awaiting) => awaiting,
+        Err(e) => {
+            error!("Error fetching votes awaiting Safe signatures: {}", e);
+            return;
+        }
+    };
+    info!("Found {} votes awaiting Safe signatures", awaiting.len());
+
+    for mut vote in awaiting {
+        if vote.proposal_end <= chrono::Utc::now().timestamp() {
+            error!(
+                "ALERT: proposal {} closed before the Safe signed vote {}",
+                vote.action.proposal_id, vote.id
+            );
+            vote.action.status = STATUS_FAILED.to_string();
+            if let Err(e) = stores.votes.finish_awaiting(vote.id, &vote.action).await {
+                error!("Error updating vote {}: {}", vote.id, e);
+            }
+            continue;
+        }
+        let Some(typed_data) = vote.action.signed_message.clone() else {
+            continue;
+        };
+        let Some(identity) = registry.get(&vote.action.identity) else {
+            error!("Vote {} belongs to unknown identity {}", vote.id, vote.action.identity);
+            continue;
+        };
+
+        let safe_vote = match collect_safe_signature(config, http, &identity, typed_data).await {
+            Ok(safe_vote) => safe_vote,
+            Err(e) => {
+                error!("Error collecting Safe signatures for vote {}: {}", vote.id, e);
+                continue;
+            }
+        };
+        if !safe_vote.ready {
+            info!("Vote {} is waiting for the Safe: {}", vote.id, safe_vote.progress);
+            continue;
+        }
+
+        vote.action.signature = Some(safe_vote.signed.signature.clone());
+        let result = submit_vote(http, &config.hub_message_url(), &safe_vote.signed).await;
+        let (_, error) = match apply_submission(&mut vote.action, result) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                error!("Error recording submission of vote {}: {}", vote.id, e);
+                continue;
+            }
+        };
+        if let Err(e) = stores.votes.finish_awaiting(vote.id, &vote.action).await {
+            error!("Error updating vote {}: {}", vote.id, e);
+            continue;
+        }
+        if config.publish_rationale && vote.action.status == STATUS_SUBMITTED {
+            if let Err(e) = publish_vote_rationale(stores, config, http, vote.id, &vote.action).await {
+                error!("Error publishing rationale of vote {}: {}", vote.id, e);
+            }
+        }
+        match error {
+            None => info!("Safe vote {} on proposal {} submitted", vote.id, vote.action.proposal_id),
+            Some(err) => warn!("Safe vote {} on proposal {} failed: {}", vote.id, vote.action.proposal_id, err),
+        }
+    }
+}
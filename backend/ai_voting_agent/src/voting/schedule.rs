@@ -0,0 +1,658 @@
+use log::{error, info, warn};
+use rand::Rng;
+use reqwest::Client as HttpClient;
+use serde_json::{json, Value};
+use std::error::Error;
+
+use crate::{
+    config::config::{Config, RevotePolicy, VoteStrategy},
+    identity::identity::{IdentityRegistry, VotingIdentity},
+    store::store::Stores,
+    voting::{
+        executor::{execute_vote, resolve_choice, VoteOrder},
+        repository::{
+            VoteExecutionEntry, EXECUTION_EXECUTED, EXECUTION_FAILED, EXECUTION_SIMULATED,
+            STATUS_AWAITING_SIGNATURES, STATUS_SIMULATED, STATUS_SUBMITTED,
+        },
+    },
+};
+
+/// Scheduled votes run at the latest this many seconds before `end`, so a
+/// scheduler tick cannot miss the deadline.
+const MIN_LEAD_SECS: i64 = 900;
+
+/// Random delay in `[0, max_secs]` added to a scheduled vote, so votes of
+/// several identities do not land at the same moment.
+pub fn draw_jitter(max_secs: u64, rng: &mut impl Rng) -> i64 {
+    match max_secs {
+        0 => 0,
+        max => rng.gen_range(0..=max) as i64,
+    }
+}
+
+/// When a scheduled vote becomes due, in unix seconds: `window_secs` before
+/// `end` plus its jitter, or as soon as it was scheduled when there is no
+/// window; in any case no later than `lead_secs` before `end`.
+pub fn vote_due_at(
+    created_at: i64,
+    end: i64,
+    window_secs: Option<i64>,
+    jitter_secs: i64,
+    lead_secs: i64,
+) -> i64 {
+    let opens = window_secs.map_or(created_at, |window| end - window);
+    (opens + jitter_secs).min(end - lead_secs)
+}
+
+/// Whether a pending vote due at `due_at` runs at `now`. It never runs before
+/// voting starts, nor once the proposal closed; it is expired then.
+pub fn is_vote_due(now: i64, start: i64, end: i64, due_at: i64) -> bool {
+    start <= now && now < end && now >= due_at
+}
+
+/// Schedules a vote from one identity according to the space's voting
+/// strategy, replacing its previous vote on the proposal if there is one.
+/// Returns the execution id.
+#[allow(clippy::too_many_arguments)]
+async fn schedule_vote(
+    stores: &Stores,
+    config: &Config,
+    identity: &VotingIdentity,
+    proposal_id: &String,
+    space: &str,
+    recommendation_id: i64,
+    choice: Option<u32>,
+    approval: Value,
+    dry_run: bool,
+) -> Result<i64, Box<dyn Error + Send + Sync>> {
+    let window_secs = match config.vote_strategy_for(space) {
+        VoteStrategy::Immediate => None,
+        VoteStrategy::LastHours(hours) => Some(hours * 3600),
+    };
+    let jitter_secs = draw_jitter(config.vote_jitter_secs, &mut rand::thread_rng());
+    let replaces_action_id = stores
+        .votes
+        .last_submitted(proposal_id, &identity.name)
+        .await?
+        .map(|(id, _)| id);
+
+    let entry = VoteExecutionEntry {
+        id: 0,
+        proposal_id: proposal_id.clone(),
+        identity: identity.name.clone(),
+        recommendation_id: Some(recommendation_id),
+        choice,
+        approval,
+        window_secs,
+        jitter_secs,
+        replaces_action_id,
+        dry_run,
+    };
+    let execution_id = stores.votes.schedule(&entry).await?;
+    info!(
+        "Vote on proposal {} as {} scheduled as execution {} (window {:?}s, jitter {}s, replaces {:?}, dry run {})",
+        proposal_id, identity.name, execution_id, window_secs, jitter_secs, replaces_action_id, dry_run
+    );
+
+    Ok(execution_id)
+}
+
+async fn get_proposal(
+    stores: &Stores,
+    proposal_id: &str,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let proposal = stores
+        .proposals
+        .get(proposal_id)
+        .await?
+        .ok_or("Proposal not found")?;
+    Ok(proposal.to_json())
+}
+
+/// Approves the latest recommendation for a proposal and schedules a vote
+/// from every identity of its space according to the space's voting strategy.
+/// Returns the execution ids. `dry_run` makes the scheduled votes simulated.
+#[allow(clippy::too_many_arguments)]
+pub async fn approve_and_schedule(
+    stores: &Stores,
+    config: &Config,
+    registry: &IdentityRegistry,
+    proposal_id: &String,
+    choice: Option<u32>,
+    approved_by: &String,
+    comment: Option<&String>,
+    dry_run: bool,
+) -> Result<Vec<i64>, Box<dyn Error + Send + Sync>> {
+    let proposal = get_proposal(stores, proposal_id).await?;
+    let space = proposal["space"]["id"].as_str().ok_or("Proposal has no space")?;
+    let identities = registry.for_space(space);
+    if identities.is_empty() {
+        return Err(format!("No voting identity for space {}", space).into());
+    }
+
+    let recommendation_id = stores
+        .recommendations
+        .approve_latest(proposal_id, approved_by, comment.map(String::as_str))
+        .await?
+        .ok_or("Proposal has no recommendation to approve")?
+        .id;
+
+    let approval = json!([{
+        "approvedBy": approved_by,
+        "approvedAt": chrono::Utc::now().timestamp(),
+        "comment": comment,
+        "recommendationId": recommendation_id,
+    }]);
+    let mut execution_ids = Vec::with_capacity(identities.len());
+    for identity in identities {
+        let execution_id = schedule_vote(
+            stores,
+            config,
+            &identity,
+            proposal_id,
+            space,
+            recommendation_id,
+            choice,
+            approval.clone(),
+            dry_run,
+        )
+        .await?;
+        execution_ids.push(execution_id);
+    }
+    Ok(execution_ids)
+}
+
+/// Called after a proposal was re-analyzed. For every identity that already
+/// voted a choice the new recommendation no longer resolves to, queues a
+/// replacement vote if the re-vote policy allows it.
+pub async fn queue_revote(
+    stores: &Stores,
+    config: &Config,
+    registry: &IdentityRegistry,
+    proposal_id: &String,
+    recommendation_id: i64,
+    recommendation: &Value,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let proposal = get_proposal(stores, proposal_id).await?;
+    let space = proposal["space"]["id"].as_str().ok_or("Proposal has no space")?;
+
+    for identity in registry.for_space(space) {
+        let Some((voted_action_id, voted_choice)) =
+            stores.votes.last_submitted(proposal_id, &identity.name).await?
+        else {
+            continue;
+        };
+        let Some(new_choice) = resolve_choice(&proposal["choices"], recommendation) else {
+            warn!("Re-analysis of proposal {} gives no resolvable choice", proposal_id);
+            return Ok(());
+        };
+        if new_choice == voted_choice {
+            info!(
+                "Re-analysis of proposal {} keeps choice {} of {}",
+                proposal_id, voted_choice, identity.name
+            );
+            continue;
+        }
+
+        match config.revote_policy {
+            RevotePolicy::Never => {
+                warn!(
+                    "Proposal {}: recommendation {} changes vote {} of {} from {} to {}, re-voting is disabled",
+                    proposal_id, recommendation_id, voted_action_id, identity.name, voted_choice, new_choice
+                );
+            }
+            RevotePolicy::Approval => {
+                warn!(
+                    "Proposal {}: recommendation {} changes vote {} of {} from {} to {}, waiting for approval",
+                    proposal_id, recommendation_id, voted_action_id, identity.name, voted_choice, new_choice
+                );
+            }
+            RevotePolicy::Auto => {
+                let approval = json!([{
+                    "approvedBy": "revote-policy",
+                    "approvedAt": chrono::Utc::now().timestamp(),
+                    "comment": format!(
+                        "re-analysis changed the choice from {} to {}", voted_choice, new_choice
+                    ),
+                    "recommendationId": recommendation_id,
+                    "replacesActionId": voted_action_id,
+                }]);
+                schedule_vote(
+                    stores,
+                    config,
+                    &identity,
+                    proposal_id,
+                    space,
+                    recommendation_id,
+                    Some(new_choice),
+                    approval,
+                    false,
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Runs the scheduled votes whose window has opened and expires the ones
+/// whose proposal closed first.
+pub async fn run_vote_executions(
+    stores: &Stores,
+    config: &Config,
+    registry: &IdentityRegistry,
+    http: &HttpClient,
+) {
+    match stores.votes.expire_executions().await {
+        Ok(expired) => {
+            for proposal_id in expired {
+                error!("ALERT: proposal {} closed before its scheduled vote ran", proposal_id);
+            }
+        }
+        Err(e) => error!("Error expiring vote executions: {}", e),
+    }
+
+    let due = match stores.votes.due_executions(MIN_LEAD_SECS).await {
+        Ok(due) => due,
+        Err(e) => {
+            error!("Error fetching due vote executions: {}", e);
+            return;
+        }
+    };
+    info!("Found {} due vote executions", due.len());
+
+    for entry in due {
+        let Some(identity) = registry.get(&entry.identity) else {
+            error!(
+                "ALERT: scheduled vote {} on proposal {} belongs to unknown identity {}",
+                entry.id, entry.proposal_id, entry.identity
+            );
+            if let Err(e) = stores.votes.finish_execution(entry.id, EXECUTION_FAILED, None).await {
+                error!("Error updating vote execution {}: {}", entry.id, e);
+            }
+            continue;
+        };
+        let mut order = VoteOrder::new(&entry.proposal_id, entry.choice, entry.approval.clone());
+        order.replaces_action_id = entry.replaces_action_id;
+        order.dry_run = entry.dry_run;
+        let (status, action_id) = match execute_vote(stores, config, http, &identity, order).await {
+            Ok(execution)
+                if execution.status == STATUS_SUBMITTED
+                    || execution.status == STATUS_AWAITING_SIGNATURES =>
+            {
+                (EXECUTION_EXECUTED, Some(execution.action_id))
+            }
+            Ok(execution) if execution.status == STATUS_SIMULATED => {
+                (EXECUTION_SIMULATED, Some(execution.action_id))
+            }
+            Ok(execution) => {
+                error!(
+                    "ALERT: scheduled vote on proposal {} as {} failed: {}",
+                    entry.proposal_id,
+                    entry.identity,
+                    execution.error.unwrap_or_default()
+                );
+                (EXECUTION_FAILED, Some(execution.action_id))
+            }
+            Err(e) => {
+                error!(
+                    "ALERT: scheduled vote on proposal {} as {} failed: {}",
+                    entry.proposal_id, entry.identity, e
+                );
+                (EXECUTION_FAILED, None)
+            }
+        };
+        if let Err(e) = stores.votes.finish_execution(entry.id, status, action_id).await {
+            error!("Error updating vote execution {}: {}", entry.id, e);
+        }
+    }
+}
+
+/// Alerts once for every recommendation that is still unapproved while its
+/// proposal ends within `escalation_hours`.
+pub async fn escalate_unapproved(stores: &Stores, config: &Config) {
+    let closing = match stores.proposals.ending_within(config.escalation_hours).await {
+        Ok(closing) => closing,
+        Err(e) => {
+            error!("Error fetching proposals near their deadline: {}", e);
+            return;
+        }
+    };
+
+    for proposal in closing {
+        let rec = match stores.recommendations.latest(&proposal.id).await {
+            Ok(Some(rec)) => rec,
+            Ok(None) => continue,
+            Err(e) => {
+                error!("Error fetching the recommendation for {}: {}", proposal.id, e);
+                continue;
+            }
+        };
+        if rec.approved_at.is_some() || rec.escalated_at.is_some() || !rec.is_valid() {
+            continue;
+        }
+        let hours_left =
+            (proposal.end.unwrap_or_default() - chrono::Utc::now().timestamp()) as f64 / 3600.0;
+        warn!(
+            "ALERT: recommendation {} for proposal {} ({}) is not approved and voting ends in {:.1}h",
+            rec.id,
+            rec.proposal_id,
+            proposal.title.as_deref().unwrap_or(""),
+            hours_left
+        );
+        if let Err(e) = stores.recommendations.mark_escalated(rec.id).await {
+            error!("Error escalating recommendation {}: {}", rec.id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use ethers::types::{Address, Signature, H256};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::sync::Arc;
+
+    use crate::{
+        identity::identity::IdentityKind,
+        proposal_snapchot::prop_struct::{Choice, Proposal, SpaceRef},
+        recommendation::store::Recommendation,
+        signer::signer::{SignerError, VoteSigner},
+        voting::repository::{VoteAction, CHANNEL_SNAPSHOT},
+    };
+
+    const START: i64 = 1_700_000_000;
+    const END: i64 = START + 7 * 86_400;
+    const SPACE: &str = "space.eth";
+
+    /// Scheduling never signs; a vote that gets this far fails.
+    #[derive(Debug)]
+    struct NoSigner;
+
+    #[async_trait]
+    impl VoteSigner for NoSigner {
+        fn address(&self) -> Address {
+            Address::zero()
+        }
+
+        async fn sign_hash(&self, _hash: H256) -> Result<Signature, SignerError> {
+            Err(SignerError("no key in tests".to_string()))
+        }
+    }
+
+    fn registry(names: &[&str]) -> IdentityRegistry {
+        IdentityRegistry::with_identities(
+            names
+                .iter()
+                .map(|name| VotingIdentity {
+                    name: name.to_string(),
+                    kind: IdentityKind::Eoa,
+                    address: Address::zero(),
+                    signer: Arc::new(NoSigner),
+                    safe: None,
+                })
+                .collect(),
+        )
+    }
+
+    /// An active proposal of `SPACE` closing `ends_in` seconds from now.
+    fn proposal(id: &str, ends_in: i64) -> Proposal {
+        let now = chrono::Utc::now().timestamp();
+        Proposal {
+            id: id.to_string(),
+            space: Some(SpaceRef {
+                id: SPACE.to_string(),
+                ..Default::default()
+            }),
+            title: Some(format!("Proposal {}", id)),
+            choices: vec![Choice("For".to_string()), Choice("Against".to_string())],
+            start: Some(now - 3600),
+            end: Some(now + ends_in),
+            state: Some("active".to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn recommend(stores: &Stores, proposal_id: &str) -> i64 {
+        let analysis = json!({ "recommendation": { "For": 0.8, "Against": 0.2 } });
+        stores
+            .recommendations
+            .save(&Recommendation::from_analysis(proposal_id, &analysis))
+            .await
+            .unwrap()
+    }
+
+    fn submitted_vote(proposal_id: &str, identity: &str, choice: u32) -> VoteAction {
+        VoteAction {
+            proposal_id: proposal_id.to_string(),
+            identity: identity.to_string(),
+            recommendation_id: None,
+            space: SPACE.to_string(),
+            choice,
+            signer_address: Some(format!("{:?}", Address::zero())),
+            signed_message: None,
+            signature: None,
+            hub_response: None,
+            status: STATUS_SUBMITTED.to_string(),
+            approval: json!([]),
+            attempt: 1,
+            resubmission_of: None,
+            replaces_action_id: None,
+            safe_message_hash: None,
+            channel: CHANNEL_SNAPSHOT.to_string(),
+            simulation: None,
+            rationale: None,
+        }
+    }
+
+    async fn approve(
+        stores: &Stores,
+        config: &Config,
+        registry: &IdentityRegistry,
+        proposal_id: &str,
+    ) -> Result<Vec<i64>, Box<dyn Error + Send + Sync>> {
+        approve_and_schedule(
+            stores,
+            config,
+            registry,
+            &proposal_id.to_string(),
+            None,
+            &"alice".to_string(),
+            Some(&"looks right".to_string()),
+            false,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn approval_schedules_a_vote_per_identity() {
+        let stores = Stores::memory_with_proposals(vec![proposal("p1", 7 * 86_400)]);
+        let recommendation_id = recommend(&stores, "p1").await;
+        let registry = registry(&["eoa", "delegate"]);
+
+        let execution_ids = approve(&stores, &Config::for_tests(), &registry, "p1").await.unwrap();
+        assert_eq!(execution_ids.len(), 2);
+
+        let rec = stores.recommendations.latest("p1").await.unwrap().unwrap();
+        assert_eq!(rec.approved_by.as_deref(), Some("alice"));
+        assert_eq!(rec.approval_comment.as_deref(), Some("looks right"));
+
+        // Immediate votes are due as soon as they are scheduled.
+        let due = stores.votes.due_executions(MIN_LEAD_SECS).await.unwrap();
+        let mut identities: Vec<&str> = due.iter().map(|e| e.identity.as_str()).collect();
+        identities.sort();
+        assert_eq!(identities, ["delegate", "eoa"]);
+        for entry in &due {
+            assert!(execution_ids.contains(&entry.id));
+            assert_eq!(entry.recommendation_id, Some(recommendation_id));
+            assert_eq!(entry.window_secs, None);
+            assert_eq!(entry.replaces_action_id, None);
+            assert_eq!(entry.approval[0]["approvedBy"], "alice");
+        }
+    }
+
+    #[tokio::test]
+    async fn approving_again_replaces_the_pending_vote() {
+        let stores = Stores::memory_with_proposals(vec![proposal("p1", 7 * 86_400)]);
+        recommend(&stores, "p1").await;
+        let registry = registry(&["eoa"]);
+        let config = Config::for_tests();
+        let voted = stores
+            .votes
+            .save_action(&submitted_vote("p1", "eoa", 2))
+            .await
+            .unwrap();
+
+        let first = approve(&stores, &config, &registry, "p1").await.unwrap();
+        let second = approve(&stores, &config, &registry, "p1").await.unwrap();
+        assert_eq!(first, second);
+
+        let due = stores.votes.due_executions(MIN_LEAD_SECS).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].replaces_action_id, Some(voted));
+    }
+
+    #[tokio::test]
+    async fn windowed_votes_wait_for_their_window() {
+        let stores = Stores::memory_with_proposals(vec![
+            proposal("later", 7 * 86_400),
+            proposal("soon", 3600),
+        ]);
+        recommend(&stores, "later").await;
+        recommend(&stores, "soon").await;
+        let registry = registry(&["eoa"]);
+        let config = Config {
+            default_vote_strategy: VoteStrategy::LastHours(6),
+            ..Config::for_tests()
+        };
+
+        approve(&stores, &config, &registry, "later").await.unwrap();
+        approve(&stores, &config, &registry, "soon").await.unwrap();
+
+        let due = stores.votes.due_executions(MIN_LEAD_SECS).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].proposal_id, "soon");
+        assert_eq!(due[0].window_secs, Some(6 * 3600));
+    }
+
+    #[tokio::test]
+    async fn approval_needs_a_recommendation_and_an_identity() {
+        let stores = Stores::memory_with_proposals(vec![proposal("p1", 7 * 86_400)]);
+        let config = Config::for_tests();
+
+        assert!(approve(&stores, &config, &registry(&["eoa"]), "p1").await.is_err());
+        recommend(&stores, "p1").await;
+        assert!(approve(&stores, &config, &registry(&[]), "p1").await.is_err());
+        assert!(approve(&stores, &config, &registry(&["eoa"]), "unknown").await.is_err());
+
+        assert!(stores.votes.due_executions(MIN_LEAD_SECS).await.unwrap().is_empty());
+        let rec = stores.recommendations.latest("p1").await.unwrap().unwrap();
+        assert_eq!(rec.approved_at, None);
+    }
+
+    #[tokio::test]
+    async fn closed_proposals_expire_their_scheduled_votes() {
+        // Closed on the hub although its `end` is still ahead.
+        let closed = Proposal {
+            state: Some("closed".to_string()),
+            ..proposal("closed", 3600)
+        };
+        let stores = Stores::memory_with_proposals(vec![closed]);
+        recommend(&stores, "closed").await;
+        let registry = registry(&["eoa"]);
+        let config = Config::for_tests();
+        approve(&stores, &config, &registry, "closed").await.unwrap();
+
+        assert!(stores.votes.due_executions(0).await.unwrap().is_empty());
+        assert_eq!(stores.votes.expire_executions().await.unwrap(), vec!["closed".to_string()]);
+        // Nothing is pending any more: nothing to expire or cancel.
+        assert!(stores.votes.expire_executions().await.unwrap().is_empty());
+        assert_eq!(stores.votes.cancel_pending(&["closed".to_string()]).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn unapproved_recommendations_near_the_deadline_are_escalated_once() {
+        let stores = Stores::memory_with_proposals(vec![
+            proposal("closing", 2 * 3600),
+            proposal("approved", 2 * 3600),
+            proposal("open", 3 * 86_400),
+        ]);
+        for id in ["closing", "approved", "open"] {
+            recommend(&stores, id).await;
+        }
+        stores
+            .recommendations
+            .approve_latest("approved", "alice", None)
+            .await
+            .unwrap();
+        let config = Config::for_tests();
+
+        escalate_unapproved(&stores, &config).await;
+        let escalated_at = |id: &'static str| {
+            let stores = stores.clone();
+            async move {
+                stores.recommendations.latest(id).await.unwrap().unwrap().escalated_at
+            }
+        };
+        let first = escalated_at("closing").await;
+        assert!(first.is_some());
+        assert_eq!(escalated_at("approved").await, None);
+        assert_eq!(escalated_at("open").await, None);
+
+        escalate_unapproved(&stores, &config).await;
+        assert_eq!(escalated_at("closing").await, first);
+    }
+
+    #[test]
+    fn immediate_votes_are_due_when_scheduled() {
+        let created_at = START + 3600;
+        let due_at = vote_due_at(created_at, END, None, 0, MIN_LEAD_SECS);
+        assert_eq!(due_at, created_at);
+        assert!(!is_vote_due(created_at - 1, START, END, due_at));
+        assert!(is_vote_due(created_at, START, END, due_at));
+    }
+
+    #[test]
+    fn windowed_votes_open_window_before_end_plus_jitter() {
+        let due_at = vote_due_at(START, END, Some(6 * 3600), 300, MIN_LEAD_SECS);
+        assert_eq!(due_at, END - 6 * 3600 + 300);
+        assert!(!is_vote_due(due_at - 1, START, END, due_at));
+        assert!(is_vote_due(due_at, START, END, due_at));
+    }
+
+    #[test]
+    fn lead_time_caps_window_and_jitter() {
+        // A window shorter than the lead time, or a jitter pushing past it,
+        // still runs `MIN_LEAD_SECS` before the end.
+        assert_eq!(vote_due_at(START, END, Some(60), 0, MIN_LEAD_SECS), END - MIN_LEAD_SECS);
+        assert_eq!(
+            vote_due_at(START, END, Some(1800), 3600, MIN_LEAD_SECS),
+            END - MIN_LEAD_SECS
+        );
+        // A vote scheduled inside the lead time is due right away.
+        let created_at = END - 60;
+        let due_at = vote_due_at(created_at, END, None, 600, MIN_LEAD_SECS);
+        assert!(is_vote_due(created_at, START, END, due_at));
+    }
+
+    #[test]
+    fn votes_never_run_outside_voting() {
+        // Scheduled before voting starts: waits for `start`.
+        let due_at = vote_due_at(START - 86_400, END, None, 0, MIN_LEAD_SECS);
+        assert!(!is_vote_due(START - 1, START, END, due_at));
+        assert!(is_vote_due(START, START, END, due_at));
+        // Once the proposal closed the vote is expired, not due.
+        assert!(!is_vote_due(END, START, END, due_at));
+        assert!(!is_vote_due(END + 1, START, END, due_at));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(draw_jitter(0, &mut rng), 0);
+        for _ in 0..1000 {
+            assert!((0..=120).contains(&draw_jitter(120, &mut rng)));
+        }
+    }
+}
@@ -1,34 +1,25 @@
 use ethers::prelude::*;
-use ethers::signers::Signer;
-use ethers::core::types::transaction::eip712::Eip712;
-use reqwest::Client;
+use reqwest::Client as HttpClient;
 use serde::{Deserialize, Serialize};
-use anyhow::{anyhow, Context};
 use chrono::Utc;
-use ethers::prelude::*;
-use serde_json::json;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
 
-// Import the EIP712 trait and helper from eip712_enc
 use eip712_enc::{hash_structured_data, EIP712};
+use ethers::types::transaction::eip712::Eip712;
 
-use crate::config::config::Config;
+use crate::signer::signer::VoteSigner;
 
 /// Helper function to produce a hex string (0x-prefixed) from bytes.
-fn hex(s: impl AsRef<[u8]>) -> String {
+pub(crate) fn hex(s: impl AsRef<[u8]>) -> String {
     format!("0x{}", hex_fmt::HexFmt(s.as_ref()))
 }
 
-#[derive(Debug, Clone, Eip712, Serialize, Deserialize)]
-#[eip712(
-    domain_name = "snapshot",
-    domain_version = "0.1.4",
-    // For off-chain signing, chain_id can be arbitrary (here using 1)
-    chain_id = 1,
-    verifying_contract = "0x0000000000000000000000000000000000000000"
-)]
+#[derive(Debug, Clone, Eip712, EthAbiType, Serialize, Deserialize)]
+#[eip712(name = "snapshot", version = "0.1.4")]
 pub struct Vote {
-    // Primary field to hash for EIP-712 signing.
-    #[eip712(primary)]
     pub from: Address,
     pub space: String,
     pub timestamp: u64,
@@ -39,54 +30,97 @@ pub struct Vote {
     pub metadata: String,
 }
 
+/// A proposal message, as created from the Snapshot UI.
+#[derive(Debug, Clone, Eip712, EthAbiType, Serialize, Deserialize)]
+#[eip712(name = "snapshot", version = "0.1.4")]
+pub struct Proposal {
+    pub from: Address,
+    pub space: String,
+    pub timestamp: u64,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub title: String,
+    pub body: String,
+    pub discussion: String,
+    pub choices: Vec<String>,
+    pub start: u64,
+    pub end: u64,
+    pub snapshot: u64,
+    pub plugins: String,
+    pub app: String,
+}
 
-// Define the structure of the payload to be sent to the Snapshot server.
-#[derive(Debug, Serialize)]
-struct SnapshotVote {
-    address: String,
-    msg: Vote,
-    sig: String,
+/// A vote or proposal message signed and ready to be sent to the Snapshot hub.
+#[derive(Debug, Clone, Serialize)]
+pub struct SignedVote {
+    /// Address the hub attributes the vote to.
+    pub address: String,
+    /// The full EIP-712 typed data that was signed.
+    pub typed_data: Value,
+    pub signature: String,
 }
 
-/// Submits a vote to Snapshot with the given proposal ID and choice.
-/// 
-/// # Arguments
-///
-/// * `proposal` - A string representing the proposal ID.
-/// * `choice` - A u32 representing the vote option.
-/// 
-/// # Returns
-///
-/// * `eyre::Result<()>` indicating success or failure.
-pub async fn vote(proposal: String, choice: u32) -> Result<(), Box<dyn std::error::Error>> {
-    let config = Config::from_env()?;
-    // Replace with your Safe key's private key.
-    let private_key = config.safe_wallet_private_key;
-
-    // Create a wallet for signing; chain_id is set to 1 for off-chain signing.
-    let wallet: LocalWallet = private_key.parse()?;
-    let wallet = wallet.with_chain_id(1u64);
-
-    // Capture the current timestamp once.
-    let timestamp = Utc::now().timestamp() as u64;
-
-    // Build the EIP‑712 message JSON.
-    // Note that we use the same field names and types as required by Snapshot.
-    let vote_message = json!({
+impl SignedVote {
+    /// Body of the `POST /api/message` request.
+    pub fn payload(&self) -> Value {
+        json!({
+            "address": self.address,
+            "msg": self.typed_data["message"],
+            "sig": self.signature
+        })
+    }
+}
+
+/// Receipt returned by the hub for an accepted vote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoteReceipt {
+    /// Id of the vote message (its EIP-712 hash).
+    pub id: String,
+    pub ipfs: Option<String>,
+    pub relayer: Option<Value>,
+}
+
+#[derive(Debug)]
+pub enum VoteError {
+    /// The vote could not be built or signed.
+    Signing(String),
+    /// The hub could not be reached or returned an unreadable answer.
+    Transport(String),
+    /// The hub answered with an error, e.g. the voting period is over.
+    Rejected { status: u16, response: Value },
+}
+
+impl fmt::Display for VoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoteError::Signing(msg) => write!(f, "Vote signing error: {}", msg),
+            VoteError::Transport(msg) => write!(f, "Vote transport error: {}", msg),
+            VoteError::Rejected { status, response } => {
+                write!(f, "Vote rejected by hub ({}): {}", status, response)
+            }
+        }
+    }
+}
+
+impl Error for VoteError {}
+
+/// Builds the EIP-712 typed data Snapshot expects for a vote.
+pub fn vote_typed_data(vote: &Vote) -> Value {
+    json!({
         "primaryType": "Vote",
         "domain": {
             "name": "snapshot",
             "version": "0.1.4"
         },
         "message": {
-            "from": wallet.address().to_string(),
-            "space": "arbitrumfoundation.eth",
-            "timestamp": timestamp,
-            "proposal": proposal,
-            "choice": choice,
-            "reason": "",
-            "app": "snapshot-v2",
-            "metadata": ""
+            "from": ethers::utils::to_checksum(&vote.from, None),
+            "space": vote.space,
+            "timestamp": vote.timestamp,
+            "proposal": vote.proposal,
+            "choice": vote.choice,
+            "reason": vote.reason,
+            "app": vote.app,
+            "metadata": vote.metadata
         },
         "types": {
             "EIP712Domain": [
@@ -104,58 +138,311 @@ pub async fn vote(proposal: String, choice: u32) -> Result<(), Box<dyn std::erro
                 { "name": "metadata", "type": "string" }
             ]
         }
-    });
+    })
+}
+
+/// Builds the EIP-712 typed data Snapshot expects for a proposal.
+pub fn proposal_typed_data(proposal: &Proposal) -> Value {
+    json!({
+        "primaryType": "Proposal",
+        "domain": {
+            "name": "snapshot",
+            "version": "0.1.4"
+        },
+        "message": {
+            "from": ethers::utils::to_checksum(&proposal.from, None),
+            "space": proposal.space,
+            "timestamp": proposal.timestamp,
+            "type": proposal.r#type,
+            "title": proposal.title,
+            "body": proposal.body,
+            "discussion": proposal.discussion,
+            "choices": proposal.choices,
+            "start": proposal.start,
+            "end": proposal.end,
+            "snapshot": proposal.snapshot,
+            "plugins": proposal.plugins,
+            "app": proposal.app
+        },
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" }
+            ],
+            "Proposal": [
+                { "name": "from", "type": "address" },
+                { "name": "space", "type": "string" },
+                { "name": "timestamp", "type": "uint64" },
+                { "name": "type", "type": "string" },
+                { "name": "title", "type": "string" },
+                { "name": "body", "type": "string" },
+                { "name": "discussion", "type": "string" },
+                { "name": "choices", "type": "string[]" },
+                { "name": "start", "type": "uint64" },
+                { "name": "end", "type": "uint64" },
+                { "name": "snapshot", "type": "uint64" },
+                { "name": "plugins", "type": "string" },
+                { "name": "app", "type": "string" }
+            ]
+        }
+    })
+}
+
+/// A vote message from `from`, timestamped now.
+pub fn new_vote(from: Address, space: &str, proposal: &str, choice: u32, reason: &str) -> Vote {
+    Vote {
+        from,
+        space: space.to_string(),
+        timestamp: Utc::now().timestamp() as u64,
+        proposal: proposal.to_string(),
+        choice,
+        reason: reason.to_string(),
+        app: "snapshot-v2".to_string(),
+        metadata: "".to_string(),
+    }
+}
+
+/// Rewrites the integer fields of `value`, of EIP-712 type `type_name`, as
+/// 0x-prefixed hex: eip712_enc only reads integers in that form, while the
+/// hub sends and expects JSON numbers.
+fn hex_integers(types: &Value, type_name: &str, value: &mut Value) {
+    if let Some(item_type) = type_name.strip_suffix("[]") {
+        for item in value.as_array_mut().into_iter().flatten() {
+            hex_integers(types, item_type, item);
+        }
+    } else if type_name.starts_with("uint") || type_name.starts_with("int") {
+        if let Some(n) = value.as_u64() {
+            *value = json!(format!("0x{:x}", n));
+        }
+    } else if let Some(fields) = types.get(type_name).and_then(Value::as_array) {
+        for field in fields {
+            let (Some(name), Some(field_type)) = (field["name"].as_str(), field["type"].as_str()) else {
+                continue;
+            };
+            if let Some(field_value) = value.get_mut(name) {
+                hex_integers(types, field_type, field_value);
+            }
+        }
+    }
+}
 
+/// EIP-712 hash of the typed data built by `vote_typed_data` or `proposal_typed_data`.
+pub fn vote_hash(typed_data: &Value) -> Result<H256, VoteError> {
+    let types = typed_data["types"].clone();
+    let primary_type = typed_data["primaryType"].as_str().unwrap_or_default();
+    let mut message = typed_data["message"].clone();
+    let mut domain = typed_data["domain"].clone();
+    hex_integers(&types, primary_type, &mut message);
+    hex_integers(&types, "EIP712Domain", &mut domain);
     // Convert the JSON into an EIP712 object (from the eip712_enc crate).
-    let eip712_data: EIP712 = serde_json::from_value(vote_message)
-        .context("Failed to serialize vote message")?;
+    let eip712_data: EIP712 = serde_json::from_value(json!({
+        "types": types,
+        "primaryType": primary_type,
+        "message": message,
+        "domain": domain,
+    }))
+    .map_err(|err| VoteError::Signing(format!("Failed to read typed data: {}", err)))?;
     // Hash the structured data per EIP‑712.
-    let message_hash = hash_structured_data(eip712_data).map_err(|err| anyhow!("{err:?}"))?;
-    // Convert the hash (sp_core::H256) to ethers's H256.
-    let ethers_hash = ethers::types::H256::from(message_hash.0);
-
-    // Sign the message hash using the wallet.
-    let signature = wallet.sign_hash(ethers_hash)?;
-    println!("Generated signature: {:?}", signature);
-
-    // Build the final payload to send to Snapshot.
-    let payload = json!({
-        "address": wallet.address().to_string(),
-        "msg": {
-            "from": wallet.address().to_string(),
-            "space": "arbitrumfoundation.eth",
-            "timestamp": timestamp,
-            "proposal": proposal,
-            "choice": choice,
-            "reason": "",
-            "app": "snapshot-v2",
-            "metadata": ""
-        },
-        "sig": signature.to_string()
-    });
-
-    // Create an asynchronous HTTP client.
-    let client = Client::new();
-    // Define the Snapshot Hub endpoint (adjust if necessary).
-    let snapshot_endpoint = "https://hub.snapshot.org/api/message";
-
-    // Send the signed vote payload as JSON.
-    let response = client
-        .post(snapshot_endpoint)
-        .json(&payload)
+    let message_hash = hash_structured_data(eip712_data)
+        .map_err(|err| VoteError::Signing(format!("{err:?}")))?;
+    // Convert the hash (ethereum_types::H256) to ethers's H256.
+    Ok(H256::from(message_hash.0))
+}
+
+/// Builds and signs a vote for `proposal` in `space` with the given signer.
+pub async fn sign_vote(
+    signer: &Arc<dyn VoteSigner>,
+    space: &str,
+    proposal: &str,
+    choice: u32,
+    reason: &str,
+) -> Result<SignedVote, VoteError> {
+    let typed_data = vote_typed_data(&new_vote(signer.address(), space, proposal, choice, reason));
+    let signature = signer
+        .sign_hash(vote_hash(&typed_data)?)
+        .await
+        .map_err(|err| VoteError::Signing(err.to_string()))?;
+
+    Ok(SignedVote {
+        address: ethers::utils::to_checksum(&signer.address(), None),
+        typed_data,
+        signature: hex(signature.to_vec()),
+    })
+}
+
+/// Signs `proposal` with the given signer, which must be its author.
+pub async fn sign_proposal(
+    signer: &Arc<dyn VoteSigner>,
+    proposal: &Proposal,
+) -> Result<SignedVote, VoteError> {
+    if proposal.from != signer.address() {
+        return Err(VoteError::Signing(format!(
+            "Proposal author {:?} is not the signer {:?}",
+            proposal.from,
+            signer.address()
+        )));
+    }
+    let typed_data = proposal_typed_data(proposal);
+    let hash = vote_hash(&typed_data)?;
+    let recomputed = proposal
+        .encode_eip712()
+        .map_err(|err| VoteError::Signing(err.to_string()))?;
+    if recomputed != hash.0 {
+        return Err(VoteError::Signing(format!(
+            "EIP-712 hash mismatch: typed data {:?}, recomputed {}",
+            hash,
+            hex(recomputed)
+        )));
+    }
+    let signature = signer
+        .sign_hash(hash)
+        .await
+        .map_err(|err| VoteError::Signing(err.to_string()))?;
+
+    Ok(SignedVote {
+        address: ethers::utils::to_checksum(&signer.address(), None),
+        typed_data,
+        signature: hex(signature.to_vec()),
+    })
+}
+
+/// Validates a signed vote without sending it: the EIP-712 hash is computed a
+/// second time from the `Vote` type and must match the one that was signed,
+/// and the signature must recover to `expected_signer`.
+///
+/// Returns the evidence stored with a simulated vote.
+pub fn simulate_vote(signed_vote: &SignedVote, expected_signer: Address) -> Result<Value, VoteError> {
+    let hash = vote_hash(&signed_vote.typed_data)?;
+    let vote: Vote = serde_json::from_value(signed_vote.typed_data["message"].clone())
+        .map_err(|err| VoteError::Signing(format!("Unreadable vote message: {}", err)))?;
+    let recomputed = vote
+        .encode_eip712()
+        .map_err(|err| VoteError::Signing(err.to_string()))?;
+    if recomputed != hash.0 {
+        return Err(VoteError::Signing(format!(
+            "EIP-712 hash mismatch: signed {:?}, recomputed {}",
+            hash,
+            hex(recomputed)
+        )));
+    }
+
+    let signature: Signature = signed_vote
+        .signature
+        .parse()
+        .map_err(|err| VoteError::Signing(format!("Unreadable signature: {}", err)))?;
+    let recovered = signature
+        .recover(RecoveryMessage::Hash(hash))
+        .map_err(|err| VoteError::Signing(err.to_string()))?;
+    if recovered != expected_signer {
+        return Err(VoteError::Signing(format!(
+            "Signature recovers to {:?} instead of {:?}",
+            recovered, expected_signer
+        )));
+    }
+
+    Ok(json!({
+        "voteHash": format!("{:?}", hash),
+        "recoveredSigner": ethers::utils::to_checksum(&recovered, None),
+        "payload": signed_vote.payload(),
+    }))
+}
+
+/// Sends a signed vote or proposal to the Snapshot hub at `hub_message_url`.
+///
+/// Returns the hub's receipt, or `VoteError::Rejected` carrying the hub's
+/// answer when the message was not accepted.
+pub async fn submit_vote(
+    http: &HttpClient,
+    hub_message_url: &str,
+    signed_vote: &SignedVote,
+) -> Result<VoteReceipt, VoteError> {
+    let response = http
+        .post(hub_message_url)
+        .json(&signed_vote.payload())
         .send()
-        .await?;
-
-    // Check for a successful response.
-    if response.status().is_success() {
-        let resp_text = response.text().await?;
-        println!("Vote successfully submitted to Snapshot: {}", resp_text);
-    } else {
-        eprintln!(
-            "Error submitting vote: {}",
-            response.text().await.unwrap_or_default()
-        );
+        .await
+        .map_err(|err| VoteError::Transport(err.to_string()))?;
+
+    let status = response.status();
+    let resp_text = response
+        .text()
+        .await
+        .map_err(|err| VoteError::Transport(err.to_string()))?;
+    // The hub answers with JSON, but keep the raw text if it does not.
+    let resp_json: Value = serde_json::from_str(&resp_text).unwrap_or(Value::String(resp_text));
+
+    if !status.is_success() {
+        return Err(VoteError::Rejected {
+            status: status.as_u16(),
+            response: resp_json,
+        });
+    }
+
+    serde_json::from_value(resp_json.clone()).map_err(|err| {
+        VoteError::Transport(format!("Unexpected hub response {}: {}", resp_json, err))
+    })
+}
+
+/// Signs and submits a vote to Snapshot with the given proposal ID and choice.
+///
+/// # Arguments
+///
+/// * `http` - The shared HTTP client.
+/// * `hub_message_url` - The hub's message endpoint, see `Config::hub_message_url`.
+/// * `signer` - The signer loaded at startup.
+/// * `space` - The Snapshot space id, e.g. `arbitrumfoundation.eth`.
+/// * `proposal` - A string representing the proposal ID.
+/// * `choice` - A u32 representing the vote option (1-based).
+/// * `reason` - Shown with the vote on Snapshot; may be empty.
+///
+/// # Returns
+///
+/// * The hub's `VoteReceipt`, or a `VoteError` if signing failed or the hub rejected the vote.
+pub async fn vote(
+    http: &HttpClient,
+    hub_message_url: &str,
+    signer: &Arc<dyn VoteSigner>,
+    space: &str,
+    proposal: &str,
+    choice: u32,
+    reason: &str,
+) -> Result<VoteReceipt, VoteError> {
+    let signed_vote = sign_vote(signer, space, proposal, choice, reason).await?;
+    submit_vote(http, hub_message_url, &signed_vote).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vote_hash_matches_the_vote_type() {
+        let vote = Vote {
+            timestamp: 1_700_000_000,
+            ..new_vote(Address::repeat_byte(0x11), "ens.eth", "0xabc", 2, "Supports the grant")
+        };
+        let hash = vote_hash(&vote_typed_data(&vote)).unwrap();
+        assert_eq!(hash.0, vote.encode_eip712().unwrap());
     }
 
-    Ok(())
-}
\ No newline at end of file
+    #[test]
+    fn vote_hash_matches_the_proposal_type() {
+        let proposal = Proposal {
+            from: Address::repeat_byte(0x22),
+            space: "ens.eth".to_string(),
+            timestamp: 1_700_000_000,
+            r#type: "single-choice".to_string(),
+            title: "Fund the grant".to_string(),
+            body: "Body".to_string(),
+            discussion: String::new(),
+            choices: vec!["For".to_string(), "Against".to_string()],
+            start: 1_700_000_100,
+            end: 1_700_600_000,
+            snapshot: 18_000_000,
+            plugins: "{}".to_string(),
+            app: "snapshot-v2".to_string(),
+        };
+        let hash = vote_hash(&proposal_typed_data(&proposal)).unwrap();
+        assert_eq!(hash.0, proposal.encode_eip712().unwrap());
+    }
+}
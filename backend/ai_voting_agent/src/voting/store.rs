@@ -0,0 +1,360 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::{error::Error, sync::Arc, sync::Mutex};
+
+use crate::{
+    proposal_snapchot::store::ProposalStore,
+    voting::{
+        repository::{
+            AwaitingVote, SubmittedVote, VoteAction, VoteExecutionEntry, CHANNEL_SNAPSHOT,
+            EXECUTION_CANCELLED, EXECUTION_EXPIRED, EXECUTION_PENDING, RECONCILE_MISSING,
+            STATUS_AWAITING_SIGNATURES, STATUS_SUBMITTED,
+        },
+        schedule::{is_vote_due, vote_due_at},
+    },
+};
+
+/// Storage of the vote audit log (`vote_actions`) and of the scheduled votes
+/// (`vote_executions`).
+#[async_trait]
+pub trait VoteStore: Send + Sync {
+    /// Appends a vote attempt to the audit log and returns its id.
+    async fn save_action(&self, action: &VoteAction) -> Result<i64, Box<dyn Error + Send + Sync>>;
+
+    async fn action(&self, id: i64) -> Result<Option<VoteAction>, Box<dyn Error + Send + Sync>>;
+
+    /// Stores where the rationale of a vote was published, or why it was not.
+    async fn save_rationale_post(&self, id: i64, post: &Value) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Safe votes still waiting for signatures, with their proposal's `end`.
+    async fn awaiting_signatures(&self) -> Result<Vec<AwaitingVote>, Box<dyn Error + Send + Sync>>;
+
+    /// Records the outcome of a vote that was waiting for Safe signatures.
+    async fn finish_awaiting(&self, id: i64, action: &VoteAction) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Submitted votes that are unchecked or missing, and older than `grace_secs`,
+    /// leaving the hub time to index them. Abandoned votes are not checked again.
+    /// Votes re-submitted or superseded by a later vote from the same signer are
+    /// skipped, since the hub only keeps the latest one.
+    async fn to_reconcile(&self, grace_secs: i64) -> Result<Vec<SubmittedVote>, Box<dyn Error + Send + Sync>>;
+
+    async fn set_reconcile_status(
+        &self,
+        id: i64,
+        reconcile_status: &str,
+        details: &Value,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// The latest vote the hub accepted for a proposal from one identity, as
+    /// `(action id, choice)`.
+    async fn last_submitted(
+        &self,
+        proposal_id: &str,
+        identity: &str,
+    ) -> Result<Option<(i64, u32)>, Box<dyn Error + Send + Sync>>;
+
+    /// Schedules a vote, replacing the pending one for the same proposal and
+    /// identity if any. Returns the execution id.
+    async fn schedule(&self, entry: &VoteExecutionEntry) -> Result<i64, Box<dyn Error + Send + Sync>>;
+
+    /// Pending executions due as computed by `vote_due_at` against the
+    /// proposal's current `end`, so edits to the deadline are followed.
+    /// Proposals the hub reports closed are never due.
+    async fn due_executions(&self, lead_secs: i64) -> Result<Vec<VoteExecutionEntry>, Box<dyn Error + Send + Sync>>;
+
+    /// Marks pending executions whose proposal already closed, by its `end` or
+    /// its `closed` state, as expired and returns their proposal ids.
+    async fn expire_executions(&self) -> Result<Vec<String>, Box<dyn Error + Send + Sync>>;
+
+    /// Cancels pending executions of the given proposals and returns how many were cancelled.
+    async fn cancel_pending(&self, proposal_ids: &[String]) -> Result<u64, Box<dyn Error + Send + Sync>>;
+
+    async fn finish_execution(
+        &self,
+        id: i64,
+        status: &str,
+        vote_action_id: Option<i64>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+#[derive(Debug, Clone)]
+struct StoredAction {
+    id: i64,
+    action: VoteAction,
+    created_at: i64,
+    reconcile_status: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct StoredExecution {
+    entry: VoteExecutionEntry,
+    status: String,
+    created_at: i64,
+}
+
+/// Votes kept in memory, for tests and local runs without a database.
+/// Proposal deadlines are read from `proposals`. Rationale posts,
+/// reconciliation details and the votes executions led to are only served
+/// by the API and are not kept.
+pub struct MemoryVoteStore {
+    proposals: Arc<dyn ProposalStore>,
+    actions: Mutex<Vec<StoredAction>>,
+    executions: Mutex<Vec<StoredExecution>>,
+}
+
+impl MemoryVoteStore {
+    pub fn new(proposals: Arc<dyn ProposalStore>) -> Self {
+        MemoryVoteStore {
+            proposals,
+            actions: Mutex::new(Vec::new()),
+            executions: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn pending(&self) -> Vec<StoredExecution> {
+        self.executions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| e.status == EXECUTION_PENDING)
+            .cloned()
+            .collect()
+    }
+
+    /// `(start, end)` of a proposal, `None` when it is unknown. Given `now`, a
+    /// proposal the hub reports closed ends at `now` at the latest.
+    async fn voting_period(
+        &self,
+        proposal_id: &str,
+        now: Option<i64>,
+    ) -> Result<Option<(i64, i64)>, Box<dyn Error + Send + Sync>> {
+        Ok(self.proposals.get(proposal_id).await?.and_then(|p| {
+            let end = match (p.state.as_deref(), now) {
+                (Some("closed"), Some(now)) => p.end?.min(now),
+                _ => p.end?,
+            };
+            Some((p.start?, end))
+        }))
+    }
+}
+
+#[async_trait]
+impl VoteStore for MemoryVoteStore {
+    async fn save_action(&self, action: &VoteAction) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let mut actions = self.actions.lock().unwrap();
+        let id = actions.iter().map(|a| a.id).max().unwrap_or(0) + 1;
+        actions.push(StoredAction {
+            id,
+            action: action.clone(),
+            created_at: chrono::Utc::now().timestamp(),
+            reconcile_status: None,
+        });
+        Ok(id)
+    }
+
+    async fn action(&self, id: i64) -> Result<Option<VoteAction>, Box<dyn Error + Send + Sync>> {
+        Ok(self
+            .actions
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|a| a.id == id)
+            .map(|a| a.action.clone()))
+    }
+
+    async fn save_rationale_post(&self, _id: i64, _post: &Value) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(())
+    }
+
+    async fn awaiting_signatures(&self) -> Result<Vec<AwaitingVote>, Box<dyn Error + Send + Sync>> {
+        let awaiting: Vec<StoredAction> = self
+            .actions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|a| a.action.status == STATUS_AWAITING_SIGNATURES)
+            .cloned()
+            .collect();
+        let mut votes = Vec::with_capacity(awaiting.len());
+        for stored in awaiting {
+            let proposal_end = self
+                .voting_period(&stored.action.proposal_id, None)
+                .await?
+                .map_or(0, |(_, end)| end);
+            votes.push(AwaitingVote {
+                id: stored.id,
+                action: stored.action,
+                proposal_end,
+            });
+        }
+        Ok(votes)
+    }
+
+    async fn finish_awaiting(&self, id: i64, action: &VoteAction) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut actions = self.actions.lock().unwrap();
+        if let Some(stored) = actions
+            .iter_mut()
+            .find(|a| a.id == id && a.action.status == STATUS_AWAITING_SIGNATURES)
+        {
+            stored.action.status = action.status.clone();
+            stored.action.signature = action.signature.clone();
+            stored.action.hub_response = action.hub_response.clone();
+        }
+        Ok(())
+    }
+
+    async fn to_reconcile(&self, grace_secs: i64) -> Result<Vec<SubmittedVote>, Box<dyn Error + Send + Sync>> {
+        let before = chrono::Utc::now().timestamp() - grace_secs;
+        let actions = self.actions.lock().unwrap();
+        let submitted = |a: &StoredAction| a.action.status == STATUS_SUBMITTED;
+        Ok(actions
+            .iter()
+            .filter(|a| {
+                submitted(a)
+                    && a.action.channel == CHANNEL_SNAPSHOT
+                    && a.action.signer_address.is_some()
+                    && a.reconcile_status.as_deref().is_none_or(|s| s == RECONCILE_MISSING)
+                    && a.created_at <= before
+                    && !actions.iter().any(|r| r.action.resubmission_of == Some(a.id))
+                    && !actions.iter().any(|n| {
+                        submitted(n)
+                            && n.id > a.id
+                            && n.action.proposal_id == a.action.proposal_id
+                            && n.action.signer_address == a.action.signer_address
+                    })
+            })
+            .map(|a| SubmittedVote {
+                id: a.id,
+                proposal_id: a.action.proposal_id.clone(),
+                identity: a.action.identity.clone(),
+                choice: a.action.choice,
+                signer_address: a.action.signer_address.clone().unwrap_or_default(),
+                signature: a.action.signature.clone(),
+                hub_response: a.action.hub_response.clone(),
+                approval: a.action.approval.clone(),
+                attempt: a.action.attempt,
+                reconcile_status: a.reconcile_status.clone(),
+            })
+            .collect())
+    }
+
+    async fn set_reconcile_status(
+        &self,
+        id: i64,
+        reconcile_status: &str,
+        _details: &Value,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if let Some(stored) = self.actions.lock().unwrap().iter_mut().find(|a| a.id == id) {
+            stored.reconcile_status = Some(reconcile_status.to_string());
+        }
+        Ok(())
+    }
+
+    async fn last_submitted(
+        &self,
+        proposal_id: &str,
+        identity: &str,
+    ) -> Result<Option<(i64, u32)>, Box<dyn Error + Send + Sync>> {
+        Ok(self
+            .actions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|a| {
+                a.action.proposal_id == proposal_id
+                    && a.action.identity == identity
+                    && a.action.status == STATUS_SUBMITTED
+            })
+            .max_by_key(|a| (a.created_at, a.id))
+            .map(|a| (a.id, a.action.choice)))
+    }
+
+    async fn schedule(&self, entry: &VoteExecutionEntry) -> Result<i64, Box<dyn Error + Send + Sync>> {
+        let mut executions = self.executions.lock().unwrap();
+        let created_at = chrono::Utc::now().timestamp();
+        if let Some(pending) = executions.iter_mut().find(|e| {
+            e.status == EXECUTION_PENDING
+                && e.entry.proposal_id == entry.proposal_id
+                && e.entry.identity == entry.identity
+        }) {
+            pending.entry = VoteExecutionEntry {
+                id: pending.entry.id,
+                ..entry.clone()
+            };
+            pending.created_at = created_at;
+            return Ok(pending.entry.id);
+        }
+        let id = executions.iter().map(|e| e.entry.id).max().unwrap_or(0) + 1;
+        executions.push(StoredExecution {
+            entry: VoteExecutionEntry { id, ..entry.clone() },
+            status: EXECUTION_PENDING.to_string(),
+            created_at,
+        });
+        Ok(id)
+    }
+
+    async fn due_executions(&self, lead_secs: i64) -> Result<Vec<VoteExecutionEntry>, Box<dyn Error + Send + Sync>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut due = Vec::new();
+        for execution in self.pending() {
+            let Some((start, end)) = self.voting_period(&execution.entry.proposal_id, Some(now)).await? else {
+                continue;
+            };
+            let due_at = vote_due_at(
+                execution.created_at,
+                end,
+                execution.entry.window_secs,
+                execution.entry.jitter_secs,
+                lead_secs,
+            );
+            if is_vote_due(now, start, end, due_at) {
+                due.push((end, execution.entry));
+            }
+        }
+        due.sort_by_key(|(end, _)| *end);
+        Ok(due.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    async fn expire_executions(&self) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let now = chrono::Utc::now().timestamp();
+        let mut closed = Vec::new();
+        for execution in self.pending() {
+            if let Some((_, end)) = self.voting_period(&execution.entry.proposal_id, Some(now)).await? {
+                if end <= now {
+                    closed.push(execution.entry.id);
+                }
+            }
+        }
+        let mut expired = Vec::new();
+        for execution in self.executions.lock().unwrap().iter_mut() {
+            if execution.status == EXECUTION_PENDING && closed.contains(&execution.entry.id) {
+                execution.status = EXECUTION_EXPIRED.to_string();
+                expired.push(execution.entry.proposal_id.clone());
+            }
+        }
+        Ok(expired)
+    }
+
+    async fn cancel_pending(&self, proposal_ids: &[String]) -> Result<u64, Box<dyn Error + Send + Sync>> {
+        let mut cancelled = 0;
+        for execution in self.executions.lock().unwrap().iter_mut() {
+            if execution.status == EXECUTION_PENDING && proposal_ids.contains(&execution.entry.proposal_id) {
+                execution.status = EXECUTION_CANCELLED.to_string();
+                cancelled += 1;
+            }
+        }
+        Ok(cancelled)
+    }
+
+    async fn finish_execution(
+        &self,
+        id: i64,
+        status: &str,
+        _vote_action_id: Option<i64>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if let Some(execution) = self.executions.lock().unwrap().iter_mut().find(|e| e.entry.id == id) {
+            execution.status = status.to_string();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,2 @@
+pub mod power;
+pub mod repository;
\ No newline at end of file
@@ -0,0 +1,415 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use ethers::prelude::*;
+use ethers::utils::format_units;
+use reqwest::Client as HttpClient;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{collections::HashSet, error::Error, str::FromStr, sync::Arc};
+use tokio_postgres::NoTls;
+
+use crate::{
+    config::config::Config,
+    voting_power::repository::{
+        get_delegator_scan, get_delegator_scan_start, get_stored_voting_power, save_delegator_scan,
+        save_voting_power, DelegatorScan,
+    },
+    contracts::contracts::{
+        http_provider, space_id_bytes32, DelegateRegistry, Erc20Votes, Governor, HttpProvider,
+        DELEGATE_REGISTRY_ADDRESS,
+    },
+};
+
+pub const SOURCE_SCORE_API: &str = "score-api";
+pub const SOURCE_RPC: &str = "rpc";
+pub const SOURCE_GOVERNOR: &str = "governor";
+
+/// Blocks per `eth_getLogs` request when scanning for delegations; RPC
+/// providers refuse or time out on unbounded ranges.
+const LOG_CHUNK_BLOCKS: u64 = 50_000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VotingPower {
+    pub address: String,
+    pub vp: f64,
+    pub vp_by_strategy: Vec<f64>,
+    /// Where the power was computed: `score-api`, `rpc` or `governor`.
+    pub source: String,
+    /// Block (or governor timepoint) the power was evaluated at.
+    pub snapshot: Option<String>,
+}
+
+/// Computes our voting power on a Snapshot proposal at its `snapshot` block.
+///
+/// `erc20-votes`, `erc20-balance-of` and `delegation` strategies are evaluated
+/// locally over RPC when an endpoint for their network is configured; anything
+/// else, or a local failure, falls back to the Snapshot score API.
+pub async fn get_snapshot_voting_power(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    http: &HttpClient,
+    address: Address,
+    proposal: &Value,
+) -> Result<VotingPower, Box<dyn Error + Send + Sync>> {
+    match local_voting_power(db_client, config, address, proposal).await {
+        Ok(Some(power)) => return Ok(power),
+        Ok(None) => {}
+        Err(e) => log::warn!(
+            "Local voting power evaluation failed for {}: {}, using the score API",
+            proposal["id"], e
+        ),
+    }
+    score_api_voting_power(config, http, address, proposal).await
+}
+
+fn snapshot_block(proposal: &Value) -> Option<u64> {
+    proposal["snapshot"].as_str().and_then(|s| s.parse().ok())
+}
+
+async fn score_api_voting_power(
+    config: &Config,
+    http: &HttpClient,
+    address: Address,
+    proposal: &Value,
+) -> Result<VotingPower, Box<dyn Error + Send + Sync>> {
+    let snapshot = snapshot_block(proposal)
+        .map(|block| json!(block))
+        .unwrap_or_else(|| json!("latest"));
+    let body = json!({
+        "jsonrpc": "2.0",
+        "method": "get_vp",
+        "params": {
+            "address": ethers::utils::to_checksum(&address, None),
+            "network": proposal["space"]["network"],
+            "strategies": proposal["strategies"],
+            "snapshot": snapshot,
+            "space": proposal["space"]["id"],
+            "delegation": false
+        }
+    });
+    let response = http
+        .post(&config.score_api_url)
+        .json(&body)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(format!("Score API failed with status: {}", response.status()).into());
+    }
+    let resp_json: Value = response.json().await?;
+    if let Some(error) = resp_json.get("error") {
+        return Err(format!("Score API error: {}", error).into());
+    }
+    let result = &resp_json["result"];
+    let vp = result["vp"].as_f64().ok_or("No vp in score API response")?;
+    let vp_by_strategy = result["vp_by_strategy"]
+        .as_array()
+        .map(|values| values.iter().filter_map(|v| v.as_f64()).collect())
+        .unwrap_or_default();
+
+    Ok(VotingPower {
+        address: ethers::utils::to_checksum(&address, None),
+        vp,
+        vp_by_strategy,
+        source: SOURCE_SCORE_API.to_string(),
+        snapshot: snapshot_block(proposal).map(|block| block.to_string()),
+    })
+}
+
+/// Evaluates the proposal's strategies over RPC. Returns `None` when one of
+/// them is not supported locally or has no RPC endpoint configured.
+async fn local_voting_power(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    address: Address,
+    proposal: &Value,
+) -> Result<Option<VotingPower>, Box<dyn Error + Send + Sync>> {
+    let Some(strategies) = proposal["strategies"].as_array() else {
+        return Ok(None);
+    };
+    let space_network = proposal["space"]["network"].as_str().unwrap_or("1");
+    let space_id = proposal["space"]["id"].as_str().unwrap_or_default();
+    let block = snapshot_block(proposal);
+
+    let mut vp_by_strategy = Vec::with_capacity(strategies.len());
+    for strategy in strategies {
+        let network = strategy["network"].as_str().unwrap_or(space_network);
+        let Some(rpc_url) = config.rpc_url(network) else {
+            return Ok(None);
+        };
+        let provider = http_provider(rpc_url)?;
+        let power = match strategy["name"].as_str() {
+            Some("delegation") => {
+                delegation_power(db_client, &provider, network, strategy, space_id, address, block)
+                    .await?
+            }
+            _ => token_power(&provider, strategy, &[address], block).await?,
+        };
+        match power {
+            Some(power) => vp_by_strategy.push(power),
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(VotingPower {
+        address: ethers::utils::to_checksum(&address, None),
+        vp: vp_by_strategy.iter().sum(),
+        vp_by_strategy,
+        source: SOURCE_RPC.to_string(),
+        snapshot: block.map(|block| block.to_string()),
+    }))
+}
+
+/// Sums an `erc20-votes` or `erc20-balance-of` strategy over `accounts`.
+async fn token_power(
+    provider: &HttpProvider,
+    strategy: &Value,
+    accounts: &[Address],
+    block: Option<u64>,
+) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+    let name = strategy["name"].as_str().unwrap_or_default();
+    if name != "erc20-votes" && name != "erc20-balance-of" {
+        return Ok(None);
+    }
+    let params = &strategy["params"];
+    let token_address = params["address"].as_str().ok_or("Strategy has no token address")?;
+    let token = Erc20Votes::new(Address::from_str(token_address)?, provider.clone());
+    let block_id = block.map(BlockId::from);
+
+    let decimals = match params["decimals"].as_u64() {
+        Some(decimals) => decimals as u32,
+        None => token.decimals().call().await? as u32,
+    };
+
+    let mut total = 0.0;
+    for account in accounts {
+        let call = match name {
+            "erc20-votes" => token.get_votes(*account),
+            _ => token.balance_of(*account),
+        };
+        let call = match block_id {
+            Some(block_id) => call.block(block_id),
+            None => call,
+        };
+        let raw = call.call().await?;
+        total += format_units(raw, decimals)?.parse::<f64>()?;
+    }
+
+    Ok(Some(total))
+}
+
+/// Evaluates a `delegation` strategy: the inner strategies summed over every
+/// account that delegated to `address` in the DelegateRegistry, either for this
+/// space or globally, as of `block`.
+///
+/// Delegators are found with `find_delegators`, which only scans the blocks not
+/// scanned before for this space and delegate on `network`.
+async fn delegation_power(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    provider: &HttpProvider,
+    network: &str,
+    strategy: &Value,
+    space_id: &str,
+    address: Address,
+    block: Option<u64>,
+) -> Result<Option<f64>, Box<dyn Error + Send + Sync>> {
+    let Some(inner_strategies) = strategy["params"]["strategies"].as_array() else {
+        return Ok(None);
+    };
+    let delegators = find_delegators(db_client, provider, network, space_id, address, block).await?;
+    if delegators.is_empty() {
+        return Ok(Some(0.0));
+    }
+
+    let mut total = 0.0;
+    for inner in inner_strategies {
+        match token_power(provider, inner, &delegators, block).await? {
+            Some(power) => total += power,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(total))
+}
+
+/// Inclusive block ranges of at most `size` blocks covering `from..=to`.
+fn block_chunks(from: u64, to: u64, size: u64) -> impl Iterator<Item = (u64, u64)> {
+    (from..=to)
+        .step_by(size as usize)
+        .map(move |start| (start, start.saturating_add(size - 1).min(to)))
+}
+
+/// First block the DelegateRegistry has code at on the provider's chain, found
+/// by bisecting `eth_getCode` up to `last_block`. Falls back to genesis when
+/// the node does not serve historical state.
+async fn registry_deployment_block(provider: &HttpProvider, registry: Address, last_block: u64) -> u64 {
+    let (mut low, mut high) = (0, last_block);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        match provider.get_code(registry, Some(BlockId::from(mid))).await {
+            Ok(code) if !code.is_empty() => high = mid,
+            Ok(_) => low = mid + 1,
+            Err(e) => {
+                log::warn!("Could not locate the DelegateRegistry deployment: {}, scanning from genesis", e);
+                return 0;
+            }
+        }
+    }
+    low
+}
+
+/// Accounts whose effective Snapshot delegation for `space_id` points to `delegate`
+/// as of `block`, or the latest block.
+///
+/// Accounts that ever delegated to `delegate` are kept in `delegator_scans` per
+/// network, space and delegate with the last block scanned, so only newer
+/// `SetDelegate` logs are fetched, `LOG_CHUNK_BLOCKS` blocks per request. The
+/// first scan starts at the registry deployment block.
+pub async fn find_delegators(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    provider: &HttpProvider,
+    network: &str,
+    space_id: &str,
+    delegate: Address,
+    block: Option<u64>,
+) -> Result<Vec<Address>, Box<dyn Error + Send + Sync>> {
+    let registry_address = Address::from_str(DELEGATE_REGISTRY_ADDRESS)?;
+    let registry = DelegateRegistry::new(registry_address, provider.clone());
+    let space_key = space_id_bytes32(space_id)?;
+    let global_key = [0u8; 32];
+    let delegate_key = ethers::utils::to_checksum(&delegate, None);
+
+    let last_block = match block {
+        Some(block) => block,
+        None => provider.get_block_number().await?.as_u64(),
+    };
+    let (start_block, scan_from, mut known) =
+        match get_delegator_scan(db_client, network, space_id, &delegate_key).await? {
+            Some(scan) => (scan.start_block, scan.scanned_to + 1, scan.delegators),
+            None => {
+                let start_block = match get_delegator_scan_start(db_client, network).await? {
+                    Some(start_block) => start_block,
+                    None => registry_deployment_block(provider, registry_address, last_block).await,
+                };
+                (start_block, start_block, Vec::new())
+            }
+        };
+
+    for (from, to) in block_chunks(scan_from, last_block, LOG_CHUNK_BLOCKS) {
+        let events = registry
+            .set_delegate_filter()
+            .topic3(delegate)
+            .from_block(from)
+            .to_block(to)
+            .query()
+            .await?;
+        let found: Vec<String> = events
+            .into_iter()
+            .filter(|event| event.id == space_key || event.id == global_key)
+            .map(|event| ethers::utils::to_checksum(&event.delegator, None))
+            .collect();
+        // Saved per chunk so an interrupted first scan resumes where it stopped.
+        let scan = DelegatorScan { start_block, scanned_to: to, delegators: found };
+        save_delegator_scan(db_client, network, space_id, &delegate_key, &scan).await?;
+        known.extend(scan.delegators);
+    }
+
+    // Stored delegators may have moved their delegation since, or only
+    // delegated after `block`; the registry at `block` decides.
+    let candidates: HashSet<Address> = known
+        .iter()
+        .filter_map(|delegator| Address::from_str(delegator).ok())
+        .collect();
+
+    let block_id = block.map(BlockId::from);
+    let mut delegators = Vec::new();
+    for delegator in candidates {
+        // A space delegation takes precedence over the global one.
+        let mut current = Address::zero();
+        for key in [space_key, global_key] {
+            let call = registry.delegation(delegator, key);
+            let call = match block_id {
+                Some(block_id) => call.block(block_id),
+                None => call,
+            };
+            current = call.call().await?;
+            if current != Address::zero() {
+                break;
+            }
+        }
+        if current == delegate {
+            delegators.push(delegator);
+        }
+    }
+    Ok(delegators)
+}
+
+/// Our voting power on an on-chain Governor proposal on chain `network`, read
+/// with `getVotes` at the proposal's snapshot timepoint and scaled by the
+/// decimals of the governor's voting token.
+pub async fn get_governor_voting_power(
+    config: &Config,
+    network: &str,
+    governor_address: &str,
+    address: Address,
+    proposal_id: U256,
+) -> Result<VotingPower, Box<dyn Error + Send + Sync>> {
+    let rpc_url = config
+        .rpc_url(network)
+        .ok_or_else(|| format!("No RPC URL configured for chain {}", network))?;
+    let provider = http_provider(rpc_url)?;
+    let governor = Governor::new(Address::from_str(governor_address)?, provider.clone());
+    let token = Erc20Votes::new(governor.token().call().await?, provider);
+    let decimals = token.decimals().call().await? as u32;
+    let timepoint = governor.proposal_snapshot(proposal_id).call().await?;
+    let votes = governor.get_votes(address, timepoint).call().await?;
+    let vp = format_units(votes, decimals)?.parse::<f64>()?;
+
+    Ok(VotingPower {
+        address: ethers::utils::to_checksum(&address, None),
+        vp,
+        vp_by_strategy: vec![vp],
+        source: SOURCE_GOVERNOR.to_string(),
+        snapshot: Some(timepoint.to_string()),
+    })
+}
+
+/// Returns the stored voting power of `address` on a Snapshot proposal,
+/// computing and storing it first when missing or when `refresh` is set.
+pub async fn ensure_voting_power(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    config: &Config,
+    http: &HttpClient,
+    address: Address,
+    proposal: &Value,
+    refresh: bool,
+) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let proposal_id = proposal["id"].as_str().ok_or("Proposal has no id")?.to_string();
+    let checksummed = ethers::utils::to_checksum(&address, None);
+
+    if !refresh {
+        if let Some(stored) = get_stored_voting_power(db_client, &proposal_id, &checksummed).await? {
+            return Ok(stored);
+        }
+    }
+    let power = get_snapshot_voting_power(db_client, config, http, address, proposal).await?;
+    save_voting_power(db_client, &proposal_id, &power).await?;
+    get_stored_voting_power(db_client, &proposal_id, &checksummed)
+        .await?
+        .ok_or_else(|| "Voting power was not stored".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_chunks_cover_the_range_once() {
+        let chunks: Vec<_> = block_chunks(0, 120_000, LOG_CHUNK_BLOCKS).collect();
+        assert_eq!(chunks, vec![(0, 49_999), (50_000, 99_999), (100_000, 120_000)]);
+        assert_eq!(block_chunks(0, 0, LOG_CHUNK_BLOCKS).collect::<Vec<_>>(), vec![(0, 0)]);
+        assert_eq!(
+            block_chunks(0, 99_999, LOG_CHUNK_BLOCKS).collect::<Vec<_>>(),
+            vec![(0, 49_999), (50_000, 99_999)]
+        );
+    }
+}
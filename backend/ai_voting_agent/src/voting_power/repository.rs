@@ -0,0 +1,155 @@
+use bb8::Pool;
+use bb8_postgres::PostgresConnectionManager;
+use serde_json::{json, Value};
+use std::{error::Error, sync::Arc};
+use tokio_postgres::{types::Json, NoTls, Row};
+
+use crate::voting_power::power::VotingPower;
+
+pub async fn save_voting_power(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: &String,
+    power: &VotingPower,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    conn.execute(
+        r#"
+        INSERT INTO voting_power (proposal_id, address, vp, vp_by_strategy, source, snapshot)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (proposal_id, address) DO UPDATE SET
+            vp = EXCLUDED.vp,
+            vp_by_strategy = EXCLUDED.vp_by_strategy,
+            source = EXCLUDED.source,
+            snapshot = EXCLUDED.snapshot,
+            computed_at = NOW()
+        "#,
+        &[
+            proposal_id,
+            &power.address,
+            &power.vp,
+            &Json(&power.vp_by_strategy),
+            &power.source,
+            &power.snapshot,
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+/// The stored voting power of `address` on a proposal, if it was computed before.
+pub async fn get_stored_voting_power(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    proposal_id: &String,
+    address: &String,
+) -> Result<Option<Value>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_opt(
+            r#"
+            SELECT proposal_id, address, vp, vp_by_strategy, source, snapshot,
+                   (EXTRACT(EPOCH FROM computed_at))::int8 AS computed_at
+            FROM voting_power
+            WHERE proposal_id = $1 AND address = $2
+            "#,
+            &[proposal_id, address],
+        )
+        .await?;
+
+    Ok(row.map(|row| row_to_voting_power(&row)))
+}
+
+fn row_to_voting_power(row: &Row) -> Value {
+    json!({
+        "proposalId": row.get::<_, String>("proposal_id"),
+        "address": row.get::<_, String>("address"),
+        "vp": row.get::<_, f64>("vp"),
+        "vpByStrategy": row.get::<_, Value>("vp_by_strategy"),
+        "source": row.get::<_, String>("source"),
+        "snapshot": row.get::<_, Option<String>>("snapshot"),
+        "computedAt": row.get::<_, i64>("computed_at"),
+    })
+}
+
+/// Delegators found so far for `delegate` in `space` on `network`, the block
+/// the scan started at and the last block scanned.
+pub struct DelegatorScan {
+    pub start_block: u64,
+    pub scanned_to: u64,
+    pub delegators: Vec<String>,
+}
+
+pub async fn get_delegator_scan(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    network: &str,
+    space: &str,
+    delegate: &str,
+) -> Result<Option<DelegatorScan>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_opt(
+            r#"
+            SELECT start_block, scanned_to, delegators
+            FROM delegator_scans
+            WHERE network = $1 AND space = $2 AND delegate = $3
+            "#,
+            &[&network, &space, &delegate],
+        )
+        .await?;
+
+    Ok(row.map(|row| DelegatorScan {
+        start_block: row.get::<_, i64>("start_block") as u64,
+        scanned_to: row.get::<_, i64>("scanned_to") as u64,
+        delegators: row.get("delegators"),
+    }))
+}
+
+/// Lowest block any scan on `network` started at, i.e. the DelegateRegistry
+/// deployment block once it has been looked up.
+pub async fn get_delegator_scan_start(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    network: &str,
+) -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    let row = conn
+        .query_one(
+            "SELECT MIN(start_block) AS start_block FROM delegator_scans WHERE network = $1",
+            &[&network],
+        )
+        .await?;
+    Ok(row.get::<_, Option<i64>>("start_block").map(|block| block as u64))
+}
+
+/// Adds `delegators` to the stored set and moves `scanned_to` forward; the
+/// scanned range never goes back when two scans overlap.
+pub async fn save_delegator_scan(
+    db_client: &Arc<Pool<PostgresConnectionManager<NoTls>>>,
+    network: &str,
+    space: &str,
+    delegate: &str,
+    scan: &DelegatorScan,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let conn = db_client.get().await?;
+    conn.execute(
+        r#"
+        INSERT INTO delegator_scans (network, space, delegate, start_block, scanned_to, delegators)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (network, space, delegate) DO UPDATE SET
+            start_block = LEAST(delegator_scans.start_block, EXCLUDED.start_block),
+            scanned_to = GREATEST(delegator_scans.scanned_to, EXCLUDED.scanned_to),
+            delegators = ARRAY(
+                SELECT DISTINCT unnest(delegator_scans.delegators || EXCLUDED.delegators)
+            ),
+            updated_at = NOW()
+        "#,
+        &[
+            &network,
+            &space,
+            &delegate,
+            &(scan.start_block as i64),
+            &(scan.scanned_to as i64),
+            &scan.delegators,
+        ],
+    )
+    .await?;
+    Ok(())
+}
@@ -25,10 +25,12 @@ export type Proposal = {
   title?: string;
   body?: string;
   author?: string;
-  quorum: string;
+  quorum: number;
   start?: number;
   end?: number;
   choices?: any;
+  scores_total: number;
+  votes: number;
 };
 
 export async function fetchProposals(space_id: string): Promise<Proposal[]> {